@@ -0,0 +1,32 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use telegrama_rs::formatter::Formatter;
+
+const PLAIN: &str = "Deployment finished successfully on all hosts";
+const ALERT: &str = "*CPU alert* on `web-1`: load is 95.5% (threshold 80%)! \
+                     Contact admin@example.com or see [runbook](https://example.com/runbook_cpu).";
+
+fn escape_markdown_v2(c: &mut Criterion) {
+    c.bench_function("escape_markdown_v2/plain", |b| {
+        b.iter(|| Formatter::escape_markdown_v2(black_box(PLAIN)))
+    });
+    c.bench_function("escape_markdown_v2/alert", |b| {
+        b.iter(|| Formatter::escape_markdown_v2(black_box(ALERT)))
+    });
+
+    let long = ALERT.repeat(50);
+    c.bench_function("escape_markdown_v2/long", |b| {
+        b.iter(|| Formatter::escape_markdown_v2(black_box(&long)))
+    });
+}
+
+fn escape_html(c: &mut Criterion) {
+    c.bench_function("escape_html/plain", |b| {
+        b.iter(|| Formatter::escape_html(black_box(PLAIN)))
+    });
+    c.bench_function("escape_html/markup", |b| {
+        b.iter(|| Formatter::escape_html(black_box("<b>Disk</b> usage > 90% & rising")))
+    });
+}
+
+criterion_group!(benches, escape_markdown_v2, escape_html);
+criterion_main!(benches);
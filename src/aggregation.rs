@@ -0,0 +1,209 @@
+//! Merging of messages that share an aggregation key into one summary.
+//!
+//! Messages sent with an `aggregation_key` option are grouped per bot, chat and
+//! key. The first message of a group is sent as usual; later ones within the
+//! window edit it into a summary with the number of occurrences and the time of
+//! the last one, e.g. `Payment gateway timeout` followed by
+//! `×12, last at 14:03:27 UTC`.
+//!
+//! Windows are configured per key pattern with
+//! [`Configuration::add_aggregation`](crate::Configuration::add_aggregation);
+//! keys matching no rule use [`DEFAULT_WINDOW`]:
+//!
+//! ```
+//! use std::time::Duration;
+//! use telegrama_rs::Telegrama;
+//!
+//! Telegrama::configure(|config| {
+//!     config
+//!         .add_aggregation(r"^timeout:", Duration::from_secs(600))
+//!         .unwrap();
+//!     // Never aggregate audit messages
+//!     config.add_aggregation(r"^audit:", Duration::ZERO).unwrap();
+//! });
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::ParseMode;
+use crate::error::Error;
+use crate::time::Instant;
+
+/// Window of keys that match no [`AggregationRule`]
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Aggregation window of the keys matching a pattern
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RuleFile", into = "RuleFile")]
+pub struct AggregationRule {
+    pattern: Regex,
+    window: Duration,
+}
+
+/// Serialized form of an [`AggregationRule`], with the window in seconds
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    pattern: String,
+    window: u64,
+}
+
+impl AggregationRule {
+    /// Rule aggregating keys matching the regular expression `pattern` for `window`
+    ///
+    /// A zero window disables aggregation of the matching keys.
+    pub fn new(pattern: &str, window: Duration) -> Result<Self, Error> {
+        let pattern = Regex::new(pattern).map_err(|e| {
+            Error::configuration(format!("Invalid aggregation pattern '{}': {}", pattern, e))
+        })?;
+        Ok(AggregationRule { pattern, window })
+    }
+
+    /// Regular expression matching the aggregation keys
+    pub fn pattern(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    /// How long after the first message later ones are merged into it
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Whether the rule applies to `key`
+    pub fn matches(&self, key: &str) -> bool {
+        self.pattern.is_match(key)
+    }
+}
+
+impl TryFrom<RuleFile> for AggregationRule {
+    type Error = Error;
+
+    fn try_from(rule: RuleFile) -> Result<Self, Self::Error> {
+        AggregationRule::new(&rule.pattern, Duration::from_secs(rule.window))
+    }
+}
+
+impl From<AggregationRule> for RuleFile {
+    fn from(rule: AggregationRule) -> Self {
+        RuleFile {
+            pattern: rule.pattern.as_str().to_string(),
+            window: rule.window.as_secs(),
+        }
+    }
+}
+
+impl fmt::Debug for AggregationRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AggregationRule")
+            .field("pattern", &self.pattern.as_str())
+            .field("window", &self.window)
+            .finish()
+    }
+}
+
+impl PartialEq for AggregationRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.window == other.window
+    }
+}
+
+/// Window of `key` under the first matching rule
+pub(crate) fn window(rules: &[AggregationRule], key: &str) -> Duration {
+    rules
+        .iter()
+        .find(|rule| rule.matches(key))
+        .map_or(DEFAULT_WINDOW, AggregationRule::window)
+}
+
+/// The first message of a group and how often the key occurred since
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Aggregate {
+    pub(crate) message_id: i64,
+    /// Formatted text of the first message, as sent
+    pub(crate) text: String,
+    pub(crate) parse_mode: ParseMode,
+    /// Messages in the group, the first one included
+    pub(crate) count: u32,
+}
+
+impl Aggregate {
+    /// Text of the first message followed by the count and the time of the last one
+    pub(crate) fn summary(&self) -> String {
+        // No characters reserved by any parse mode, so nothing to escape
+        format!(
+            "{}\n\n×{}, last at {}",
+            self.text,
+            self.count,
+            Utc::now().format("%H:%M:%S UTC")
+        )
+    }
+}
+
+struct Group {
+    expires_at: Instant,
+    aggregate: Aggregate,
+}
+
+/// Open groups by [`group_key`]
+static GROUPS: Mutex<Option<HashMap<u64, Group>>> = Mutex::new(None);
+
+/// Group of an aggregation key, scoped to the bot and chat
+pub(crate) fn group_key(bot_token: &str, chat_id: &str, key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (bot_token, chat_id, key).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Add a message to an open group, returning the group with the new count
+///
+/// `None` if the group has no first message yet, or its window is over.
+pub(crate) fn join(group: u64) -> Option<Aggregate> {
+    let now = Instant::now();
+    let mut groups = GROUPS.lock().unwrap_or_else(PoisonError::into_inner);
+    let groups = groups.get_or_insert_with(HashMap::new);
+
+    groups.retain(|_, group| group.expires_at > now);
+    let group = groups.get_mut(&group)?;
+    group.aggregate.count += 1;
+    Some(group.aggregate.clone())
+}
+
+/// Open a group for `window` with its delivered first message
+pub(crate) fn start(
+    group: u64,
+    window: Duration,
+    message_id: i64,
+    text: String,
+    parse_mode: ParseMode,
+) {
+    let mut groups = GROUPS.lock().unwrap_or_else(PoisonError::into_inner);
+    groups.get_or_insert_with(HashMap::new).insert(
+        group,
+        Group {
+            expires_at: Instant::now() + window,
+            aggregate: Aggregate {
+                message_id,
+                text,
+                parse_mode,
+                count: 1,
+            },
+        },
+    );
+}
+
+/// Close a group, e.g. because its first message can no longer be edited
+pub(crate) fn forget(group: u64) {
+    let mut groups = GROUPS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(groups) = groups.as_mut() {
+        groups.remove(&group);
+    }
+}
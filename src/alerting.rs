@@ -0,0 +1,353 @@
+//! Telegram alerts for error events, rate-limited so noisy errors stay readable.
+//!
+//! Enabled with the `tracing-alerts` feature. [`AlertLayer`] is a
+//! [`tracing_subscriber`] layer forwarding events at or above a level (`ERROR`
+//! by default) to the configured chat. Two limits keep one noisy error line
+//! from turning into thousands of messages:
+//!
+//! - **Grouping**: events with the same [`Fingerprint`] form a group. The first
+//!   one is sent right away; later ones within the group window are only
+//!   counted, and a summary with their number is sent when the window closes.
+//!   The fingerprint is taken from a `fingerprint` field of the event if it has
+//!   one, else from its callsite and [normalized](crate::fingerprint::normalize)
+//!   message.
+//! - **Rate limiting**: each callsite opens at most a few groups per interval,
+//!   however much its messages differ.
+//!
+//! ```no_run
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(telegrama_rs::alerting::AlertLayer::new()?)
+//!     .init();
+//!
+//! tracing::error!(order_id = 42, "Payment capture failed");
+//! tracing::error!(fingerprint = "smtp-down", "Could not reach {}", "smtp.example.com");
+//! # Ok::<(), telegrama_rs::Error>(())
+//! ```
+//!
+//! Alerts are sent by a [`DeliveryQueue`], so logging never waits for Telegram.
+//! Events of this crate itself are never forwarded, so a failing send can't
+//! cause more alerts. Records of the `log` crate are forwarded once converted
+//! to events, e.g. with `tracing_log::LogTracer`.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tracing::callsite::Identifier;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::client::Client;
+use crate::configuration::Configuration;
+use crate::error::Error;
+use crate::fingerprint::Fingerprint;
+use crate::formatter::format_duration;
+use crate::message::OutgoingMessage;
+use crate::queue::DeliveryQueue;
+
+/// Groups a callsite may open per rate limit interval unless configured
+pub const DEFAULT_MAX_PER_CALLSITE: usize = 5;
+
+/// Rate limit interval unless configured
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a group collects events after its first one unless configured
+pub const DEFAULT_GROUP_WINDOW: Duration = Duration::from_secs(600);
+
+/// How often closed groups are checked for a summary
+const TICK: Duration = Duration::from_secs(1);
+
+/// Events of one fingerprint since its alert
+struct Group {
+    opened: Instant,
+    /// First line of the alert
+    headline: String,
+    /// Events after the first one
+    occurrences: u64,
+    /// Configuration of the thread that logged the first event
+    config: Arc<Configuration>,
+}
+
+struct State {
+    window: Duration,
+    /// When each callsite recently opened a group
+    callsites: HashMap<Identifier, VecDeque<Instant>>,
+    groups: HashMap<Fingerprint, Group>,
+}
+
+/// State shared with the thread sending summaries
+struct Shared {
+    queue: DeliveryQueue,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn enqueue(&self, config: Arc<Configuration>, text: String) {
+        let _guard = Configuration::push_snapshot(config);
+        // Fire and forget, failures are logged by the client
+        drop(self.queue.enqueue(OutgoingMessage::new(text)));
+    }
+
+    /// Close the groups whose window is over (all of them if `all`), sending their summaries
+    fn close_groups(&self, all: bool) {
+        let now = Instant::now();
+        let (window, closed) = {
+            let mut state = self.state();
+            let window = state.window;
+            let closed: Vec<Fingerprint> = state
+                .groups
+                .iter()
+                .filter(|(_, group)| all || now.duration_since(group.opened) >= window)
+                .map(|(fingerprint, _)| *fingerprint)
+                .collect();
+            let closed: Vec<Group> = closed
+                .iter()
+                .filter_map(|fingerprint| state.groups.remove(fingerprint))
+                .collect();
+            (window, closed)
+        };
+        for group in closed.into_iter().filter(|group| group.occurrences > 0) {
+            let text = summary(&group, window);
+            self.enqueue(group.config, text);
+        }
+    }
+}
+
+/// Text sent for the events of a group after its alert
+fn summary(group: &Group, window: Duration) -> String {
+    format!(
+        "🔁 {} more time(s) within {}:\n{}",
+        group.occurrences,
+        format_duration(window),
+        group.headline
+    )
+}
+
+/// [`Layer`] sending events at or above a level as Telegram alerts
+pub struct AlertLayer {
+    shared: Arc<Shared>,
+    level: Level,
+    max_per_callsite: usize,
+    interval: Duration,
+    stop: Option<Sender<()>>,
+    summaries: Option<JoinHandle<()>>,
+}
+
+impl AlertLayer {
+    /// Send `ERROR` events to the configured chat with a new [`Client`]
+    pub fn new() -> Result<Self, Error> {
+        Self::with_client(Client::new())
+    }
+
+    /// Send `ERROR` events to the configured chat with `client`
+    pub fn with_client(client: Client) -> Result<Self, Error> {
+        let shared = Arc::new(Shared {
+            queue: DeliveryQueue::start(client)?,
+            state: Mutex::new(State {
+                window: DEFAULT_GROUP_WINDOW,
+                callsites: HashMap::new(),
+                groups: HashMap::new(),
+            }),
+        });
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let summaries = {
+            let shared = Arc::clone(&shared);
+            std::thread::Builder::new()
+                .name("telegrama-alerts".to_string())
+                .spawn(move || loop {
+                    match stopped.recv_timeout(TICK) {
+                        Err(RecvTimeoutError::Timeout) => shared.close_groups(false),
+                        // The layer was dropped
+                        _ => return shared.close_groups(true),
+                    }
+                })
+                .map_err(|e| Error::other(format!("Failed to start alert summaries: {}", e)))?
+        };
+
+        Ok(AlertLayer {
+            shared,
+            level: Level::ERROR,
+            max_per_callsite: DEFAULT_MAX_PER_CALLSITE,
+            interval: DEFAULT_INTERVAL,
+            stop: Some(stop),
+            summaries: Some(summaries),
+        })
+    }
+
+    /// Forward events at `level` or above, e.g. [`Level::WARN`]
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Open at most `max` groups per callsite within `interval`
+    pub fn with_rate_limit(mut self, max: usize, interval: Duration) -> Self {
+        self.max_per_callsite = max;
+        self.interval = interval;
+        self
+    }
+
+    /// Collect the events of a fingerprint for `window` after its alert
+    pub fn with_group_window(self, window: Duration) -> Self {
+        self.shared.state().window = window;
+        self
+    }
+
+    /// Open a group for an event, `false` if it joined one or was rate-limited
+    fn admit(&self, callsite: Identifier, fingerprint: Fingerprint, headline: &str) -> bool {
+        let now = Instant::now();
+        let mut state = self.shared.state();
+        let State {
+            window,
+            callsites,
+            groups,
+        } = &mut *state;
+
+        if let Some(group) = groups.get_mut(&fingerprint) {
+            if now.duration_since(group.opened) < *window {
+                group.occurrences += 1;
+                return false;
+            }
+        }
+
+        let recent = callsites.entry(callsite).or_default();
+        while recent
+            .front()
+            .is_some_and(|&opened| now.duration_since(opened) >= self.interval)
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= self.max_per_callsite {
+            return false;
+        }
+        recent.push_back(now);
+
+        let expired = groups.insert(
+            fingerprint,
+            Group {
+                opened: now,
+                headline: headline.to_string(),
+                occurrences: 0,
+                config: Configuration::snapshot(),
+            },
+        );
+        let window = *window;
+        drop(state);
+
+        // A group the summary thread didn't close yet
+        if let Some(group) = expired.filter(|group| group.occurrences > 0) {
+            let text = summary(&group, window);
+            self.shared.enqueue(group.config, text);
+        }
+        true
+    }
+}
+
+impl Drop for AlertLayer {
+    fn drop(&mut self) {
+        // Stopping the summary thread sends the open groups' summaries
+        self.stop.take();
+        if let Some(handle) = self.summaries.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl fmt::Debug for AlertLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlertLayer")
+            .field("level", &self.level)
+            .field("max_per_callsite", &self.max_per_callsite)
+            .field("interval", &self.interval)
+            .field("group_window", &self.shared.state().window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for AlertLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > self.level || metadata.target().starts_with("telegrama_rs") {
+            return;
+        }
+
+        let mut visitor = AlertVisitor::default();
+        event.record(&mut visitor);
+
+        let fingerprint = match &visitor.fingerprint {
+            Some(template) => Fingerprint::from_template(template),
+            None => Fingerprint::from_message(&callsite(metadata), &visitor.message),
+        };
+        let headline = format!(
+            "🚨 {} {}: {}",
+            metadata.level(),
+            metadata.target(),
+            visitor.message
+        );
+        if !self.admit(metadata.callsite(), fingerprint, &headline) {
+            return;
+        }
+
+        let mut text = headline;
+        if !visitor.fields.is_empty() {
+            text.push('\n');
+            text.push_str(&visitor.fields);
+        }
+        self.shared.enqueue(Configuration::snapshot(), text);
+    }
+}
+
+/// Source location of an event, e.g. `src/payments.rs:42`
+fn callsite(metadata: &Metadata<'_>) -> String {
+    match (metadata.file(), metadata.line()) {
+        (Some(file), Some(line)) => format!("{}:{}", file, line),
+        _ => metadata.name().to_string(),
+    }
+}
+
+/// Collects the message, the fingerprint and the other fields of an event as text
+#[derive(Default)]
+struct AlertVisitor {
+    message: String,
+    fingerprint: Option<String>,
+    /// One `name = value` line per field
+    fields: String,
+}
+
+impl Visit for AlertVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            "fingerprint" => self.fingerprint = Some(value.to_string()),
+            _ => self.record_debug(field, &format_args!("{}", value)),
+        }
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.record_debug(field, &format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "fingerprint" => self.fingerprint = Some(format!("{:?}", value)),
+            _ => {
+                if !self.fields.is_empty() {
+                    self.fields.push('\n');
+                }
+                let _ = write!(self.fields, "{} = {:?}", field.name(), value);
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! `telegrama` command-line tool, built with the `cli` feature.
+//!
+//! ```text
+//! telegrama send "Backup finished" --chat -100123 --parse-mode markdown --silent
+//! journalctl -u app | tail -50 | telegrama send --code-block
+//! telegrama run -- make deploy
+//! ```
+//!
+//! Without a text argument, the message is read from stdin. Input too long for a
+//! single message keeps its last lines, or is split with `--chunk`. `run` reports
+//! the outcome of a command and exits with its exit code.
+//!
+//! Settings are read from the file given with `--config` (or `TELEGRAMA_CONFIG`),
+//! then from `TELEGRAMA_*` environment variables, then from the flags.
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+
+use clap::{Parser, Subcommand};
+use telegrama_rs::formatter::{Formatter, MAX_MESSAGE_LENGTH};
+use telegrama_rs::process::RunReport;
+use telegrama_rs::{Client, Configuration, Error, ParseMode, Telegrama};
+
+/// Room reserved for a timestamp line and code fences
+const DECORATION_LENGTH: usize = 64;
+
+/// Send Telegram messages from shell scripts and cron jobs
+#[derive(Debug, Parser)]
+#[command(name = "telegrama", version)]
+struct Cli {
+    /// TOML or YAML configuration file
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliCommand {
+    /// Send a message to the configured chat
+    Send(SendArgs),
+    /// Run a command and report its outcome to the configured chat
+    Run(RunArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct SendArgs {
+    /// Message text, read from stdin if omitted or `-`
+    text: Option<String>,
+
+    /// Chat ID, @username or alias, instead of the configured chat
+    #[arg(long, value_name = "CHAT", allow_negative_numbers = true)]
+    chat: Option<String>,
+
+    /// MarkdownV2, Markdown, HTML or plain, instead of the configured mode
+    #[arg(long, value_name = "MODE")]
+    parse_mode: Option<ParseMode>,
+
+    /// Named bot profile of the configuration to send with
+    #[arg(long, value_name = "NAME")]
+    bot: Option<String>,
+
+    /// Deliver without a notification sound
+    #[arg(long)]
+    silent: bool,
+
+    /// Wrap the text in a code block, e.g. for logs (sent as MarkdownV2)
+    #[arg(long, conflicts_with = "parse_mode")]
+    code_block: bool,
+
+    /// Send long input as several messages instead of keeping its last lines
+    #[arg(long)]
+    chunk: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct RunArgs {
+    /// Chat ID, @username or alias, instead of the configured chat
+    #[arg(long, value_name = "CHAT", allow_negative_numbers = true)]
+    chat: Option<String>,
+
+    /// Named bot profile of the configuration to send with
+    #[arg(long, value_name = "NAME")]
+    bot: Option<String>,
+
+    /// Deliver the report without a notification sound
+    #[arg(long)]
+    silent: bool,
+
+    /// Only report failures
+    #[arg(long)]
+    on_failure: bool,
+
+    /// Command to run, after `--`
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    command: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("telegrama: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode, Error> {
+    let config_file = cli
+        .config
+        .or_else(|| std::env::var_os("TELEGRAMA_CONFIG").map(PathBuf::from));
+    if let Some(path) = config_file {
+        Telegrama::configure_from_file(path)?;
+    }
+    Telegrama::configure_from_env()?;
+
+    match cli.command {
+        CliCommand::Send(args) => send(args).map(|()| ExitCode::SUCCESS),
+        CliCommand::Run(args) => run_command(args),
+    }
+}
+
+fn run_command(args: RunArgs) -> Result<ExitCode, Error> {
+    let mut command = Command::new(&args.command[0]);
+    command.args(&args.command[1..]);
+    let report = RunReport::run(command)?;
+
+    if !(args.on_failure && report.success()) {
+        let mut options: Vec<(&str, &str)> = Vec::new();
+        if let Some(chat) = &args.chat {
+            options.push(("chat_id", chat));
+        }
+        if let Some(bot) = &args.bot {
+            options.push(("bot", bot));
+        }
+        if args.silent {
+            options.push(("disable_notification", "true"));
+        }
+
+        // The command's exit code matters more than the report
+        if let Err(e) = report.send(&Client::new(), &options) {
+            eprintln!("telegrama: failed to send the report: {}", e);
+        }
+    }
+
+    Ok(ExitCode::from(report.exit_code().clamp(0, 255) as u8))
+}
+
+fn send(args: SendArgs) -> Result<(), Error> {
+    let text = match args.text {
+        Some(text) if text != "-" => text,
+        _ => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| Error::other(format!("Failed to read stdin: {}", e)))?;
+            input
+        }
+    };
+    let text = text.trim_end();
+    if text.trim().is_empty() {
+        return Err(Error::formatting("Nothing to send"));
+    }
+
+    let parse_mode = if args.code_block {
+        Some(ParseMode::MarkdownV2)
+    } else {
+        args.parse_mode
+    };
+
+    let mut options: Vec<(&str, &str)> = Vec::new();
+    if let Some(chat) = &args.chat {
+        options.push(("chat_id", chat));
+    }
+    if let Some(mode) = parse_mode {
+        options.push(("parse_mode", mode.as_api_str().unwrap_or("plain")));
+    }
+    if let Some(bot) = &args.bot {
+        options.push(("bot", bot));
+    }
+    if args.silent {
+        options.push(("disable_notification", "true"));
+    }
+
+    let body = if args.code_block {
+        Formatter::escape_markdown_code(text).into_owned()
+    } else {
+        text.to_string()
+    };
+    let limit = message_limit();
+    let parts = if args.chunk {
+        chunk_lines(&body, limit)
+    } else {
+        vec![keep_last_lines(&body, limit)]
+    };
+
+    let client = Client::new();
+    for part in parts {
+        let message = if args.code_block {
+            format!("```\n{}\n```", part)
+        } else {
+            part
+        };
+        client.send_message(&message, &options)?;
+    }
+    Ok(())
+}
+
+/// Bytes of input that fit in a message next to the configured decoration
+///
+/// Prefix and suffix are counted twice, as escaping can double them.
+fn message_limit() -> usize {
+    let config = Configuration::snapshot();
+    let max_length = config
+        .formatting_options()
+        .truncate
+        .map_or(MAX_MESSAGE_LENGTH, |truncate| {
+            truncate.min(MAX_MESSAGE_LENGTH)
+        });
+    let decoration =
+        config.message_prefix().map_or(0, str::len) + config.message_suffix().map_or(0, str::len);
+
+    max_length
+        .saturating_sub(2 * decoration + DECORATION_LENGTH)
+        .max(DECORATION_LENGTH)
+}
+
+/// Keep the last lines of `text` that fit in `limit` bytes, noting what was dropped
+fn keep_last_lines(text: &str, limit: usize) -> String {
+    if text.len() <= limit {
+        return text.to_string();
+    }
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut kept = 0;
+    let mut length = 0;
+    for line in lines.iter().rev() {
+        // Leave room for the note about the dropped lines
+        if length + line.len() + 1 > limit.saturating_sub(DECORATION_LENGTH) {
+            break;
+        }
+        length += line.len() + 1;
+        kept += 1;
+    }
+
+    if kept == 0 {
+        // A single huge line: keep its end
+        let last = lines.last().copied().unwrap_or_default();
+        return tail(last, limit).to_string();
+    }
+
+    format!(
+        "[{} earlier lines omitted]\n{}",
+        lines.len() - kept,
+        lines[lines.len() - kept..].join("\n")
+    )
+}
+
+/// Split `text` at line breaks into parts of at most `limit` bytes
+///
+/// Lines longer than `limit` are split at character boundaries.
+fn chunk_lines(text: &str, limit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let mut line = line;
+        while line.len() > limit {
+            let split = floor_char_boundary(line, limit);
+            if !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            parts.push(line[..split].to_string());
+            line = &line[split..];
+        }
+
+        if !current.is_empty() && current.len() + 1 + line.len() > limit {
+            parts.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// The last at most `limit` bytes of `text`, starting at a character boundary
+fn tail(text: &str, limit: usize) -> &str {
+    let mut start = text.len().saturating_sub(limit);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+/// The largest character boundary of `text` at or below `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
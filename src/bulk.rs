@@ -0,0 +1,105 @@
+//! Sending many messages at once, e.g. to fan a daily report out to subscribers.
+//!
+//! [`Client::send_messages`](crate::Client::send_messages) sends on a few worker
+//! threads that share one rate limit. Messages to the same chat keep their order.
+//!
+//! ```no_run
+//! use telegrama_rs::{Client, OutgoingMessage};
+//!
+//! let subscribers = ["-100123", "-100456", "789"];
+//! let messages = subscribers
+//!     .iter()
+//!     .map(|chat_id| OutgoingMessage::new("*Daily report* is ready").to(*chat_id))
+//!     .collect();
+//!
+//! for (chat_id, result) in subscribers.iter().zip(Client::new().send_messages(messages)) {
+//!     if let Err(e) = result {
+//!         eprintln!("{}: {}", chat_id, e);
+//!     }
+//! }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+pub use crate::message::OutgoingMessage;
+
+/// How [`Client::send_messages_with`](crate::Client::send_messages_with) spreads its sends
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkOptions {
+    /// Number of chats sent to at the same time
+    pub concurrency: usize,
+    /// Messages started per second across all workers, below Telegram's limit of about 30
+    pub messages_per_second: f64,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        BulkOptions {
+            concurrency: 8,
+            messages_per_second: 25.0,
+        }
+    }
+}
+
+/// Messages grouped by chat, in input order, with their input index
+pub(crate) type ChatQueue = VecDeque<Vec<(usize, OutgoingMessage)>>;
+
+/// Group `messages` by target chat, keeping their order within a chat
+pub(crate) fn group_by_chat(messages: Vec<OutgoingMessage>) -> ChatQueue {
+    let mut groups: Vec<Vec<(usize, OutgoingMessage)>> = Vec::new();
+    let mut group_of_chat: HashMap<String, usize> = HashMap::new();
+    for (index, message) in messages.into_iter().enumerate() {
+        match group_of_chat.get(message.chat()) {
+            Some(&group) => groups[group].push((index, message)),
+            None => {
+                group_of_chat.insert(message.chat().to_string(), groups.len());
+                groups.push(vec![(index, message)]);
+            }
+        }
+    }
+    groups.into()
+}
+
+/// Paces sends shared by all workers of a bulk send
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Limiter allowing `per_second` sends per second (unlimited if not positive)
+    pub(crate) fn new(per_second: f64) -> Self {
+        let interval = if per_second > 0.0 {
+            Duration::from_secs_f64(1.0 / per_second)
+        } else {
+            Duration::ZERO
+        };
+        RateLimiter {
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for the next free send slot
+    pub(crate) fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        let delay = slot.saturating_duration_since(Instant::now());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Hold every worker back for `duration`, e.g. after flood control
+    pub(crate) fn pause(&self, duration: Duration) {
+        let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+        *next = (*next).max(Instant::now() + duration);
+    }
+}
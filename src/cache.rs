@@ -0,0 +1,84 @@
+//! Short-lived cache of idempotent Bot API lookups.
+//!
+//! [`Client::get_me`](crate::Client::get_me), [`Client::get_chat`](crate::Client::get_chat)
+//! and the `getChat` lookups behind `@username` chat targets reuse their result
+//! for a few minutes, so rendering a header from them on every message doesn't
+//! cost a request each time.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// How long a lookup is reused by default
+pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// Cached results and their expiry, keyed by bot (token hash), method and lowercase target
+type Entries = HashMap<(u64, &'static str, String), (serde_json::Value, Instant)>;
+
+/// Results of idempotent getters, owned by a [`Client`](crate::Client)
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    /// Cache keeping results for `ttl` (zero disables caching)
+    pub(crate) fn new(ttl: Duration) -> Self {
+        ResponseCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cached result of `method` for `target`, unless expired
+    pub(crate) fn get(
+        &self,
+        bot_token: &str,
+        method: &'static str,
+        target: &str,
+    ) -> Option<serde_json::Value> {
+        let now = Instant::now();
+        let entries = self.lock();
+        entries
+            .get(&key(bot_token, method, target))
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(result, _)| result.clone())
+    }
+
+    /// Remember the result of `method` for `target`
+    pub(crate) fn store(
+        &self,
+        bot_token: &str,
+        method: &'static str,
+        target: &str,
+        result: serde_json::Value,
+    ) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        entries.insert(key(bot_token, method, target), (result, now + self.ttl));
+    }
+
+    /// Forget every cached result
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn key(bot_token: &str, method: &'static str, target: &str) -> (u64, &'static str, String) {
+    let mut hasher = DefaultHasher::new();
+    bot_token.hash(&mut hasher);
+    (hasher.finish(), method, target.to_lowercase())
+}
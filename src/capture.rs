@@ -0,0 +1,151 @@
+//! Opt-in capture of recent Bot API exchanges for debugging.
+//!
+//! Enable it with [`Client::with_debug_capture`](crate::Client::with_debug_capture) and read
+//! the entries with [`Client::debug_log`](crate::Client::debug_log) to see exactly what was sent
+//! to Telegram and what came back, e.g. when a message fails with
+//! `Bad Request: can't parse entities`. The bot token and webhook `secret_token`
+//! are always masked; other fields (such as `text`) can be masked with
+//! [`DebugCapture::redact_field`].
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use crate::error::Error;
+use crate::secret::{redact, REDACTED};
+use crate::transport::RawResponse;
+
+/// Settings of the debug capture mode
+#[derive(Debug, Clone)]
+pub struct DebugCapture {
+    capacity: usize,
+    redacted_fields: Vec<String>,
+}
+
+impl DebugCapture {
+    /// Keep the last `capacity` requests
+    pub fn new(capacity: usize) -> Self {
+        DebugCapture {
+            capacity,
+            redacted_fields: vec!["secret_token".to_string()],
+        }
+    }
+
+    /// Mask a JSON field (at any depth) in captured requests and responses
+    pub fn redact_field<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.redacted_fields.push(name.as_ref().to_string());
+        self
+    }
+}
+
+/// One captured request and its outcome
+#[derive(Debug, Clone)]
+pub struct DebugEntry {
+    /// When the request was sent
+    pub sent_at: SystemTime,
+    /// How long the transport took to answer
+    pub duration: Duration,
+    /// Bot API method (e.g. `sendMessage`)
+    pub method: String,
+    /// Final JSON payload, after middleware, with redacted fields masked
+    pub request: serde_json::Value,
+    /// HTTP status, if a response was received
+    pub status: Option<u16>,
+    /// Raw response body, with redacted fields masked
+    pub response: Option<String>,
+    /// Transport error, if no response was received
+    pub error: Option<String>,
+}
+
+/// Bounded buffer of captured exchanges shared by a client
+#[derive(Debug)]
+pub(crate) struct DebugLog {
+    capture: DebugCapture,
+    entries: Mutex<VecDeque<DebugEntry>>,
+}
+
+impl DebugLog {
+    pub(crate) fn new(capture: DebugCapture) -> Self {
+        DebugLog {
+            entries: Mutex::new(VecDeque::with_capacity(capture.capacity)),
+            capture,
+        }
+    }
+
+    /// Record one transport attempt
+    pub(crate) fn record(
+        &self,
+        bot_token: &str,
+        method: &str,
+        params: &serde_json::Value,
+        outcome: &Result<RawResponse, Error>,
+        sent_at: SystemTime,
+        duration: Duration,
+    ) {
+        if self.capture.capacity == 0 {
+            return;
+        }
+
+        let mut request = params.clone();
+        self.redact_fields(&mut request);
+
+        let (status, response, error) = match outcome {
+            Ok(raw) => {
+                let body = match serde_json::from_str::<serde_json::Value>(&raw.body) {
+                    Ok(mut json) => {
+                        self.redact_fields(&mut json);
+                        json.to_string()
+                    }
+                    Err(_) => raw.body.clone(),
+                };
+                (Some(raw.status), Some(redact(&body, bot_token)), None)
+            }
+            Err(e) => (None, None, Some(redact(&e.to_string(), bot_token))),
+        };
+
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() == self.capture.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(DebugEntry {
+            sent_at,
+            duration,
+            method: method.to_string(),
+            request,
+            status,
+            response,
+            error,
+        });
+    }
+
+    /// Captured entries, oldest first
+    pub(crate) fn entries(&self) -> Vec<DebugEntry> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.iter().cloned().collect()
+    }
+
+    fn redact_fields(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self
+                        .capture
+                        .redacted_fields
+                        .iter()
+                        .any(|field| field == key)
+                    {
+                        *value = REDACTED.into();
+                    } else {
+                        self.redact_fields(value);
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => {
+                values
+                    .iter_mut()
+                    .for_each(|value| self.redact_fields(value));
+            }
+            _ => {}
+        }
+    }
+}
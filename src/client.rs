@@ -1,16 +1,106 @@
-use log::{error, info};
-use reqwest::blocking::{Client as ReqwestClient, Response as ReqwestResponse};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
 use std::time::Duration;
+use tracing::{debug, error, info, instrument, warn, Span};
 
-use crate::configuration::Configuration;
-use crate::error::Error;
-use crate::formatter::Formatter;
-use crate::formatter::FormattingOptions;
+use crate::aggregation;
+use crate::bulk::{self, BulkOptions, OutgoingMessage, RateLimiter};
+use crate::cache::{self, ResponseCache};
+use crate::capture::{DebugCapture, DebugEntry, DebugLog};
+use crate::configuration::{
+    ClientOptions, Configuration, DuplicateStrategy, FallbackPolicy, LinkPreviewOptions, ParseMode,
+    Route,
+};
+use crate::delivery::{FailedDelivery, FallbackDelivery};
+use crate::error::{Error, StaleChatReason};
+use crate::escalation;
+use crate::filter::Verdict;
+use crate::formatter::{
+    self, utf16_len, FormatContext, Formatter, FormattingOptions, SplitMode, MAX_MESSAGE_LENGTH,
+};
+use crate::handle::MessageHandle;
+use crate::history::{self, ErrorHistory, FailedSend};
+use crate::idempotency;
+use crate::media::{InputFile, InputMedia};
+use crate::middleware::Middleware;
+use crate::moderation::{
+    ChatAdministratorRights, ChatInviteLink, ChatPermissions, InviteLinkOptions,
+};
+use crate::payments::{Invoice, ShippingOption};
+use crate::receipt::DeliveryReceipt;
+use crate::resolver;
+use crate::stats;
+use crate::throttle;
+use crate::time::{self, Instant};
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+use crate::transport::AsyncReqwestTransport;
+use crate::transport::{self, RawResponse, RequestBody, Transport};
+use crate::types::{Chat, ChatMember, ForumTopic, TopicIconColor, User};
+use crate::webhook::{WebhookInfo, WebhookOptions};
+
+thread_local! {
+    /// Bot API requests made by the current thread, or by the [`SendScope`]
+    /// polled on it, to count the attempts of a send
+    static REQUESTS: Cell<u32> = const { Cell::new(0) };
+}
+
+/// A send that keeps its configuration and request count on whichever thread polls it
+///
+/// Every poll makes the configuration in effect when the send started the
+/// current one and counts requests in the send's own [`REQUESTS`], so an
+/// [`AsyncClient`] send moving between the threads of a runtime reads the same
+/// settings throughout and counts only its own attempts.
+struct SendScope<F> {
+    config: Arc<Configuration>,
+    requests: u32,
+    send: Pin<Box<F>>,
+}
+
+impl<F: Future> SendScope<F> {
+    fn new(send: F) -> Self {
+        SendScope {
+            config: Configuration::snapshot(),
+            requests: 0,
+            send: Box::pin(send),
+        }
+    }
+}
+
+impl<F: Future> Future for SendScope<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        let _config = Configuration::push_snapshot(Arc::clone(&this.config));
+        let outer = REQUESTS.with(|requests| requests.replace(this.requests));
+        let poll = this.send.as_mut().poll(cx);
+        this.requests = REQUESTS.with(|requests| requests.replace(outer));
+        poll
+    }
+}
+
+/// Callback invoked with the chat ID and reason of a permanent delivery failure
+type StaleChatCallback = Arc<dyn Fn(&str, StaleChatReason) + Send + Sync>;
+
+/// Callback invoked with the chat ID, requested and delivered parse mode of a fallback
+type FallbackCallback = Arc<dyn Fn(&str, ParseMode, ParseMode) + Send + Sync>;
 
 /// HTTP client for communicating with the Telegram API
 pub struct Client {
-    client: ReqwestClient,
+    transport: Arc<dyn Transport>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    debug_log: Option<Arc<DebugLog>>,
+    on_stale_chat: Option<StaleChatCallback>,
+    on_fallback: Option<FallbackCallback>,
+    cache: ResponseCache,
+    errors: ErrorHistory,
+    fallback_delivery: Option<Arc<dyn FallbackDelivery>>,
 }
 
 /// Response from the Telegram API
@@ -22,6 +112,72 @@ pub struct Response {
     pub description: Option<String>,
     /// Response result
     pub result: Option<serde_json::Value>,
+    /// Error code of failed requests (mirrors the HTTP status)
+    #[serde(default)]
+    pub error_code: Option<i64>,
+    /// Extra information about failed requests (e.g. flood control)
+    #[serde(default)]
+    pub parameters: Option<ResponseParameters>,
+}
+
+/// Extra information returned with some failed requests
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResponseParameters {
+    /// The group was migrated to a supergroup with this ID
+    pub migrate_to_chat_id: Option<i64>,
+    /// Seconds to wait before repeating a request rejected by flood control
+    pub retry_after: Option<u64>,
+}
+
+/// File ready to be downloaded, returned by [`Client::get_file`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct File {
+    /// Identifier of the file, usable to download or reuse it
+    pub file_id: String,
+    /// Identifier that stays the same across bots and over time
+    pub file_unique_id: String,
+    /// File size in bytes, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    /// Path to pass to [`Client::download_file`]
+    ///
+    /// A local Bot API server started with `--local` returns an absolute path
+    /// on its file system instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+}
+
+/// Timeout of the `getMe` request made by [`Client::health_check`]
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Result of [`Client::health_check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// The Bot API answered and accepted the token
+    Reachable {
+        /// Username of the bot
+        username: Option<String>,
+    },
+    /// The bot token was rejected (revoked or mistyped)
+    Unauthorized,
+    /// Flood control is active, retry after the given number of seconds
+    RateLimited {
+        /// Seconds to wait before the next request
+        retry_after: u64,
+    },
+    /// The Bot API could not be reached (DNS, TLS, timeout, ...)
+    NetworkError(String),
+    /// The Bot API answered with another error
+    ApiError(String),
+    /// No bot token is configured
+    Misconfigured(String),
+}
+
+impl HealthStatus {
+    /// Whether messages can currently be sent
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, HealthStatus::Reachable { .. })
+    }
 }
 
 /// Helper struct for building API requests
@@ -29,14 +185,52 @@ pub struct Response {
 struct SendMessageParams<'a> {
     /// Telegram chat ID
     chat_id: &'a str,
+    /// Forum topic to send the message to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_thread_id: Option<i64>,
     /// Message text
     text: &'a str,
-    /// Parse mode (MarkdownV2, HTML, or None)
+    /// Parse mode (MarkdownV2, Markdown, HTML, or None)
     #[serde(skip_serializing_if = "Option::is_none")]
     parse_mode: Option<&'a str>,
-    /// Whether to disable web page previews
+    /// Link preview generation options
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link_preview_options: Option<&'a LinkPreviewOptions>,
+    /// Deliver the message without a notification sound
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    disable_notification: bool,
+    /// Message to reply to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_parameters: Option<ReplyParameters>,
+}
+
+/// Bot API `ReplyParameters` of a message sent as a reply
+#[derive(Debug, Clone, Copy, Serialize)]
+struct ReplyParameters {
+    /// Identifier of the message to reply to, in the same chat
+    message_id: i64,
+    /// Send the message even if the replied-to message is gone
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    allow_sending_without_reply: bool,
+}
+
+/// Parameters of an `answerCallbackQuery` request
+#[derive(Debug, Serialize)]
+struct AnswerCallbackQueryParams<'a> {
+    /// Identifier of the query to answer
+    callback_query_id: &'a str,
+    /// Notification shown to the user
     #[serde(skip_serializing_if = "Option::is_none")]
-    disable_web_page_preview: Option<bool>,
+    text: Option<&'a str>,
+    /// Show an alert instead of a notification at the top of the chat
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    show_alert: bool,
+    /// URL opened by the user's client (games and `t.me` bot links)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+    /// Seconds the answer may be cached client-side
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_time: Option<u32>,
 }
 
 impl Default for Client {
@@ -46,366 +240,2494 @@ impl Default for Client {
 }
 
 impl Client {
-    /// Create a new Telegram client
+    /// Create a new Telegram client using the default transport of the enabled backend
+    ///
+    /// Honors the [`ClientOptions`] of the current configuration and reuses the
+    /// shared HTTP client (and its keep-alive connections) built for them. If the
+    /// options can't be applied (e.g. an unreadable certificate), the error is
+    /// logged and the backend defaults are used; call [`Client::from_options`] to
+    /// handle it instead.
     pub fn new() -> Self {
-        let client = ReqwestClient::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .unwrap_or_else(|_| ReqwestClient::new());
+        let config = Configuration::snapshot();
+        Self::from_options(config.client_options()).unwrap_or_else(|e| {
+            error!(error = %e, "Invalid client options, using defaults");
+            Self::from_shared_transport(
+                transport::shared_transport(&ClientOptions::default())
+                    .expect("default client options are valid"),
+            )
+        })
+    }
+
+    /// Create a client whose default transport honors the given [`ClientOptions`]
+    ///
+    /// The underlying HTTP client is shared with every other client created
+    /// with the same options.
+    pub fn from_options(options: &ClientOptions) -> Result<Self, Error> {
+        Ok(Self::from_shared_transport(transport::shared_transport(
+            options,
+        )?))
+    }
+
+    /// Create a client that sends every request through a custom [`Transport`]
+    pub fn with_transport<T: Transport + 'static>(transport: T) -> Self {
+        Self::from_shared_transport(Arc::new(transport))
+    }
+
+    /// Create a client without middleware or callbacks around a transport
+    fn from_shared_transport(transport: Arc<dyn Transport>) -> Self {
+        Client {
+            transport,
+            middleware: Vec::new(),
+            debug_log: None,
+            on_stale_chat: None,
+            on_fallback: None,
+            cache: ResponseCache::new(cache::DEFAULT_TTL),
+            errors: ErrorHistory::new(history::DEFAULT_CAPACITY),
+            fallback_delivery: None,
+        }
+    }
+
+    /// Run a [`Middleware`] around every API call, after previously added ones
+    pub fn with_middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Deliver messages through `delivery` when sending them to Telegram finally fails
+    ///
+    /// Messages stopped by a content filter are not handed over.
+    pub fn with_fallback_delivery<D: FallbackDelivery + 'static>(mut self, delivery: D) -> Self {
+        self.fallback_delivery = Some(Arc::new(delivery));
+        self
+    }
+
+    /// Record the payload and raw response of recent requests, see [`Client::debug_log`]
+    pub fn with_debug_capture(mut self, capture: DebugCapture) -> Self {
+        self.debug_log = Some(Arc::new(DebugLog::new(capture)));
+        self
+    }
 
-        Client { client }
+    /// Call `callback` when a chat permanently stops accepting messages
+    ///
+    /// Fires with the chat ID when a send fails because the bot was blocked or
+    /// kicked, the chat doesn't exist or the user was deleted, so applications can
+    /// prune dead subscribers.
+    ///
+    /// ```
+    /// use telegrama_rs::Client;
+    ///
+    /// let client = Client::new().on_stale_chat(|chat_id, reason| {
+    ///     eprintln!("unsubscribing {}: {:?}", chat_id, reason);
+    /// });
+    /// ```
+    pub fn on_stale_chat<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str, StaleChatReason) + Send + Sync + 'static,
+    {
+        self.on_stale_chat = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call `callback` when a message was delivered in a fallback parse mode
+    ///
+    /// Fires with the chat ID, the requested mode and the mode that Telegram
+    /// finally accepted, so degraded messages can be tracked.
+    pub fn on_fallback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str, ParseMode, ParseMode) + Send + Sync + 'static,
+    {
+        self.on_fallback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Reuse `getMe` and `getChat` results for `ttl` instead of the default
+    /// [`cache::DEFAULT_TTL`] (zero disables caching)
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache = ResponseCache::new(ttl);
+        self
+    }
+
+    /// Forget cached `getMe` and `getChat` results, e.g. after renaming a chat
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
+
+    /// Keep the last `capacity` failed sends instead of the default
+    /// [`history::DEFAULT_CAPACITY`] (zero disables the history)
+    pub fn with_error_history(mut self, capacity: usize) -> Self {
+        self.errors = ErrorHistory::new(capacity);
+        self
+    }
+
+    /// Last failed sends of this client, oldest first
+    ///
+    /// Each entry has the chat, the error and the beginning of the message, so
+    /// an operator can ask the bot why messages stopped arriving. Suppressed
+    /// duplicates are not failures and aren't recorded.
+    pub fn recent_errors(&self) -> Vec<FailedSend> {
+        self.errors.entries()
+    }
+
+    /// Requests captured in debug mode, oldest first (empty unless enabled)
+    ///
+    /// Every HTTP attempt is recorded, including retries and format fallbacks.
+    pub fn debug_log(&self) -> Vec<DebugEntry> {
+        self.debug_log
+            .as_ref()
+            .map(|log| log.entries())
+            .unwrap_or_default()
     }
 
     /// Send a message to the Telegram API
+    ///
+    /// Runs in a `telegrama.send_message` span recording the `chat_id`,
+    /// `parse_mode` and final `outcome` (`"sent"` or the error kind).
     pub fn send_message(&self, message: &str, options: &[(&str, &str)]) -> Result<Response, Error> {
-        // Get configuration using the clone method to avoid deadlocks
-        let config = Configuration::get_cloned_instance()?;
+        transport::block_on(self.send_text(message, options, false))
+    }
+
+    /// Send `message` through the pipeline of [`Client::send_message`], in its own [`SendScope`]
+    ///
+    /// A `preformatted` message is already escaped for the parse mode: it is
+    /// sent as is, without formatting and without parse mode fallbacks.
+    fn send_text<'a>(
+        &'a self,
+        message: &'a str,
+        options: &'a [(&'a str, &'a str)],
+        preformatted: bool,
+    ) -> impl Future<Output = Result<Response, Error>> + 'a {
+        SendScope::new(self.deliver_text(message, options, preformatted))
+    }
+
+    /// The pipeline of [`Client::send_text`]
+    ///
+    /// Blocking callers wait for it, [`AsyncClient`] awaits it.
+    #[instrument(
+        name = "telegrama.send_message",
+        skip_all,
+        fields(chat_id, parse_mode, outcome)
+    )]
+    async fn deliver_text(
+        &self,
+        message: &str,
+        options: &[(&str, &str)],
+        preformatted: bool,
+    ) -> Result<Response, Error> {
+        // Take a snapshot so a concurrent `configure` can't change settings mid-send
+        let config = Configuration::snapshot();
+        let started = Instant::now();
+        let requests_before = REQUESTS.with(Cell::get);
+        // Every request below, fallbacks and escalation included, shares one send deadline
+        let deadline = config
+            .client_options()
+            .send_deadline
+            .map(|seconds| started + Duration::from_secs(seconds));
 
         // Validate configuration
         config.validate()?;
 
-        // Get required parameters
-        let bot_token = config.bot_token()?;
-
-        // Determine chat ID (options override config)
-        let chat_id = options
-            .iter()
-            .find(|(k, _)| *k == "chat_id")
-            .map(|(_, v)| *v)
-            .unwrap_or_else(|| {
-                // Use default chat ID as fallback
-                match config.chat_id() {
-                    Ok(id) => id,
-                    Err(_) => {
-                        // This shouldn't happen due to validation, but handle it anyway
-                        error!("No chat ID provided in options or configuration");
-                        ""
-                    }
-                }
-            });
+        // Get required parameters (a named bot profile overrides the default token)
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = self
+            .extract_chat_id_until(options, &config, bot_token, deadline)
+            .await?;
+        let chat_id = chat_id.as_ref();
+        Span::current().record("chat_id", chat_id);
 
-        if chat_id.is_empty() {
-            return Err(Error::configuration("Chat ID not provided"));
-        }
+        // Let the content filters drop, divert or rewrite the message
+        let (message, quarantine) = match config.content_filters().run(message) {
+            Verdict::Send(text) => (text, None),
+            Verdict::Quarantine(text, reason) if config.quarantine_chat_id().is_some() => {
+                warn!(%reason, "Message quarantined by a content filter");
+                let header = format!("Quarantined message for {} ({}):\n\n", chat_id, reason);
+                (Cow::Owned(header + &text), Some(reason))
+            }
+            Verdict::Drop(reason) | Verdict::Quarantine(_, reason) => {
+                warn!(%reason, "Message dropped by a content filter");
+                let e = Error::Filtered {
+                    reason,
+                    quarantined: false,
+                };
+                return self.fail_unsent(&config, chat_id, message, e, started, requests_before);
+            }
+        };
+        // Quarantined messages go to the quarantine chat instead of the target
+        let target = chat_id;
+        let quarantine_chat = match (&quarantine, config.quarantine_chat_id()) {
+            (Some(_), Some(quarantine_chat)) => Some(
+                self.extract_chat_id_until(
+                    &[("chat_id", quarantine_chat)],
+                    &config,
+                    bot_token,
+                    deadline,
+                )
+                .await?,
+            ),
+            _ => None,
+        };
+        let chat_id = quarantine_chat.as_deref().unwrap_or(chat_id);
+        let message = message.as_ref();
 
         // Get default formatting options from the config
         let config_formatting = config.formatting_options();
 
         // Convert config formatting options to formatter module's FormattingOptions
-        let default_formatting_options = crate::formatter::FormattingOptions {
-            escape_markdown: config_formatting.escape_markdown,
-            obfuscate_emails: config_formatting.obfuscate_emails,
-            escape_html: config_formatting.escape_html,
-            truncate: config_formatting.truncate,
-        };
+        let default_formatting_options = FormattingOptions::from(config_formatting);
 
         // Extract formatting options from options
         let formatting_options =
-            self.extract_formatting_options(options, default_formatting_options);
+            Self::extract_formatting_options(options, default_formatting_options);
 
-        // Extract parse mode from options (or use default)
-        let mut parse_mode = options
+        let parse_mode = Self::extract_parse_mode(options, &config);
+        Span::current().record("parse_mode", parse_mode.as_api_str().unwrap_or("plain"));
+
+        // Extract link preview settings from options (or use default)
+        let link_preview =
+            Self::extract_link_preview_options(options, config.link_preview_options());
+        let link_preview_options = Some(&link_preview).filter(|options| !options.is_empty());
+
+        // Per-call total timeout in seconds, replacing `ClientOptions::request_timeout`
+        let timeout = options
             .iter()
-            .find(|(k, _)| *k == "parse_mode")
-            .map(|(_, v)| *v)
-            .or_else(|| config.default_parse_mode());
+            .find(|(k, _)| *k == "timeout")
+            .and_then(|(_, v)| match v.parse::<u64>() {
+                Ok(seconds) => Some(Duration::from_secs(seconds)),
+                Err(_) => {
+                    warn!(value = %v, "Ignoring invalid timeout option");
+                    None
+                }
+            });
 
-        // Validate parse mode
-        if let Some(mode) = parse_mode {
-            if mode != "MarkdownV2" && mode != "HTML" && !mode.is_empty() {
-                // Fix invalid parse mode to avoid API errors
-                parse_mode = None;
-            }
-        }
+        // Forum topic of the chat to post in
+        let message_thread_id = Self::extract_message_thread_id(options, &config);
 
-        // Extract web page preview setting from options (or use default)
-        let disable_web_page_preview = options
+        // Deliver silently, e.g. for low-priority notifications
+        let disable_notification = options
             .iter()
-            .find(|(k, _)| *k == "disable_web_page_preview")
-            .map(|(_, v)| v.to_lowercase() == "true")
-            .unwrap_or_else(|| config.disable_web_page_preview());
+            .any(|(k, v)| *k == "disable_notification" && v.to_lowercase() == "true");
+
+        // Message of the same chat to reply to, optionally sent standalone if it was deleted
+        let allow_sending_without_reply = options
+            .iter()
+            .any(|(k, v)| *k == "allow_sending_without_reply" && v.to_lowercase() == "true");
+        let reply_parameters = options
+            .iter()
+            .find(|(k, _)| *k == "reply_to_message_id")
+            .and_then(|(_, v)| match v.parse::<i64>() {
+                Ok(message_id) => Some(ReplyParameters {
+                    message_id,
+                    allow_sending_without_reply,
+                }),
+                Err(_) => {
+                    warn!(value = %v, "Ignoring invalid reply_to_message_id option");
+                    None
+                }
+            });
+
+        // Parse modes to retry with if Telegram rejects the message; markup
+        // escaped for one parse mode can't be reformatted for another
+        let fallback_modes = if preformatted {
+            Vec::new()
+        } else {
+            Self::extract_fallback_modes(options, &config, parse_mode)
+        };
 
         // Format the message
-        info!("Formatting message: {}", message);
+        if config.log_message_text() {
+            debug!(text = %message, "Formatting message");
+        } else {
+            debug!(length = message.len(), "Formatting message");
+        }
 
         // Apply different formatting based on parse mode
-        let formatted_message = match parse_mode {
-            Some("MarkdownV2") => {
-                // Use markdown formatting
-                let md_formatting_options = FormattingOptions {
-                    escape_markdown: true,
-                    escape_html: false,
-                    obfuscate_emails: formatting_options.obfuscate_emails,
-                    truncate: formatting_options.truncate,
-                };
-                Formatter::format(message, Some(md_formatting_options))?
-            }
-            Some("HTML") => {
-                // Use HTML formatting
-                let html_formatting_options = FormattingOptions {
-                    escape_markdown: false,
-                    escape_html: true,
-                    obfuscate_emails: formatting_options.obfuscate_emails,
-                    truncate: formatting_options.truncate,
-                };
-                Formatter::format(message, Some(html_formatting_options))?
-            }
-            _ => {
-                // Plain text, no special formatting
-                let plain_formatting_options = FormattingOptions {
-                    escape_markdown: false,
-                    escape_html: false,
-                    obfuscate_emails: formatting_options.obfuscate_emails,
-                    truncate: formatting_options.truncate,
-                };
-                Formatter::format(message, Some(plain_formatting_options))?
+        let formatted = if preformatted {
+            Ok(Cow::Borrowed(message))
+        } else {
+            Formatter::format(
+                message,
+                Some(Self::formatting_for(parse_mode, &formatting_options)),
+            )
+        };
+        let formatted_message = match formatted {
+            Ok(formatted_message) => formatted_message,
+            Err(e) => {
+                error!(error = %e, "Could not format message");
+                return self.fail_unsent(&config, target, message, e, started, requests_before);
             }
         };
 
-        info!("Formatted message: {}", formatted_message);
+        if config.log_message_text() {
+            debug!(text = %formatted_message, "Formatted message");
+        } else {
+            debug!(length = formatted_message.len(), "Formatted message");
+        }
 
         // Handle empty messages
         if formatted_message.is_empty() {
             error!("Message is empty after formatting");
-            return Err(Error::formatting("Message is empty after formatting"));
+            let e = Error::formatting("Message is empty after formatting");
+            return self.fail_unsent(&config, target, message, e, started, requests_before);
         }
 
-        let result = self.send_message_request(
-            bot_token,
-            chat_id,
-            &formatted_message,
-            parse_mode,
-            disable_web_page_preview,
-        );
+        // Telegram would reject a message over its limit anyway
+        if let Err(e) =
+            Formatter::check_length(&formatted_message, parse_mode, FormatContext::Message)
+        {
+            error!(error = %e, "Message is too long after formatting");
+            return self.fail_unsent(&config, target, message, e, started, requests_before);
+        }
+
+        // Suppress duplicates of a message sent within the idempotency window
+        let idempotency_window = config.client_options().idempotency_window;
+        let idempotency_key = match options.iter().find(|(k, _)| *k == "idempotency_key") {
+            Some((_, key)) => Some((
+                idempotency::explicit_key(chat_id, key),
+                idempotency_window.map_or(idempotency::DEFAULT_WINDOW, Duration::from_secs),
+            )),
+            // Hash the unformatted text, timestamps would make every message unique
+            None => idempotency_window.map(|window| {
+                (
+                    idempotency::content_key(chat_id, message),
+                    Duration::from_secs(window),
+                )
+            }),
+        };
+        if let Some((key, window)) = idempotency_key {
+            if !idempotency::claim(key, window) {
+                let original = match config.client_options().duplicate_strategy {
+                    DuplicateStrategy::Coalesce => idempotency::repeat(key),
+                    DuplicateStrategy::Suppress => None,
+                };
+                let Some(original) = original else {
+                    warn!("Duplicate message suppressed");
+                    stats::record_duplicate();
+                    return Err(Error::Duplicate);
+                };
 
-        // Try fallbacks if primary fails
-        match result {
-            Ok(response) => {
-                info!("Message sent successfully!");
-                Ok(response)
+                // Count the repeat on the original message instead of sending it again
+                let params = Self::edit_text_params(
+                    chat_id,
+                    original.message_id,
+                    &original.counted_text(),
+                    original.parse_mode,
+                    link_preview_options,
+                )?;
+                match self
+                    .execute_until(bot_token, "editMessageText", params, timeout, deadline)
+                    .await
+                {
+                    Ok(response) => {
+                        info!(count = original.count, "Duplicate message coalesced");
+                        stats::record_duplicate();
+                        return Ok(response);
+                    }
+                    Err(e) => {
+                        // E.g. the original was deleted; send the message anew
+                        warn!(error = %e, "Could not coalesce duplicate message, sending it again");
+                        idempotency::release(key);
+                        idempotency::claim(key, window);
+                    }
+                }
             }
-            Err(e) => {
-                // Log the error
-                error!("Error sending message with primary parse mode: {}", e);
+        }
 
-                // Extract status code if it's an API error
-                let should_try_plaintext = match &e {
-                    Error::Api(desc) => desc.contains("parse_mode"),
-                    _ => false,
-                };
+        // Merge messages sharing an aggregation key into a summary of the first one
+        let aggregation = options
+            .iter()
+            .find(|(k, _)| *k == "aggregation_key")
+            .map(|(_, key)| {
+                (
+                    aggregation::group_key(bot_token, chat_id, key),
+                    config.aggregation_window(key),
+                )
+            })
+            .filter(|(_, window)| !window.is_zero());
+        if let Some(aggregate) = aggregation.and_then(|(group, _)| aggregation::join(group)) {
+            let params = Self::edit_text_params(
+                chat_id,
+                aggregate.message_id,
+                &aggregate.summary(),
+                aggregate.parse_mode,
+                link_preview_options,
+            )?;
+            match self
+                .execute_until(bot_token, "editMessageText", params, timeout, deadline)
+                .await
+            {
+                Ok(response) => {
+                    info!(count = aggregate.count, "Message aggregated");
+                    return Ok(response);
+                }
+                Err(e) => {
+                    // E.g. the first message was deleted; start a new group
+                    warn!(error = %e, "Could not aggregate message, sending it again");
+                    if let Some((group, _)) = aggregation {
+                        aggregation::forget(group);
+                    }
+                }
+            }
+        }
+
+        // Keep runaway loops from flooding chats, reporting what was dropped
+        let client_options = config.client_options();
+        if let Some(limit) = client_options.max_messages_per_minute {
+            match throttle::acquire(limit, client_options.throttle_policy, deadline).await {
+                Ok(0) => {}
+                Ok(suppressed) => {
+                    let summary = format!(
+                        "⚠️ Flood throttle suppressed {} message{}",
+                        suppressed,
+                        if suppressed == 1 { "" } else { "s" }
+                    );
+                    let summary = self
+                        .send_message_request(
+                            bot_token,
+                            SendMessageParams {
+                                chat_id,
+                                message_thread_id,
+                                text: &summary,
+                                parse_mode: None,
+                                link_preview_options: None,
+                                disable_notification,
+                                reply_parameters: None,
+                            },
+                            timeout,
+                            deadline,
+                        )
+                        .await;
+                    if let Err(e) = summary {
+                        warn!(error = %e, suppressed, "Could not send the flood throttle summary");
+                    }
+                }
+                Err(e) => {
+                    warn!("Message suppressed by the flood throttle");
+                    if let Some((key, _)) = idempotency_key {
+                        idempotency::release(key);
+                    }
+                    let outcome = Err(e);
+                    stats::record_delivery(&outcome);
+                    let attempts = Self::requests_since(requests_before);
+                    Self::notify_receipt(&config, target, &outcome, started, attempts);
+                    return outcome;
+                }
+            }
+        }
 
-                if should_try_plaintext {
-                    // Try with plain text as fallback
-                    info!("Falling back to plain text format");
+        let mut delivered_text = formatted_message;
+        let mut outcome = self
+            .send_message_request(
+                bot_token,
+                SendMessageParams {
+                    chat_id,
+                    message_thread_id,
+                    text: &delivered_text,
+                    parse_mode: parse_mode.as_api_str(),
+                    link_preview_options,
+                    disable_notification,
+                    reply_parameters,
+                },
+                timeout,
+                deadline,
+            )
+            .await;
+        let mut delivered_mode = parse_mode;
 
-                    // Use the original message with minimal formatting
-                    let plain_formatting_options = FormattingOptions {
-                        escape_markdown: false,
-                        escape_html: false,
-                        obfuscate_emails: formatting_options.obfuscate_emails,
-                        truncate: formatting_options.truncate,
-                    };
+        // Walk the fallback chain while Telegram rejects the message
+        for mode in fallback_modes {
+            let Err(e) = &outcome else {
+                break;
+            };
+            if !Self::is_format_rejection(e) {
+                break;
+            }
+            // The send deadline covers the whole fallback chain
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                warn!("Send deadline exceeded, skipping parse mode fallbacks");
+                break;
+            }
 
-                    let plain_message = Formatter::format(message, Some(plain_formatting_options))?;
+            error!(error = %e, parse_mode = %delivered_mode, "Sending failed");
+            info!(fallback = %mode, "Falling back to another parse mode");
 
-                    self.send_message_request(
-                        bot_token,
-                        chat_id,
-                        &plain_message,
-                        None,
-                        disable_web_page_preview,
-                    )
-                } else if parse_mode == Some("MarkdownV2") {
-                    // Try with HTML as fallback
-                    info!("Falling back to HTML format");
-
-                    // Format message for HTML
-                    let html_formatting_options = FormattingOptions {
-                        escape_markdown: false,
-                        escape_html: true,
-                        obfuscate_emails: formatting_options.obfuscate_emails,
-                        truncate: formatting_options.truncate,
-                    };
-
-                    let html_message = Formatter::format(message, Some(html_formatting_options))?;
-
-                    match self.send_message_request(
-                        bot_token,
+            let fallback_message = match Formatter::format(
+                message,
+                Some(Self::formatting_for(mode, &formatting_options)),
+            ) {
+                Ok(fallback_message) => fallback_message,
+                Err(e) => {
+                    // Report the formatting failure like any other failed send
+                    outcome = Err(e);
+                    break;
+                }
+            };
+            outcome = self
+                .send_message_request(
+                    bot_token,
+                    SendMessageParams {
                         chat_id,
-                        &html_message,
-                        Some("HTML"),
-                        disable_web_page_preview,
-                    ) {
-                        Ok(response) => Ok(response),
-                        Err(html_error) => {
-                            // If HTML fails too, try plain text
-                            error!("Error sending message with HTML format: {}", html_error);
-                            info!("Falling back to plain text format");
-
-                            // Format message as plain text
-                            let plain_formatting_options = FormattingOptions {
-                                escape_markdown: false,
-                                escape_html: false,
-                                obfuscate_emails: formatting_options.obfuscate_emails,
-                                truncate: formatting_options.truncate,
-                            };
-
-                            let plain_message =
-                                Formatter::format(message, Some(plain_formatting_options))?;
-
-                            self.send_message_request(
-                                bot_token,
-                                chat_id,
-                                &plain_message,
-                                None,
-                                disable_web_page_preview,
-                            )
-                        }
+                        message_thread_id,
+                        text: &fallback_message,
+                        parse_mode: mode.as_api_str(),
+                        link_preview_options,
+                        disable_notification,
+                        reply_parameters,
+                    },
+                    timeout,
+                    deadline,
+                )
+                .await;
+            delivered_text = fallback_message;
+            delivered_mode = mode;
+        }
+
+        // Point entity parse errors at the caller's text rather than the escaped one
+        let mut outcome =
+            outcome.map_err(|e| Self::locate_bad_entities(e, &delivered_text, message, &config));
+
+        // Escalations and fallback deliveries below don't count as attempts
+        let attempts = Self::requests_since(requests_before);
+
+        // A quarantined message never reached its chat
+        if let (Some(reason), Ok(_)) = (quarantine, &outcome) {
+            outcome = Err(Error::Filtered {
+                reason,
+                quarantined: true,
+            });
+        }
+
+        match &outcome {
+            Ok(_) => {
+                info!(parse_mode = %delivered_mode, "Message sent");
+                if delivered_mode != parse_mode {
+                    if let Some(callback) = &self.on_fallback {
+                        callback(chat_id, parse_mode, delivered_mode);
                     }
-                } else {
-                    // Return the original error
-                    Err(e)
                 }
             }
+            Err(e) => {
+                error!(error = %e, "Sending failed");
+                self.errors.record(target, message, e);
+                if !matches!(e, Error::Filtered { .. }) {
+                    self.escalate(bot_token, target, e, &config, deadline).await;
+                    self.deliver_elsewhere(target, message, e);
+                }
+            }
+        }
+
+        if let (Some(callback), Err(e)) = (&self.on_stale_chat, &outcome) {
+            if let Some(reason) = e.stale_chat_reason() {
+                warn!(?reason, "Chat can no longer receive messages");
+                callback(chat_id, reason);
+            }
+        }
+
+        // Remember the delivered message for coalescing and aggregation
+        let message_id = outcome
+            .as_ref()
+            .ok()
+            .and_then(|response| response.result.as_ref())
+            .and_then(|message| message.get("message_id"))
+            .and_then(|id| id.as_i64());
+        if let Some(message_id) = message_id {
+            if let Some((group, window)) = aggregation {
+                let text = delivered_text.to_string();
+                aggregation::start(group, window, message_id, text, delivered_mode);
+            }
+            if let Some((key, _)) = idempotency_key.filter(|_| {
+                config.client_options().duplicate_strategy == DuplicateStrategy::Coalesce
+            }) {
+                idempotency::remember(key, message_id, delivered_text.into_owned(), delivered_mode);
+            }
         }
+
+        // Only network failures may have delivered the message; free the key otherwise
+        if let (Some((key, _)), Err(e)) = (idempotency_key, &outcome) {
+            if !matches!(e.kind(), "http" | "transport") {
+                idempotency::release(key);
+            }
+        }
+
+        Span::current().record(
+            "outcome",
+            match &outcome {
+                Ok(_) => "sent",
+                Err(e) => e.kind(),
+            },
+        );
+
+        stats::record_delivery(&outcome);
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_delivery(chat_id, &outcome);
+        Self::notify_receipt(&config, target, &outcome, started, attempts);
+
+        outcome
     }
 
-    /// Send a request to the Telegram API
-    fn send_message_request(
+    /// Report a send that failed before its message went out, like a failed delivery
+    fn fail_unsent(
         &self,
-        bot_token: &str,
-        chat_id: &str,
-        text: &str,
-        parse_mode: Option<&str>,
-        disable_web_page_preview: bool,
+        config: &Configuration,
+        target: &str,
+        message: &str,
+        error: Error,
+        started: Instant,
+        requests_before: u32,
     ) -> Result<Response, Error> {
-        // Build the API URL
-        let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
+        Span::current().record("outcome", error.kind());
+        self.errors.record(target, message, &error);
+        let outcome = Err(error);
+        stats::record_delivery(&outcome);
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_delivery(target, &outcome);
+        let attempts = Self::requests_since(requests_before);
+        Self::notify_receipt(config, target, &outcome, started, attempts);
+        outcome
+    }
 
-        // Handle parse_mode - Telegram API requires empty string or a valid mode, not null
-        // Based on API testing, null is not accepted but empty string is
-        #[allow(clippy::redundant_guards)]
-        let effective_parse_mode = match parse_mode {
-            Some(mode) if mode.is_empty() => Some(""),
-            Some(mode) if mode != "MarkdownV2" && mode != "HTML" => Some(""),
-            None => Some(""), // Use empty string instead of None (null)
-            other => other,
-        };
+    /// Bot API requests made by the current thread since it had made `before`
+    fn requests_since(before: u32) -> u32 {
+        REQUESTS.with(Cell::get).wrapping_sub(before)
+    }
 
-        // Prepare parameters
-        let params = SendMessageParams {
-            chat_id,
-            text,
-            parse_mode: effective_parse_mode,
-            disable_web_page_preview: Some(disable_web_page_preview),
+    /// Hand the receipt of a send to the callbacks registered on `config`
+    fn notify_receipt(
+        config: &Configuration,
+        chat_id: &str,
+        outcome: &Result<Response, Error>,
+        started: Instant,
+        attempts: u32,
+    ) {
+        let receipt = DeliveryReceipt {
+            chat_id: chat_id.to_string(),
+            message_id: outcome
+                .as_ref()
+                .ok()
+                .and_then(|response| response.result.as_ref())
+                .and_then(|message| message.get("message_id"))
+                .and_then(|id| id.as_i64()),
+            attempts,
+            latency: started.elapsed(),
         };
+        config.receipt_callbacks().notify(&receipt, outcome);
+    }
 
-        let response = match self.client.post(&url).json(&params).send() {
-            Ok(resp) => {
-                info!("Received response with status code: {}", resp.status());
-
-                // Read the response body as text for debugging
-                match resp.text() {
-                    Ok(_) => {
-                        // We need to re-send the request since we've consumed the body
-                        match self.client.post(&url).json(&params).send() {
-                            Ok(new_resp) => new_resp,
-                            Err(e) => {
-                                return Err(Error::Http(e));
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        // We need to re-send the request since we've consumed the response
-                        match self.client.post(&url).json(&params).send() {
-                            Ok(new_resp) => new_resp,
-                            Err(e) => {
-                                return Err(Error::Http(e));
-                            }
-                        }
-                    }
-                }
-            }
+    /// Count a failed delivery to `target` and notify the escalation chat if it fails too often
+    ///
+    /// Requests stop at the `deadline` of the failed send.
+    async fn escalate(
+        &self,
+        bot_token: &str,
+        target: &str,
+        error: &Error,
+        config: &Configuration,
+        deadline: Option<Instant>,
+    ) {
+        let Some(policy) = config
+            .escalation()
+            .filter(|policy| !policy.chat_id.is_empty())
+        else {
+            return;
+        };
+        let escalation_chat = [("chat_id", policy.chat_id.as_str())];
+        let chat_id = match self
+            .extract_chat_id_until(&escalation_chat, config, bot_token, deadline)
+            .await
+        {
+            Ok(chat_id) if chat_id != target => chat_id,
+            Ok(_) => return,
             Err(e) => {
-                error!("HTTP request failed: {}", e);
-                return Err(Error::Http(e));
+                warn!(error = %e, "Invalid escalation chat");
+                return;
             }
         };
+        let Some(text) = escalation::record_failure(policy, target, error) else {
+            return;
+        };
 
-        // Parse the response
-        info!("Processing response...");
-        self.handle_response(response)
+        warn!(escalation_chat = %chat_id, "Escalating repeated delivery failures");
+        let escalated = self
+            .send_message_request(
+                bot_token,
+                SendMessageParams {
+                    chat_id: &chat_id,
+                    message_thread_id: None,
+                    text: &text,
+                    parse_mode: None,
+                    link_preview_options: None,
+                    disable_notification: false,
+                    reply_parameters: None,
+                },
+                None,
+                deadline,
+            )
+            .await;
+        if let Err(e) = escalated {
+            error!(error = %e, "Escalation failed");
+        }
     }
 
-    /// Handle the API response
-    fn handle_response(&self, response: ReqwestResponse) -> Result<Response, Error> {
-        // Check HTTP status code
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
-                .unwrap_or_else(|_| "Unable to read response body".to_string());
-
-            error!("HTTP error status {}: {}", status.as_u16(), body);
-
-            return Err(Error::api(format!(
-                "HTTP error (status {}): {}",
-                status.as_u16(),
-                body
-            )));
-        }
-
-        // Parse the response as JSON
-        info!("Parsing response as JSON...");
-        let telegram_response: Response = match response.json() {
-            Ok(res) => {
-                info!("Successfully parsed response JSON");
-                res
+    /// Hand a message Telegram could not deliver to the fallback delivery, if any
+    fn deliver_elsewhere(&self, chat_id: &str, text: &str, error: &Error) {
+        let Some(delivery) = &self.fallback_delivery else {
+            return;
+        };
+        let failed = FailedDelivery {
+            chat_id,
+            text,
+            error,
+        };
+        match delivery.deliver(&failed) {
+            Ok(()) => info!("Message handed to the fallback delivery"),
+            Err(e) => error!(error = %e, "Fallback delivery failed"),
+        }
+    }
+
+    /// Parameters of an `editMessageText` request with already formatted `text`
+    fn edit_text_params(
+        chat_id: &str,
+        message_id: i64,
+        text: &str,
+        parse_mode: ParseMode,
+        link_preview_options: Option<&LinkPreviewOptions>,
+    ) -> Result<serde_json::Value, Error> {
+        let mut params = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "text": text,
+        });
+        if let Some(mode) = parse_mode.as_api_str() {
+            params["parse_mode"] = mode.into();
+        }
+        if let Some(link_preview) = link_preview_options {
+            params["link_preview_options"] = serde_json::to_value(link_preview)
+                .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        }
+        Ok(params)
+    }
+
+    /// Resolve an `@username` chat target to its numeric ID through a cached `getChat`
+    ///
+    /// Other targets are returned unchanged. If the lookup fails the username is
+    /// used as-is, which Telegram accepts for public channels.
+    async fn resolve_username<'a>(
+        &self,
+        bot_token: &str,
+        chat_id: &'a str,
+        deadline: Option<Instant>,
+    ) -> Cow<'a, str> {
+        if !resolver::is_username(chat_id) {
+            return Cow::Borrowed(chat_id);
+        }
+        if let Some(resolved) = resolver::cached(bot_token, chat_id) {
+            return Cow::Owned(resolved);
+        }
+
+        let resolved = self
+            .cached_lookup(bot_token, "getChat", chat_id, deadline)
+            .await
+            .and_then(|chat| {
+                chat.get("id")
+                    .and_then(|id| id.as_i64())
+                    .map(|id| id.to_string())
+                    .ok_or_else(|| Error::api("getChat response without a chat ID"))
+            });
+
+        match resolved {
+            Ok(resolved) => {
+                debug!(username = chat_id, chat_id = %resolved, "Resolved chat username");
+                resolver::store(bot_token, chat_id, resolved.clone());
+                Cow::Owned(resolved)
             }
             Err(e) => {
-                error!("Failed to parse API response: {}", e);
-                return Err(Error::api(format!("Failed to parse API response: {}", e)));
+                warn!(username = chat_id, error = %e, "Could not resolve chat username");
+                Cow::Borrowed(chat_id)
             }
-        };
+        }
+    }
 
-        // Check if the API returned an error
-        if !telegram_response.ok {
-            let description = telegram_response
-                .description
-                .unwrap_or_else(|| "Unknown API error".to_string());
+    /// Send many messages concurrently, returning their results in input order
+    ///
+    /// Uses the default [`BulkOptions`]; see [`Client::send_messages_with`].
+    pub fn send_messages(&self, messages: Vec<OutgoingMessage>) -> Vec<Result<Response, Error>> {
+        self.send_messages_with(messages, &BulkOptions::default())
+    }
 
-            error!("Telegram API returned error: {}", description);
-            return Err(Error::api(description));
-        }
+    /// Send many messages on up to `concurrency` threads sharing one rate limit
+    ///
+    /// Messages to the same chat are sent one after another in input order;
+    /// different chats are sent to in parallel. Each message goes through
+    /// [`Client::send_message`], including retries, with the configuration in
+    /// effect for the calling thread. Flood control errors pause all workers.
+    pub fn send_messages_with(
+        &self,
+        messages: Vec<OutgoingMessage>,
+        options: &BulkOptions,
+    ) -> Vec<Result<Response, Error>> {
+        let count = messages.len();
+        let queue = Mutex::new(bulk::group_by_chat(messages));
+        let limiter = RateLimiter::new(options.messages_per_second);
+        let results = Mutex::new((0..count).map(|_| None).collect::<Vec<_>>());
+        let config = Configuration::snapshot();
+        let workers = options.concurrency.max(1).min(count);
 
-        info!("Request was successful (ok=true)");
-        Ok(telegram_response)
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    // Workers send with the caller's scoped configuration
+                    let _guard = Configuration::push_snapshot(Arc::clone(&config));
+                    loop {
+                        let Some(group) = queue
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .pop_front()
+                        else {
+                            break;
+                        };
+                        for (index, message) in group {
+                            limiter.wait();
+                            let result = message.send_with(self);
+                            if let Err(Error::RateLimited { retry_after }) = &result {
+                                limiter.pause(Duration::from_secs(*retry_after));
+                            }
+                            results.lock().unwrap_or_else(PoisonError::into_inner)[index] =
+                                Some(result);
+                        }
+                    }
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(Error::other("Message was not sent"))))
+            .collect()
     }
 
-    /// Extract formatting options from the options array
-    fn extract_formatting_options(
+    /// Send `text` followed by [`Formatter::mention`]s of `users`, so each of them is notified
+    ///
+    /// Mentions that don't fit next to the text in one message are sent in
+    /// further messages, each repeating the text. Accepts the options of
+    /// [`Client::send_message`]. The text is formatted like a message's, the
+    /// mentions are appended as built, so they are neither escaped again nor
+    /// filtered; parse mode fallbacks don't apply.
+    pub fn notify_users<S: AsRef<str>>(
         &self,
+        text: &str,
+        users: &[(i64, S)],
         options: &[(&str, &str)],
-        default_formatting_options: FormattingOptions,
-    ) -> FormattingOptions {
-        // Start with defaults
-        let mut formatting_options = default_formatting_options;
+    ) -> Result<Vec<Response>, Error> {
+        let config = Configuration::snapshot();
+        let parse_mode = Self::extract_parse_mode(options, &config);
 
-        // Override with any provided options
-        for (key, value) in options {
-            match *key {
-                "escape_markdown" => {
-                    formatting_options.escape_markdown = value.to_lowercase() == "true";
-                }
+        // Room left for mentions once the text is formatted and decorated
+        let formatting_options = Self::extract_formatting_options(
+            options,
+            FormattingOptions::from(config.formatting_options()),
+        );
+        let max_length = formatting_options
+            .truncate
+            .map_or(MAX_MESSAGE_LENGTH, |truncate| {
+                truncate.min(MAX_MESSAGE_LENGTH)
+            });
+        let formatted = Formatter::format(
+            text,
+            Some(Self::formatting_for(parse_mode, &formatting_options)),
+        )?;
+        let room = max_length.saturating_sub(utf16_len(&formatted) + 1).max(1);
+
+        Formatter::mentions(users, parse_mode, room)
+            .into_iter()
+            .map(|mentions| {
+                let message = if formatted.is_empty() {
+                    mentions
+                } else {
+                    format!("{}\n{}", formatted, mentions)
+                };
+                transport::block_on(self.send_text(&message, options, true))
+            })
+            .collect()
+    }
+
+    /// Send a text too long for one message as several, split by [`Formatter::split_message`]
+    ///
+    /// Each part goes through [`Client::send_message`] with `options`.
+    /// `split_mode` chooses where parts end: `compact` (the default) fills them,
+    /// `lines` splits at blank lines and line breaks and numbers the parts like
+    /// `(2/5)`. Sending stops at the first part that fails.
+    ///
+    /// ```no_run
+    /// use telegrama_rs::Client;
+    ///
+    /// let report = std::fs::read_to_string("incident.log")?;
+    /// Client::new().send_long_message(&report, &[("split_mode", "lines")])?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn send_long_message(
+        &self,
+        text: &str,
+        options: &[(&str, &str)],
+    ) -> Result<Vec<Response>, Error> {
+        let config = Configuration::snapshot();
+        let parse_mode = Self::extract_parse_mode(options, &config);
+        let formatting_options = Self::extract_formatting_options(
+            options,
+            FormattingOptions::from(config.formatting_options()),
+        );
+        let mode = options
+            .iter()
+            .find(|(k, _)| *k == "split_mode")
+            .and_then(|(_, v)| match v.parse::<SplitMode>() {
+                Ok(mode) => Some(mode),
+                Err(e) => {
+                    warn!(error = %e, "Ignoring invalid split_mode option");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let parts = Formatter::split_message(
+            text,
+            Some(Self::formatting_for(parse_mode, &formatting_options)),
+            FormatContext::Message,
+            mode,
+        )?;
+        // `truncate_lines` applied to the whole text already
+        let mut options = options.to_vec();
+        options.push(("truncate_lines", "0"));
+        parts
+            .iter()
+            .map(|part| self.send_message(part, &options))
+            .collect()
+    }
+
+    /// Replace the text of a message sent by the bot
+    ///
+    /// The text goes through the same formatting as [`Client::send_message`],
+    /// without the format fallbacks. Accepts the `chat_id`, `bot`, `parse_mode`,
+    /// link preview and formatting options of [`Client::send_message`].
+    pub fn edit_message_text(
+        &self,
+        message_id: i64,
+        text: &str,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        config.validate()?;
+
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = self.extract_chat_id(options, &config, bot_token)?;
+        let parse_mode = Self::extract_parse_mode(options, &config);
+        let formatting_options = Self::extract_formatting_options(
+            options,
+            FormattingOptions::from(config.formatting_options()),
+        );
+        let link_preview =
+            Self::extract_link_preview_options(options, config.link_preview_options());
+
+        let original = text;
+        let text = Formatter::format(
+            original,
+            Some(Self::formatting_for(parse_mode, &formatting_options)),
+        )?;
+        if text.is_empty() {
+            return Err(Error::formatting("Message is empty after formatting"));
+        }
+        Formatter::check_length(&text, parse_mode, FormatContext::Message)?;
+
+        let mut params = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "text": text,
+        });
+        if let Some(mode) = parse_mode.as_api_str() {
+            params["parse_mode"] = mode.into();
+        }
+        if !link_preview.is_empty() {
+            params["link_preview_options"] = serde_json::to_value(&link_preview)
+                .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        }
+
+        self.execute(bot_token, "editMessageText", params, None)
+            .map_err(|e| Self::locate_bad_entities(e, &text, original, &config))
+    }
+
+    /// Replace the caption of a media message
+    ///
+    /// The caption goes through the same formatting as messages (prefix,
+    /// suffix, escaping for the parse mode) and is truncated to
+    /// [`MAX_CAPTION_LENGTH`](crate::formatter::MAX_CAPTION_LENGTH). Accepts the
+    /// `chat_id`, `bot`, `parse_mode` and formatting options of
+    /// [`Client::send_message`].
+    pub fn edit_message_caption(
+        &self,
+        message_id: i64,
+        caption: &str,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        config.validate()?;
+
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = self.extract_chat_id(options, &config, bot_token)?;
+        let parse_mode = Self::extract_parse_mode(options, &config);
+        let formatting_options = Self::extract_formatting_options(
+            options,
+            FormattingOptions::from(config.formatting_options()),
+        );
+
+        let caption = Formatter::format_caption(
+            caption,
+            Some(Self::formatting_for(parse_mode, &formatting_options)),
+        )?;
+
+        let mut params = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "caption": caption,
+        });
+        if let Some(mode) = parse_mode.as_api_str() {
+            params["parse_mode"] = mode.into();
+        }
+
+        self.execute(bot_token, "editMessageCaption", params, None)
+    }
+
+    /// Replace the photo, video or document of a media message
+    ///
+    /// The media's caption is formatted like in [`Client::edit_message_caption`];
+    /// without one, the message loses its caption. Accepts the same options.
+    pub fn edit_message_media(
+        &self,
+        message_id: i64,
+        media: &InputMedia,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        config.validate()?;
+
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = self.extract_chat_id(options, &config, bot_token)?;
+
+        let mut input_media = serde_json::json!({
+            "type": media.kind.as_str(),
+            "media": media.media,
+        });
+        if let Some(caption) = &media.caption {
+            let parse_mode = Self::extract_parse_mode(options, &config);
+            let formatting_options = Self::extract_formatting_options(
+                options,
+                FormattingOptions::from(config.formatting_options()),
+            );
+            let caption = Formatter::format_caption(
+                caption,
+                Some(Self::formatting_for(parse_mode, &formatting_options)),
+            )?;
+
+            input_media["caption"] = caption.into_owned().into();
+            if let Some(mode) = parse_mode.as_api_str() {
+                input_media["parse_mode"] = mode.into();
+            }
+        }
+
+        let params = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "media": input_media,
+        });
+
+        self.execute(bot_token, "editMessageMedia", params, None)
+    }
+
+    /// Upload a file as a general document
+    ///
+    /// Accepts the `chat_id`, `bot`, `caption`, `parse_mode`, `message_thread_id`,
+    /// `disable_notification` and formatting options of [`Client::send_message`];
+    /// the caption is formatted like in [`Client::edit_message_caption`]. With
+    /// `caption_overflow` set to `reply`, a caption over
+    /// [`MAX_CAPTION_LENGTH`](crate::formatter::MAX_CAPTION_LENGTH) is split
+    /// instead of truncated, and the rest sent as replies to the file. A
+    /// [thumbnail](InputFile::with_thumbnail) is uploaded along. The file is
+    /// streamed into the request, [`InputFile::with_progress`] follows the
+    /// upload:
+    ///
+    /// ```no_run
+    /// use telegrama_rs::{Client, InputFile};
+    ///
+    /// let report = InputFile::from_path("backup.tar.gz")?.with_progress(|sent, total| {
+    ///     eprint!("\rUploading {}%", sent * 100 / total.max(1));
+    /// });
+    /// Client::new().send_document(&report, &[("caption", "Nightly backup")])?;
+    /// # Ok::<(), telegrama_rs::Error>(())
+    /// ```
+    pub fn send_document(
+        &self,
+        document: &InputFile,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        self.send_file("sendDocument", "document", document, options)
+    }
+
+    /// Upload a video, accepting the options of [`Client::send_document`]
+    pub fn send_video(
+        &self,
+        video: &InputFile,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        self.send_file("sendVideo", "video", video, options)
+    }
+
+    /// Upload an audio file shown in the music player, accepting the options of [`Client::send_document`]
+    pub fn send_audio(
+        &self,
+        audio: &InputFile,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        self.send_file("sendAudio", "audio", audio, options)
+    }
+
+    /// Upload `file` as the `field` parameter of a send `method`
+    fn send_file(
+        &self,
+        method: &str,
+        field: &str,
+        file: &InputFile,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        config.validate()?;
+
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = self.extract_chat_id(options, &config, bot_token)?;
+        let mut params = serde_json::json!({ "chat_id": chat_id.as_ref() });
+
+        let mut overflow = None;
+        if let Some((_, caption)) = options.iter().find(|(k, _)| *k == "caption") {
+            let parse_mode = Self::extract_parse_mode(options, &config);
+            let formatting_options = Self::extract_formatting_options(
+                options,
+                FormattingOptions::from(config.formatting_options()),
+            );
+            let formatting = Some(Self::formatting_for(parse_mode, &formatting_options));
+            let caption = if options
+                .iter()
+                .any(|(k, v)| *k == "caption_overflow" && *v == "reply")
+            {
+                let (caption, rest) =
+                    Formatter::split_for(caption, formatting.clone(), FormatContext::Caption)?;
+                overflow = rest.map(|rest| (rest, formatting));
+                caption
+            } else {
+                Formatter::format_caption(caption, formatting)?.into_owned()
+            };
+
+            params["caption"] = caption.into();
+            if let Some(mode) = parse_mode.as_api_str() {
+                params["parse_mode"] = mode.into();
+            }
+        }
+        if let Some(thread_id) = Self::extract_message_thread_id(options, &config) {
+            params["message_thread_id"] = thread_id.into();
+        }
+        if options
+            .iter()
+            .any(|(k, v)| *k == "disable_notification" && v.to_lowercase() == "true")
+        {
+            params["disable_notification"] = true.into();
+        }
+
+        let mut files = vec![(field.to_string(), file.clone())];
+        // Thumbnails can only be uploaded, as a part referenced by name
+        if let Some(thumbnail) = file.validated_thumbnail()? {
+            params["thumbnail"] = "attach://thumbnail".into();
+            files.push(("thumbnail".to_string(), thumbnail));
+        }
+        let deadline = Self::deadline_from_now(&config);
+        let response = transport::block_on(self.execute_with_files(
+            bot_token,
+            method,
+            params.clone(),
+            &files,
+            None,
+            deadline,
+        ))?;
+
+        // The rest of a long caption follows as replies, split to fit messages
+        if let Some((rest, formatting)) = overflow {
+            let reply_to = response
+                .result
+                .as_ref()
+                .and_then(|result| result.get("message_id"))
+                .and_then(serde_json::Value::as_i64);
+            // The file was sent, so a failed reply must not make callers send it again
+            if let Err(e) = self.send_overflow(bot_token, &params, &rest, formatting, reply_to) {
+                warn!(error = %e, "Failed to send the overflow of a caption");
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Send the unformatted overflow of a caption as messages replying to `reply_to`
+    ///
+    /// The chat, parse mode, forum topic and notification setting are taken
+    /// from the `params` the file was sent with.
+    fn send_overflow(
+        &self,
+        bot_token: &str,
+        params: &serde_json::Value,
+        overflow: &str,
+        formatting: Option<FormattingOptions>,
+        reply_to: Option<i64>,
+    ) -> Result<(), Error> {
+        let parts = Formatter::split_message(
+            overflow,
+            formatting.clone(),
+            FormatContext::Message,
+            SplitMode::Compact,
+        )?;
+        for part in parts {
+            let mut reply = serde_json::json!({
+                "chat_id": params["chat_id"],
+                "text": Formatter::format(&part, formatting.clone())?,
+            });
+            for key in ["parse_mode", "message_thread_id", "disable_notification"] {
+                if let Some(value) = params.get(key) {
+                    reply[key] = value.clone();
+                }
+            }
+            if let Some(message_id) = reply_to {
+                reply["reply_parameters"] = serde_json::json!({ "message_id": message_id });
+            }
+            self.execute(bot_token, "sendMessage", reply, None)?;
+        }
+        Ok(())
+    }
+
+    /// Delete a message
+    ///
+    /// Accepts the `chat_id` and `bot` options. Bots can delete their own
+    /// messages for 48 hours, and other messages in chats where they may.
+    pub fn delete_message(
+        &self,
+        message_id: i64,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let params = serde_json::json!({ "message_id": message_id });
+        self.execute_in_chat("deleteMessage", params, options)
+    }
+
+    /// Pin a message, notifying the chat members unless `disable_notification`
+    ///
+    /// Accepts the `chat_id` and `bot` options; in groups and channels the bot
+    /// needs the `can_pin_messages` right.
+    pub fn pin_chat_message(
+        &self,
+        message_id: i64,
+        disable_notification: bool,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let params = serde_json::json!({
+            "message_id": message_id,
+            "disable_notification": disable_notification,
+        });
+        self.execute_in_chat("pinChatMessage", params, options)
+    }
+
+    /// Send a message like [`Client::send_message`] and return a handle to it
+    ///
+    /// The handle edits, deletes, pins or replies to the message without
+    /// passing its chat and message ID around.
+    ///
+    /// ```no_run
+    /// use telegrama_rs::Client;
+    ///
+    /// let client = Client::new();
+    /// let status = client.send_message_with_handle("Deploying v1.4...", &[])?;
+    /// status.edit("Deployed v1.4")?;
+    /// status.reply("Changelog: ...")?;
+    /// # Ok::<(), telegrama_rs::Error>(())
+    /// ```
+    pub fn send_message_with_handle(
+        &self,
+        message: &str,
+        options: &[(&str, &str)],
+    ) -> Result<MessageHandle<'_>, Error> {
+        let response = self.send_message(message, options)?;
+        let config = Configuration::snapshot();
+        let bot = Self::extract_bot_name(options, &config)?.map(str::to_string);
+        MessageHandle::from_response(self, &response, bot)
+    }
+
+    /// Acknowledge an inline keyboard button press
+    ///
+    /// Telegram clients show a progress indicator until the query is answered.
+    /// `text` is shown as a notification, or as an alert with `show_alert`.
+    pub fn answer_callback_query(
+        &self,
+        callback_query_id: &str,
+        text: Option<&str>,
+        show_alert: bool,
+        url: Option<&str>,
+        cache_time: Option<u32>,
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        let params = AnswerCallbackQueryParams {
+            callback_query_id,
+            text,
+            show_alert,
+            url,
+            cache_time,
+        };
+        let body = serde_json::to_value(&params)
+            .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        self.execute(bot_token, "answerCallbackQuery", body, None)
+    }
+
+    /// Send an invoice for a payment
+    ///
+    /// Accepts the `chat_id` and `bot` options. Payments must be confirmed with
+    /// [`Client::answer_pre_checkout_query`] before the user is charged.
+    pub fn send_invoice(
+        &self,
+        invoice: &Invoice,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let params = serde_json::to_value(invoice)
+            .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        self.execute_in_chat("sendInvoice", params, options)
+    }
+
+    /// Accept or refuse the shipping address of a flexible invoice
+    ///
+    /// With `ok`, `shipping_options` lists the available methods; otherwise
+    /// `error_message` tells the user why the address can't be served.
+    pub fn answer_shipping_query(
+        &self,
+        shipping_query_id: &str,
+        ok: bool,
+        shipping_options: &[ShippingOption],
+        error_message: Option<&str>,
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        let mut params = serde_json::json!({
+            "shipping_query_id": shipping_query_id,
+            "ok": ok,
+        });
+        if ok {
+            params["shipping_options"] = serde_json::to_value(shipping_options)
+                .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        }
+        if let Some(error_message) = error_message {
+            params["error_message"] = error_message.into();
+        }
+
+        self.execute(bot_token, "answerShippingQuery", params, None)
+    }
+
+    /// Confirm or cancel a payment before the user is charged
+    ///
+    /// Telegram cancels the payment if no answer arrives within 10 seconds.
+    /// Without `ok`, `error_message` tells the user why, e.g. that the item sold out.
+    pub fn answer_pre_checkout_query(
+        &self,
+        pre_checkout_query_id: &str,
+        ok: bool,
+        error_message: Option<&str>,
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        let mut params = serde_json::json!({
+            "pre_checkout_query_id": pre_checkout_query_id,
+            "ok": ok,
+        });
+        if let Some(error_message) = error_message {
+            params["error_message"] = error_message.into();
+        }
+
+        self.execute(bot_token, "answerPreCheckoutQuery", params, None)
+    }
+
+    /// Create a topic in a forum supergroup, e.g. one per incident
+    ///
+    /// Send messages to the topic with the `message_thread_id` option of
+    /// [`Client::send_message`]. Accepts the `chat_id` and `bot` options; the bot
+    /// needs the `can_manage_topics` right.
+    pub fn create_forum_topic(
+        &self,
+        name: &str,
+        icon_color: Option<TopicIconColor>,
+        icon_custom_emoji_id: Option<&str>,
+        options: &[(&str, &str)],
+    ) -> Result<ForumTopic, Error> {
+        let config = Configuration::snapshot();
+        config.validate()?;
+
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = self.extract_chat_id(options, &config, bot_token)?;
+
+        let mut params = serde_json::json!({
+            "chat_id": chat_id,
+            "name": name,
+        });
+        if let Some(color) = icon_color {
+            params["icon_color"] = color.rgb().into();
+        }
+        if let Some(emoji_id) = icon_custom_emoji_id {
+            params["icon_custom_emoji_id"] = emoji_id.into();
+        }
+
+        let response = self.execute(bot_token, "createForumTopic", params, None)?;
+        let result = response
+            .result
+            .ok_or_else(|| Error::api("createForumTopic response without a topic"))?;
+
+        serde_json::from_value(result)
+            .map_err(|e| Error::api(format!("Invalid createForumTopic response: {}", e)))
+    }
+
+    /// Rename a forum topic or change its icon
+    ///
+    /// `None` keeps the current value; an empty `icon_custom_emoji_id` removes
+    /// the icon. Accepts the same options as [`Client::create_forum_topic`].
+    pub fn edit_forum_topic(
+        &self,
+        message_thread_id: i64,
+        name: Option<&str>,
+        icon_custom_emoji_id: Option<&str>,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let mut params = serde_json::json!({ "message_thread_id": message_thread_id });
+        if let Some(name) = name {
+            params["name"] = name.into();
+        }
+        if let Some(emoji_id) = icon_custom_emoji_id {
+            params["icon_custom_emoji_id"] = emoji_id.into();
+        }
+
+        self.execute_in_chat("editForumTopic", params, options)
+    }
+
+    /// Close a forum topic, e.g. once its incident is resolved
+    ///
+    /// Accepts the same options as [`Client::create_forum_topic`].
+    pub fn close_forum_topic(
+        &self,
+        message_thread_id: i64,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let params = serde_json::json!({ "message_thread_id": message_thread_id });
+        self.execute_in_chat("closeForumTopic", params, options)
+    }
+
+    /// Reopen a closed forum topic
+    ///
+    /// Accepts the same options as [`Client::create_forum_topic`].
+    pub fn reopen_forum_topic(
+        &self,
+        message_thread_id: i64,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let params = serde_json::json!({ "message_thread_id": message_thread_id });
+        self.execute_in_chat("reopenForumTopic", params, options)
+    }
+
+    /// Ban a user from a group, supergroup or channel
+    ///
+    /// The ban lasts until the Unix time `until_date`, or forever without one
+    /// (or if it's less than 30 seconds or more than 366 days away).
+    /// `revoke_messages` deletes all of the user's messages in the chat. Accepts
+    /// the `chat_id` and `bot` options; the bot needs the `can_restrict_members` right.
+    pub fn ban_chat_member(
+        &self,
+        user_id: i64,
+        until_date: Option<i64>,
+        revoke_messages: bool,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let mut params = serde_json::json!({ "user_id": user_id });
+        if let Some(until_date) = until_date {
+            params["until_date"] = until_date.into();
+        }
+        if revoke_messages {
+            params["revoke_messages"] = true.into();
+        }
+
+        self.execute_in_chat("banChatMember", params, options)
+    }
+
+    /// Lift the ban of a user, who can then join again through a link
+    ///
+    /// With `only_if_banned`, members that aren't banned are left alone; without
+    /// it, unbanning a current member removes them from the chat. Accepts the
+    /// same options as [`Client::ban_chat_member`].
+    pub fn unban_chat_member(
+        &self,
+        user_id: i64,
+        only_if_banned: bool,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let mut params = serde_json::json!({ "user_id": user_id });
+        if only_if_banned {
+            params["only_if_banned"] = true.into();
+        }
+
+        self.execute_in_chat("unbanChatMember", params, options)
+    }
+
+    /// Restrict what a member of a supergroup may do, e.g. mute them
+    ///
+    /// The restriction lasts until the Unix time `until_date`, or forever
+    /// without one. Accepts the same options as [`Client::ban_chat_member`].
+    pub fn restrict_chat_member(
+        &self,
+        user_id: i64,
+        permissions: &ChatPermissions,
+        until_date: Option<i64>,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let mut params = serde_json::json!({
+            "user_id": user_id,
+            "permissions": permissions,
+        });
+        if let Some(until_date) = until_date {
+            params["until_date"] = until_date.into();
+        }
+
+        self.execute_in_chat("restrictChatMember", params, options)
+    }
+
+    /// Grant a member administrator rights, or demote them with no rights
+    ///
+    /// Accepts the `chat_id` and `bot` options; the bot needs the
+    /// `can_promote_members` right and can only grant rights it has itself.
+    pub fn promote_chat_member(
+        &self,
+        user_id: i64,
+        rights: &ChatAdministratorRights,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let mut params = serde_json::to_value(rights)
+            .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        params["user_id"] = user_id.into();
+
+        self.execute_in_chat("promoteChatMember", params, options)
+    }
+
+    /// Set the default permissions of all members of a group or supergroup
+    ///
+    /// Accepts the same options as [`Client::ban_chat_member`].
+    pub fn set_chat_permissions(
+        &self,
+        permissions: &ChatPermissions,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let params = serde_json::json!({ "permissions": permissions });
+        self.execute_in_chat("setChatPermissions", params, options)
+    }
+
+    /// Look up a member of a chat, e.g. to check admin rights before a privileged command
+    ///
+    /// Accepts the `chat_id` and `bot` options. The bot must be an administrator
+    /// to look up members other than itself in most chats.
+    pub fn get_chat_member(
+        &self,
+        user_id: i64,
+        options: &[(&str, &str)],
+    ) -> Result<ChatMember, Error> {
+        let params = serde_json::json!({ "user_id": user_id });
+        let response = self.execute_in_chat("getChatMember", params, options)?;
+        Self::parse_result("getChatMember", response)
+    }
+
+    /// Number of members of a chat
+    ///
+    /// Accepts the same options as [`Client::get_chat_member`].
+    pub fn get_chat_member_count(&self, options: &[(&str, &str)]) -> Result<u64, Error> {
+        let response =
+            self.execute_in_chat("getChatMemberCount", serde_json::json!({}), options)?;
+        Self::parse_result("getChatMemberCount", response)
+    }
+
+    /// Create an additional invite link, e.g. a single-use link for onboarding
+    ///
+    /// Accepts the `chat_id` and `bot` options; the bot needs the
+    /// `can_invite_users` right.
+    pub fn create_chat_invite_link(
+        &self,
+        link: &InviteLinkOptions,
+        options: &[(&str, &str)],
+    ) -> Result<ChatInviteLink, Error> {
+        let params = serde_json::to_value(link)
+            .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        let response = self.execute_in_chat("createChatInviteLink", params, options)?;
+        Self::parse_result("createChatInviteLink", response)
+    }
+
+    /// Replace the settings of an invite link created by the bot
+    ///
+    /// Accepts the same options as [`Client::create_chat_invite_link`].
+    pub fn edit_chat_invite_link(
+        &self,
+        invite_link: &str,
+        link: &InviteLinkOptions,
+        options: &[(&str, &str)],
+    ) -> Result<ChatInviteLink, Error> {
+        let mut params = serde_json::to_value(link)
+            .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        params["invite_link"] = invite_link.into();
+        let response = self.execute_in_chat("editChatInviteLink", params, options)?;
+        Self::parse_result("editChatInviteLink", response)
+    }
+
+    /// Revoke an invite link created by the bot
+    ///
+    /// Revoking the primary link creates a new one. Accepts the same options as
+    /// [`Client::create_chat_invite_link`].
+    pub fn revoke_chat_invite_link(
+        &self,
+        invite_link: &str,
+        options: &[(&str, &str)],
+    ) -> Result<ChatInviteLink, Error> {
+        let params = serde_json::json!({ "invite_link": invite_link });
+        let response = self.execute_in_chat("revokeChatInviteLink", params, options)?;
+        Self::parse_result("revokeChatInviteLink", response)
+    }
+
+    /// Generate a new primary invite link, revoking the previous one
+    ///
+    /// Accepts the same options as [`Client::create_chat_invite_link`].
+    pub fn export_chat_invite_link(&self, options: &[(&str, &str)]) -> Result<String, Error> {
+        let response =
+            self.execute_in_chat("exportChatInviteLink", serde_json::json!({}), options)?;
+        Self::parse_result("exportChatInviteLink", response)
+    }
+
+    /// Execute a method acting on the chat given by the `chat_id` option or configuration
+    ///
+    /// The resolved `chat_id` is added to `params`; the `bot` option selects the
+    /// bot profile.
+    fn execute_in_chat(
+        &self,
+        method: &str,
+        mut params: serde_json::Value,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        config.validate()?;
+
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = self.extract_chat_id(options, &config, bot_token)?;
+        params["chat_id"] = chat_id.as_ref().into();
+
+        self.execute(bot_token, method, params, None)
+    }
+
+    /// Information about the bot, cached for a few minutes
+    ///
+    /// Accepts the `bot` option to describe a named bot profile.
+    pub fn get_me(&self, options: &[(&str, &str)]) -> Result<User, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let deadline = Self::deadline_from_now(&config);
+        let result = transport::block_on(self.cached_lookup(bot_token, "getMe", "", deadline))?;
+        serde_json::from_value(result)
+            .map_err(|e| Error::api(format!("Invalid getMe response: {}", e)))
+    }
+
+    /// Information about a chat, e.g. its title, cached for a few minutes
+    ///
+    /// Accepts the `chat_id` and `bot` options; `@username` targets are looked
+    /// up directly.
+    pub fn get_chat(&self, options: &[(&str, &str)]) -> Result<Chat, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = Self::extract_bot_token(options, &config)?;
+        let chat_id = config.resolve_chat(Self::chat_target(options, &config)?);
+        let deadline = Self::deadline_from_now(&config);
+        let result =
+            transport::block_on(self.cached_lookup(bot_token, "getChat", chat_id, deadline))?;
+        serde_json::from_value(result)
+            .map_err(|e| Error::api(format!("Invalid getChat response: {}", e)))
+    }
+
+    /// Result of an idempotent lookup of `target` (`chat_id` of `getChat`), from the cache if fresh
+    async fn cached_lookup(
+        &self,
+        bot_token: &str,
+        method: &'static str,
+        target: &str,
+        deadline: Option<Instant>,
+    ) -> Result<serde_json::Value, Error> {
+        if let Some(result) = self.cache.get(bot_token, method, target) {
+            debug!(method, "Using cached result");
+            return Ok(result);
+        }
+
+        let params = if target.is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::json!({ "chat_id": target })
+        };
+        let result = self
+            .execute_until(bot_token, method, params, None, deadline)
+            .await?
+            .result
+            .ok_or_else(|| Error::api(format!("{} response without a result", method)))?;
+        self.cache.store(bot_token, method, target, result.clone());
+        Ok(result)
+    }
+
+    /// Deserialize the result of a successful `method` call
+    fn parse_result<T: DeserializeOwned>(method: &str, response: Response) -> Result<T, Error> {
+        let result = response
+            .result
+            .ok_or_else(|| Error::api(format!("{} response without a result", method)))?;
+
+        serde_json::from_value(result)
+            .map_err(|e| Error::api(format!("Invalid {} response: {}", method, e)))
+    }
+
+    /// Ask Telegram to push updates to `url` (HTTPS) instead of long polling
+    pub fn set_webhook(&self, url: &str, options: &WebhookOptions) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        let mut params = serde_json::json!({ "url": url });
+        if let Some(ip_address) = &options.ip_address {
+            params["ip_address"] = ip_address.as_str().into();
+        }
+        if let Some(max_connections) = options.max_connections {
+            params["max_connections"] = max_connections.into();
+        }
+        if let Some(allowed_updates) = &options.allowed_updates {
+            params["allowed_updates"] = allowed_updates.clone().into();
+        }
+        if options.drop_pending_updates {
+            params["drop_pending_updates"] = true.into();
+        }
+        if let Some(secret_token) = &options.secret_token {
+            params["secret_token"] = secret_token.expose_secret().into();
+        }
+
+        // A self-signed certificate is uploaded with the request
+        let files: Vec<(String, InputFile)> = options
+            .certificate
+            .iter()
+            .map(|certificate| ("certificate".to_string(), certificate.clone()))
+            .collect();
+
+        let deadline = Self::deadline_from_now(&config);
+        transport::block_on(self.execute_with_files(
+            bot_token,
+            "setWebhook",
+            params,
+            &files,
+            None,
+            deadline,
+        ))
+    }
+
+    /// Remove the webhook, optionally dropping updates that weren't delivered yet
+    pub fn delete_webhook(&self, drop_pending_updates: bool) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        let mut params = serde_json::json!({});
+        if drop_pending_updates {
+            params["drop_pending_updates"] = true.into();
+        }
+
+        self.execute(bot_token, "deleteWebhook", params, None)
+    }
+
+    /// Get the current webhook and its delivery status
+    ///
+    /// The `url` is empty when no webhook is set.
+    pub fn get_webhook_info(&self) -> Result<WebhookInfo, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        let response = self.execute(bot_token, "getWebhookInfo", serde_json::json!({}), None)?;
+        let result = response
+            .result
+            .ok_or_else(|| Error::api("getWebhookInfo response without a result"))?;
+
+        serde_json::from_value(result)
+            .map_err(|e| Error::api(format!("Invalid getWebhookInfo response: {}", e)))
+    }
+
+    /// Get the download path of a file received in an update
+    ///
+    /// Telegram keeps the returned path valid for at least an hour.
+    pub fn get_file(&self, file_id: &str) -> Result<File, Error> {
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        let params = serde_json::json!({ "file_id": file_id });
+        let response = self.execute(bot_token, "getFile", params, None)?;
+        let result = response
+            .result
+            .ok_or_else(|| Error::api("getFile response without a file"))?;
+
+        serde_json::from_value(result)
+            .map_err(|e| Error::api(format!("Invalid getFile response: {}", e)))
+    }
+
+    /// Download a file into `writer`, returning the number of bytes written
+    ///
+    /// `file_path` is [`File::file_path`]. Absolute paths, returned by a local
+    /// Bot API server in `--local` mode, are read directly from disk.
+    pub fn download_file<W: std::io::Write>(
+        &self,
+        file_path: &str,
+        writer: &mut W,
+    ) -> Result<u64, Error> {
+        if std::path::Path::new(file_path).is_absolute() {
+            let mut file = std::fs::File::open(file_path)
+                .map_err(|e| Error::other(format!("Failed to open file '{}': {}", file_path, e)))?;
+            return std::io::copy(&mut file, writer)
+                .map_err(|e| Error::other(format!("Failed to copy file '{}': {}", file_path, e)));
+        }
+
+        let config = Configuration::snapshot();
+        let bot_token = config.resolve_bot_token(None)?;
+
+        debug!(file_path, "Downloading file");
+        self.transport
+            .download(bot_token, file_path, writer)
+            .map_err(|e| e.redact(bot_token))
+    }
+
+    /// Check that the Bot API is reachable and the bot token is valid
+    ///
+    /// Performs a single `getMe` request with a 5 second timeout, without retries
+    /// or middleware, so it can back an application's `/healthz` endpoint.
+    pub fn health_check(&self) -> HealthStatus {
+        let config = Configuration::snapshot();
+        let bot_token = match config.resolve_bot_token(None) {
+            Ok(token) => token,
+            Err(e) => return HealthStatus::Misconfigured(e.to_string()),
+        };
+
+        let body = RequestBody::Json(serde_json::json!({}));
+        let raw = match self.transport.execute_with_timeout(
+            bot_token,
+            "getMe",
+            body,
+            HEALTH_CHECK_TIMEOUT,
+        ) {
+            Ok(raw) => raw,
+            Err(e) => return HealthStatus::NetworkError(e.redact(bot_token).to_string()),
+        };
+
+        let status = raw.status;
+        match Self::handle_response(raw) {
+            Ok(response) => HealthStatus::Reachable {
+                username: response
+                    .result
+                    .as_ref()
+                    .and_then(|bot| bot.get("username"))
+                    .and_then(|username| username.as_str())
+                    .map(str::to_string),
+            },
+            Err(Error::RateLimited { retry_after }) => HealthStatus::RateLimited { retry_after },
+            Err(_) if status == 401 => HealthStatus::Unauthorized,
+            Err(e) => HealthStatus::ApiError(e.redact(bot_token).to_string()),
+        }
+    }
+
+    /// Send a request to the Telegram API
+    async fn send_message_request(
+        &self,
+        bot_token: &str,
+        mut params: SendMessageParams<'_>,
+        timeout: Option<Duration>,
+        deadline: Option<Instant>,
+    ) -> Result<Response, Error> {
+        // Handle parse_mode - Telegram API requires empty string or a valid mode, not null
+        // Based on API testing, null is not accepted but empty string is
+        #[allow(clippy::redundant_guards)]
+        let effective_parse_mode = match params.parse_mode {
+            Some(mode) if mode.is_empty() => Some(""),
+            Some(mode) if !matches!(mode, "MarkdownV2" | "Markdown" | "HTML") => Some(""),
+            None => Some(""), // Use empty string instead of None (null)
+            other => other,
+        };
+        params.parse_mode = effective_parse_mode;
+
+        let body = serde_json::to_value(&params)
+            .map_err(|e| Error::other(format!("Failed to serialize request: {}", e)))?;
+        self.execute_until(bot_token, "sendMessage", body, timeout, deadline)
+            .await
+    }
+
+    /// Execute a Bot API method through the registered middleware
+    ///
+    /// The call gets the whole `send_deadline`, if any.
+    fn execute(
+        &self,
+        bot_token: &str,
+        method: &str,
+        params: serde_json::Value,
+        timeout: Option<Duration>,
+    ) -> Result<Response, Error> {
+        let deadline = Self::deadline_from_now(&Configuration::snapshot());
+        transport::block_on(self.execute_until(bot_token, method, params, timeout, deadline))
+    }
+
+    /// Execute a Bot API method through the registered middleware, giving up at `deadline`
+    async fn execute_until(
+        &self,
+        bot_token: &str,
+        method: &str,
+        params: serde_json::Value,
+        timeout: Option<Duration>,
+        deadline: Option<Instant>,
+    ) -> Result<Response, Error> {
+        self.execute_with_files(bot_token, method, params, &[], timeout, deadline)
+            .await
+    }
+
+    /// End of the `send_deadline` of a call starting now
+    fn deadline_from_now(config: &Configuration) -> Option<Instant> {
+        config
+            .client_options()
+            .send_deadline
+            .map(|seconds| Instant::now() + Duration::from_secs(seconds))
+    }
+
+    /// Execute a Bot API method uploading `files`, through the registered middleware
+    ///
+    /// Without files, the parameters are sent as JSON; otherwise as
+    /// `multipart/form-data`. Local files are passed by path to a local Bot API
+    /// server. Middleware and the debug log only see `params`.
+    #[instrument(
+        name = "telegrama.request",
+        level = "debug",
+        skip(self, bot_token, params, files, timeout, deadline)
+    )]
+    async fn execute_with_files(
+        &self,
+        bot_token: &str,
+        method: &str,
+        mut params: serde_json::Value,
+        files: &[(String, InputFile)],
+        timeout: Option<Duration>,
+        deadline: Option<Instant>,
+    ) -> Result<Response, Error> {
+        // A local Bot API server opens co-located files itself
+        let mut uploads = Vec::with_capacity(files.len());
+        for (name, file) in files {
+            match file.local_uri() {
+                Some(uri) if self.transport.is_local_server() => params[name.as_str()] = uri.into(),
+                _ => uploads.push((name.clone(), file.clone())),
+            }
+        }
+        let files = uploads.as_slice();
+
+        let vetoed = self
+            .middleware
+            .iter()
+            .try_for_each(|middleware| middleware.on_before_send(method, &mut params));
+        let result = match vetoed {
+            Ok(()) => {
+                self.execute_with_retries(bot_token, method, params, files, timeout, deadline)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        // Custom transports and middleware may echo the URL or token in errors
+        let result = result.map_err(|e| e.redact(bot_token));
+
+        for middleware in &self.middleware {
+            match &result {
+                Ok(response) => middleware.on_after_send(method, response),
+                Err(e) => middleware.on_error(method, e),
+            }
+        }
+
+        result
+    }
+
+    /// Execute a Bot API method, retrying transient failures
+    ///
+    /// Each attempt sends the request exactly once. Network errors, 5xx responses
+    /// and flood control (429) are retried up to `ClientOptions::retry_count`
+    /// times, waiting as long as the `backoff` strategy says or the `retry_after`
+    /// Telegram asks for.
+    /// A `timeout` replaces the transport's request timeout for every attempt.
+    /// With a `deadline`, attempts are cut short and retries given up so that
+    /// the call never ends later.
+    async fn execute_with_retries(
+        &self,
+        bot_token: &str,
+        method: &str,
+        params: serde_json::Value,
+        files: &[(String, InputFile)],
+        timeout: Option<Duration>,
+        deadline: Option<Instant>,
+    ) -> Result<Response, Error> {
+        let config = Configuration::snapshot();
+        let client_options = config.client_options();
+        let mut attempt = 0;
+
+        loop {
+            // Never wait for a response past the deadline
+            let timeout = match deadline {
+                Some(deadline) => Some(
+                    timeout
+                        .unwrap_or(Duration::from_secs(client_options.request_timeout))
+                        .min(deadline.saturating_duration_since(Instant::now())),
+                ),
+                None => timeout,
+            };
+            let body = if files.is_empty() {
+                RequestBody::Json(params.clone())
+            } else {
+                RequestBody::Multipart {
+                    params: params.clone(),
+                    files: files.to_vec(),
+                }
+            };
+            let sent_at = time::system_now();
+            let started = Instant::now();
+            REQUESTS.with(|requests| requests.set(requests.get().wrapping_add(1)));
+            let outcome = self
+                .transport
+                .execute_async(bot_token, method, body, timeout)
+                .await;
+            let elapsed = started.elapsed();
+            debug!(
+                method,
+                attempt,
+                latency_ms = elapsed.as_millis() as u64,
+                status = outcome.as_ref().ok().map(|raw| raw.status),
+                "Bot API request finished"
+            );
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_latency(method, elapsed);
+            if let Some(log) = &self.debug_log {
+                log.record(bot_token, method, &params, &outcome, sent_at, elapsed);
+            }
+
+            let backoff = || {
+                client_options.backoff.delay(
+                    attempt + 1,
+                    Duration::from_secs(client_options.retry_delay),
+                    Duration::from_secs(client_options.max_retry_delay),
+                )
+            };
+            let retry_delay = match &outcome {
+                Err(_) => Some(backoff()),
+                Ok(raw) if raw.status == 429 => {
+                    stats::record_rate_limit();
+                    Some(
+                        Self::parse_response(raw)
+                            .ok()
+                            .and_then(|response| response.parameters)
+                            .and_then(|parameters| parameters.retry_after)
+                            .map_or_else(backoff, Duration::from_secs),
+                    )
+                }
+                Ok(raw) if raw.status >= 500 => Some(backoff()),
+                Ok(_) => None,
+            };
+
+            match retry_delay {
+                Some(delay) if attempt < client_options.retry_count => {
+                    // Give up early rather than sleep past the deadline
+                    if deadline.is_some_and(|deadline| Instant::now() + delay >= deadline) {
+                        warn!(
+                            delay_secs = delay.as_secs_f64(),
+                            "Retry would exceed the send deadline, giving up"
+                        );
+                        return outcome.and_then(Self::handle_response);
+                    }
+                    attempt += 1;
+                    stats::record_retry();
+                    warn!(
+                        attempt,
+                        delay_secs = delay.as_secs_f64(),
+                        "Request failed, retrying"
+                    );
+                    time::sleep(delay).await;
+                }
+                _ => {
+                    return outcome.and_then(Self::handle_response);
+                }
+            }
+        }
+    }
+
+    /// Parse a buffered response body as a Telegram API response
+    fn parse_response(response: &RawResponse) -> Result<Response, serde_json::Error> {
+        serde_json::from_str(&response.body)
+    }
+
+    /// Handle the API response
+    ///
+    /// The body is buffered once and reused for logging, status checks and JSON
+    /// parsing. Telegram returns JSON with a description for most errors, so the
+    /// body is parsed regardless of the HTTP status.
+    fn handle_response(response: RawResponse) -> Result<Response, Error> {
+        // Sent messages are echoed back, so the body is only logged on request
+        if Configuration::snapshot().log_message_text() {
+            debug!(status = response.status, body = %response.body, "Received response");
+        } else {
+            debug!(
+                status = response.status,
+                length = response.body.len(),
+                "Received response"
+            );
+        }
+
+        let telegram_response = match Self::parse_response(&response) {
+            Ok(res) => res,
+            Err(e) if response.is_success() => {
+                error!(error = %e, "Failed to parse API response");
+                return Err(Error::api(format!("Failed to parse API response: {}", e)));
+            }
+            Err(_) => {
+                // Not a Bot API error (e.g. a proxy error page)
+                error!(status = response.status, body = %response.body, "HTTP error");
+                return Err(Error::api(format!(
+                    "HTTP error (status {}): {}",
+                    response.status, response.body
+                )));
+            }
+        };
+
+        // Check if the API returned an error
+        if !telegram_response.ok || !response.is_success() {
+            if let Some(retry_after) = telegram_response
+                .parameters
+                .as_ref()
+                .and_then(|parameters| parameters.retry_after)
+            {
+                error!(retry_after, "Telegram API rate limit hit");
+                return Err(Error::RateLimited { retry_after });
+            }
+
+            let description = telegram_response
+                .description
+                .unwrap_or_else(|| format!("HTTP error (status {})", response.status));
+
+            error!(status = response.status, %description, "Telegram API returned an error");
+            return Err(Error::api(description));
+        }
+
+        debug!("Request was successful");
+        Ok(telegram_response)
+    }
+
+    /// Extract link preview options from the options array
+    ///
+    /// `disable_web_page_preview` is accepted as an alias of `link_preview_disabled`.
+    fn extract_link_preview_options(
+        options: &[(&str, &str)],
+        mut link_preview: LinkPreviewOptions,
+    ) -> LinkPreviewOptions {
+        let flag = |value: &str| Some(value.to_lowercase() == "true");
+
+        for (key, value) in options {
+            match *key {
+                "disable_web_page_preview" | "link_preview_disabled" => {
+                    link_preview.is_disabled = flag(value);
+                }
+                "link_preview_url" => link_preview.url = Some(value.to_string()),
+                "link_preview_prefer_small_media" => {
+                    link_preview.prefer_small_media = flag(value);
+                }
+                "link_preview_prefer_large_media" => {
+                    link_preview.prefer_large_media = flag(value);
+                }
+                "link_preview_show_above_text" => link_preview.show_above_text = flag(value),
+                _ => {}
+            }
+        }
+
+        link_preview
+    }
+
+    /// Bot token of the `bot` option's profile, or the default token
+    fn extract_bot_token<'a>(
+        options: &[(&str, &str)],
+        config: &'a Configuration,
+    ) -> Result<&'a str, Error> {
+        config.resolve_bot_token(Self::extract_bot_name(options, config)?)
+    }
+
+    /// Bot profile of the `bot` option or the `route` option's route, if any
+    fn extract_bot_name<'a>(
+        options: &[(&'a str, &'a str)],
+        config: &'a Configuration,
+    ) -> Result<Option<&'a str>, Error> {
+        if let Some((_, bot)) = options.iter().find(|(k, _)| *k == "bot") {
+            return Ok(Some(bot));
+        }
+        Ok(Self::extract_route(options, config)?.and_then(|route| route.bot.as_deref()))
+    }
+
+    /// Route named by the `route` option
+    fn extract_route<'a>(
+        options: &[(&str, &str)],
+        config: &'a Configuration,
+    ) -> Result<Option<&'a Route>, Error> {
+        options
+            .iter()
+            .find(|(k, _)| *k == "route")
+            .map(|(_, name)| config.route(name))
+            .transpose()
+    }
+
+    /// Target chat of the `chat_id` option or the configuration
+    ///
+    /// Chat aliases are resolved by name, then @usernames to numeric IDs.
+    fn extract_chat_id<'a>(
+        &self,
+        options: &[(&'a str, &'a str)],
+        config: &'a Configuration,
+        bot_token: &str,
+    ) -> Result<Cow<'a, str>, Error> {
+        let deadline = Self::deadline_from_now(config);
+        transport::block_on(self.extract_chat_id_until(options, config, bot_token, deadline))
+    }
+
+    /// [`Client::extract_chat_id`], looking usernames up before `deadline`
+    async fn extract_chat_id_until<'a>(
+        &self,
+        options: &[(&'a str, &'a str)],
+        config: &'a Configuration,
+        bot_token: &str,
+        deadline: Option<Instant>,
+    ) -> Result<Cow<'a, str>, Error> {
+        let chat_id = Self::chat_target(options, config)?;
+        Ok(self
+            .resolve_username(bot_token, config.resolve_chat(chat_id), deadline)
+            .await)
+    }
+
+    /// Forum topic of the `message_thread_id` option or the `route` option's route
+    fn extract_message_thread_id(options: &[(&str, &str)], config: &Configuration) -> Option<i64> {
+        match options.iter().find(|(k, _)| *k == "message_thread_id") {
+            Some((_, v)) => match v.parse::<i64>() {
+                Ok(thread_id) => Some(thread_id),
+                Err(_) => {
+                    warn!(value = %v, "Ignoring invalid message_thread_id option");
+                    None
+                }
+            },
+            None => Self::extract_route(options, config)
+                .ok()
+                .flatten()
+                .and_then(|route| route.message_thread_id),
+        }
+    }
+
+    /// Whether Telegram rejected a message in a way another parse mode may fix
+    ///
+    /// Only a bad request may be fixed by another format, not a gone chat, a
+    /// permission or a network error.
+    fn is_format_rejection(error: &Error) -> bool {
+        matches!(error, Error::Api(description) if description.starts_with("Bad Request"))
+            && error.stale_chat_reason().is_none()
+    }
+
+    /// Chat of the `chat_id` option or the configuration, before alias and username resolution
+    fn chat_target<'a>(
+        options: &[(&'a str, &'a str)],
+        config: &'a Configuration,
+    ) -> Result<&'a str, Error> {
+        // Determine chat ID (options override the route, which overrides config)
+        let route_chat = Self::extract_route(options, config)?.map(|route| route.chat_id.as_str());
+        let chat_id = options
+            .iter()
+            .find(|(k, _)| *k == "chat_id")
+            .map(|(_, v)| *v)
+            .or(route_chat)
+            .unwrap_or_else(|| {
+                // Use default chat ID as fallback
+                match config.chat_id() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        // This shouldn't happen due to validation, but handle it anyway
+                        error!("No chat ID provided in options or configuration");
+                        ""
+                    }
+                }
+            });
+
+        if chat_id.is_empty() {
+            return Err(Error::configuration("Chat ID not provided"));
+        }
+
+        Ok(chat_id)
+    }
+
+    /// Parse mode of the `parse_mode` option or the configuration
+    pub(crate) fn extract_parse_mode(
+        options: &[(&str, &str)],
+        config: &Configuration,
+    ) -> ParseMode {
+        match options
+            .iter()
+            .find(|(k, _)| *k == "parse_mode")
+            .map(|(_, v)| *v)
+            .or_else(|| config.default_parse_mode())
+        {
+            Some("MarkdownV2") => ParseMode::MarkdownV2,
+            Some("Markdown") => ParseMode::MarkdownLegacy,
+            Some("HTML") => ParseMode::Html,
+            // Send invalid parse modes as plain text to avoid API errors
+            _ => ParseMode::Plain,
+        }
+    }
+
+    /// Turn an entity parse error for `formatted` into [`Error::BadEntities`] about `original`
+    fn locate_bad_entities(
+        error: Error,
+        formatted: &str,
+        original: &str,
+        config: &Configuration,
+    ) -> Error {
+        let Error::Api(description) = &error else {
+            return error;
+        };
+        formatter::bad_entities(
+            description,
+            formatted,
+            original,
+            config.message_prefix().unwrap_or_default(),
+            config.message_suffix().unwrap_or_default(),
+        )
+        .unwrap_or(error)
+    }
+
+    /// Formatting options for a message sent in the given parse mode
+    pub(crate) fn formatting_for(
+        mode: ParseMode,
+        options: &FormattingOptions,
+    ) -> FormattingOptions {
+        match mode {
+            ParseMode::MarkdownV2 => FormattingOptions {
+                escape_markdown: true,
+                escape_html: false,
+                sanitize_html: false,
+                ..options.clone()
+            },
+            // Sanitize instead of escaping if requested
+            ParseMode::Html => FormattingOptions {
+                escape_markdown: false,
+                escape_html: !options.sanitize_html,
+                ..options.clone()
+            },
+            // Legacy Markdown templates are written pre-escaped
+            ParseMode::MarkdownLegacy | ParseMode::Plain => FormattingOptions {
+                escape_markdown: false,
+                escape_html: false,
+                sanitize_html: false,
+                ..options.clone()
+            },
+        }
+    }
+
+    /// Parse modes to retry a rejected message with, in order
+    ///
+    /// A `fallback_chain` (option or configuration) wins over the `fallback_policy`.
+    fn extract_fallback_modes(
+        options: &[(&str, &str)],
+        config: &Configuration,
+        parse_mode: ParseMode,
+    ) -> Vec<ParseMode> {
+        let option = |name: &str| options.iter().find(|(k, _)| *k == name).map(|(_, v)| *v);
+
+        let chain = option("fallback_chain")
+            .and_then(|chain| {
+                chain
+                    .split(',')
+                    .filter(|mode| !mode.trim().is_empty())
+                    .map(str::parse::<ParseMode>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| warn!(error = %e, "Ignoring invalid fallback_chain option"))
+                    .ok()
+            })
+            .or_else(|| config.fallback_chain().map(<[ParseMode]>::to_vec));
+
+        let chain = chain.unwrap_or_else(|| {
+            let policy = option("fallback_policy")
+                .and_then(|policy| {
+                    policy
+                        .parse::<FallbackPolicy>()
+                        .map_err(|e| warn!(error = %e, "Ignoring invalid fallback_policy option"))
+                        .ok()
+                })
+                .unwrap_or_else(|| config.fallback_policy());
+
+            match (policy, parse_mode) {
+                (FallbackPolicy::Strict, _) => Vec::new(),
+                (FallbackPolicy::Auto, ParseMode::MarkdownV2 | ParseMode::MarkdownLegacy) => {
+                    vec![ParseMode::Html, ParseMode::Plain]
+                }
+                (FallbackPolicy::Auto | FallbackPolicy::PlainOnly, _) => vec![ParseMode::Plain],
+            }
+        });
+
+        // Never retry in a mode that was already tried
+        let mut modes = Vec::with_capacity(chain.len());
+        for mode in chain {
+            if mode != parse_mode && !modes.contains(&mode) {
+                modes.push(mode);
+            }
+        }
+        modes
+    }
+
+    /// Extract formatting options from the options array
+    pub(crate) fn extract_formatting_options(
+        options: &[(&str, &str)],
+        default_formatting_options: FormattingOptions,
+    ) -> FormattingOptions {
+        // Start with defaults
+        let mut formatting_options = default_formatting_options;
+
+        // Override with any provided options
+        for (key, value) in options {
+            match *key {
+                "escape_markdown" => {
+                    formatting_options.escape_markdown = value.to_lowercase() == "true";
+                }
                 "obfuscate_emails" => {
                     formatting_options.obfuscate_emails = value.to_lowercase() == "true";
                 }
                 "escape_html" => {
                     formatting_options.escape_html = value.to_lowercase() == "true";
                 }
+                "sanitize_html" => {
+                    formatting_options.sanitize_html = value.to_lowercase() == "true";
+                }
                 "truncate" => {
                     formatting_options.truncate = value.parse::<usize>().ok();
                 }
+                "truncation_suffix" => {
+                    formatting_options.truncation_suffix = value.to_string();
+                }
+                "truncate_lines" => {
+                    formatting_options.truncate_lines =
+                        value.parse::<usize>().ok().filter(|&lines| lines > 0);
+                }
+                "truncate_mode" => match value.parse() {
+                    Ok(mode) => formatting_options.truncate_mode = mode,
+                    Err(e) => warn!(error = %e, "Ignoring invalid truncate_mode option"),
+                },
+                "scrub_secrets" => {
+                    formatting_options.scrub_secrets = value.to_lowercase() == "true";
+                }
                 _ => {}
             }
         }
@@ -413,3 +2735,84 @@ impl Client {
         formatting_options
     }
 }
+
+/// Non-blocking [`Client`], for async code and WASM targets
+///
+/// Messages go through the pipeline of [`Client::send_message`], awaited
+/// instead of waited for: formatting, parse mode fallbacks, retries and
+/// backoff, throttling, duplicate handling, aggregation, content filters,
+/// escalation, username lookups, callbacks and receipts all apply. Waits
+/// don't block the executor. Meant for async code that must not block and
+/// for WASM targets such as edge workers, where the `wasm` feature sends with
+/// `fetch`.
+///
+/// [`AsyncClient::new`] and [`AsyncClient::with_transport`] send through an
+/// [`AsyncReqwestTransport`]; any configured [`Client`] converts into an
+/// `AsyncClient` to keep its middleware, callbacks and transport. Blocking
+/// transports (e.g. ureq) block the task while a request is made.
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+#[derive(Clone)]
+pub struct AsyncClient {
+    client: Arc<Client>,
+}
+
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl Default for AsyncClient {
+    fn default() -> Self {
+        Self::with_transport(AsyncReqwestTransport::default())
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl std::fmt::Debug for AsyncClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncClient").finish_non_exhaustive()
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl From<Client> for AsyncClient {
+    fn from(client: Client) -> Self {
+        AsyncClient {
+            client: Arc::new(client),
+        }
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl AsyncClient {
+    /// Create a client honoring the [`ClientOptions`] of the current configuration
+    pub fn new() -> Result<Self, Error> {
+        let config = Configuration::snapshot();
+        AsyncReqwestTransport::from_options(config.client_options()).map(Self::with_transport)
+    }
+
+    /// Create a client sending through `transport`
+    pub fn with_transport(transport: AsyncReqwestTransport) -> Self {
+        Self::from(Client::with_transport(transport))
+    }
+
+    /// Send a message to the Telegram API
+    ///
+    /// Takes the options of [`Client::send_message`] and runs in the same
+    /// `telegrama.send_message` span. The configuration in effect when the
+    /// send starts applies until it's done, whichever thread polls it.
+    ///
+    /// ```no_run
+    /// # async fn notify() -> Result<(), telegrama_rs::Error> {
+    /// use telegrama_rs::AsyncClient;
+    ///
+    /// AsyncClient::new()?
+    ///     .send_message("Edge deploy finished", &[("parse_mode", "HTML")])
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_message(
+        &self,
+        message: &str,
+        options: &[(&str, &str)],
+    ) -> Result<Response, Error> {
+        self.client.send_text(message, options, false).await
+    }
+}
@@ -0,0 +1,335 @@
+//! Bot commands such as `/status` or `/mute 1h`.
+//!
+//! [`CommandDispatcher`] picks commands out of incoming messages, runs the
+//! handler registered for them and sends the handler's reply back to the chat
+//! through the [`Client`]. It is an [`UpdateHandler`], so it can be passed to a
+//! [`Webhook`](crate::Webhook) directly.
+//!
+//! ```
+//! use std::time::Duration;
+//! use telegrama_rs::command::CommandDispatcher;
+//! use telegrama_rs::Client;
+//!
+//! let dispatcher = CommandDispatcher::new(Client::new())
+//!     .with_bot_username("alert_bot")
+//!     .command("status", |_| Ok(Some("All systems operational".to_string())))
+//!     .command("mute", |ctx| {
+//!         let duration: Duration = ctx.command.duration_arg(0)?;
+//!         Ok(Some(format!("Muted for {} minutes", duration.as_secs() / 60)))
+//!     });
+//! ```
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::types::{Message, Update};
+use crate::update::UpdateHandler;
+
+/// Result of a command handler: the reply to send, if any
+pub type CommandResult = Result<Option<String>, Error>;
+
+/// Handler registered for a command
+type CommandHandler = Arc<dyn Fn(&CommandContext<'_>) -> CommandResult + Send + Sync>;
+
+/// Handler of text messages that aren't commands
+type MessageHandler = Arc<dyn Fn(&Message) -> CommandResult + Send + Sync>;
+
+/// A command parsed from a message text, e.g. `/mute@alert_bot 1h`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    name: String,
+    bot_username: Option<String>,
+    args: String,
+}
+
+impl Command {
+    /// Parse a message text starting with a command
+    ///
+    /// Returns `None` if the text doesn't start with `/` followed by a valid
+    /// command name (letters, digits and underscores, at most 32 characters).
+    pub fn parse(text: &str) -> Option<Self> {
+        let rest = text.strip_prefix('/')?;
+        let (head, args) = match rest.find(char::is_whitespace) {
+            Some(end) => (&rest[..end], rest[end..].trim()),
+            None => (rest, ""),
+        };
+        let (name, bot_username) = match head.split_once('@') {
+            Some((name, bot)) => (name, Some(bot)),
+            None => (head, None),
+        };
+
+        let valid_name = !name.is_empty()
+            && name.len() <= 32
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name || bot_username.is_some_and(str::is_empty) {
+            return None;
+        }
+
+        Some(Command {
+            name: name.to_ascii_lowercase(),
+            bot_username: bot_username.map(str::to_string),
+            args: args.to_string(),
+        })
+    }
+
+    /// Command name in lowercase, without the `/`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Bot the command was addressed to with `/command@bot`
+    pub fn bot_username(&self) -> Option<&str> {
+        self.bot_username.as_deref()
+    }
+
+    /// Everything after the command, trimmed
+    pub fn args(&self) -> &str {
+        &self.args
+    }
+
+    /// Whitespace-separated argument at `index`
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.split_whitespace().nth(index)
+    }
+
+    /// Parse the argument at `index`, e.g. as a number
+    pub fn parse_arg<T>(&self, index: usize) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let arg = self.required_arg(index)?;
+        arg.parse()
+            .map_err(|e| Error::other(format!("Invalid argument '{}': {}", arg, e)))
+    }
+
+    /// Parse the argument at `index` as a duration, see [`parse_duration`]
+    pub fn duration_arg(&self, index: usize) -> Result<Duration, Error> {
+        parse_duration(self.required_arg(index)?)
+    }
+
+    fn required_arg(&self, index: usize) -> Result<&str, Error> {
+        self.arg(index).ok_or_else(|| {
+            Error::other(format!("Missing argument {} of /{}", index + 1, self.name))
+        })
+    }
+}
+
+/// Parse a duration such as `90s`, `15m`, `1h`, `2d` or `1h30m`
+///
+/// A bare number is taken as seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, Error> {
+    let invalid = || Error::other(format!("Invalid duration '{}', use e.g. 30m or 1h", value));
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let amount: u64 = number.parse().map_err(|_| invalid())?;
+        total = amount
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || value.trim().is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// What a command handler gets to work with
+pub struct CommandContext<'a> {
+    /// The parsed command
+    pub command: Command,
+    /// The message carrying the command
+    pub message: &'a Message,
+    client: &'a Client,
+}
+
+impl CommandContext<'_> {
+    /// Client the dispatcher replies with, for calls beyond the reply
+    pub fn client(&self) -> &Client {
+        self.client
+    }
+
+    /// Whether the sender is an owner or administrator of the chat
+    ///
+    /// Looks the sender up with [`Client::get_chat_member`], so privileged
+    /// commands like `/silence` can be refused to regular members. Messages
+    /// sent on behalf of a chat have no sender and are never from an admin.
+    pub fn sender_is_admin(&self) -> Result<bool, Error> {
+        let Some(sender) = &self.message.from else {
+            return Ok(false);
+        };
+        let chat_id = self.message.chat.id.to_string();
+        let member = self
+            .client
+            .get_chat_member(sender.id, &[("chat_id", chat_id.as_str())])?;
+        Ok(member.is_admin())
+    }
+}
+
+/// Runs registered handlers for commands in incoming messages
+///
+/// Commands addressed to another bot (`/status@other_bot`) and commands without
+/// a handler are ignored. A handler's reply is sent to the chat of the message;
+/// if the handler fails, its error message is sent instead.
+pub struct CommandDispatcher {
+    client: Client,
+    bot_username: Option<String>,
+    handlers: HashMap<String, CommandHandler>,
+    on_message: Option<MessageHandler>,
+}
+
+impl CommandDispatcher {
+    /// Create a dispatcher replying through `client`
+    pub fn new(client: Client) -> Self {
+        CommandDispatcher {
+            client,
+            bot_username: None,
+            handlers: HashMap::new(),
+            on_message: None,
+        }
+    }
+
+    /// Username of the bot, without the `@`
+    ///
+    /// Needed in groups with several bots, so commands addressed to the others
+    /// are ignored.
+    pub fn with_bot_username<S: AsRef<str>>(mut self, username: S) -> Self {
+        self.bot_username = Some(username.as_ref().trim_start_matches('@').to_string());
+        self
+    }
+
+    /// Register the handler of a command, replacing any previous one
+    ///
+    /// `name` is matched case-insensitively, with or without the leading `/`.
+    pub fn command<F>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Fn(&CommandContext<'_>) -> CommandResult + Send + Sync + 'static,
+    {
+        let name = name.trim_start_matches('/').to_ascii_lowercase();
+        self.handlers.insert(name, Arc::new(handler));
+        self
+    }
+
+    /// Handle text messages that aren't commands, e.g. answers in a conversation
+    ///
+    /// The reply is sent like a command's. See [`Conversations`](crate::Conversations)
+    /// to remember what was asked.
+    pub fn on_message<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Message) -> CommandResult + Send + Sync + 'static,
+    {
+        self.on_message = Some(Arc::new(handler));
+        self
+    }
+
+    /// Names of the registered commands
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    /// Run the handler of the command or text message in an update, if any
+    ///
+    /// Returns whether a handler ran. Fails if the reply couldn't be sent.
+    pub fn dispatch(&self, update: &Update) -> Result<bool, Error> {
+        let Some(message) = update.message.as_ref().or(update.channel_post.as_ref()) else {
+            return Ok(false);
+        };
+        let Some(text) = message.text.as_deref() else {
+            return Ok(false);
+        };
+
+        let reply = match Command::parse(text) {
+            Some(command) => match self.run_command(command, message) {
+                Some(reply) => reply,
+                None => return Ok(false),
+            },
+            None => match &self.on_message {
+                Some(handler) => {
+                    debug!(chat_id = message.chat.id, "Running message handler");
+                    handler(message).unwrap_or_else(|e| {
+                        warn!(error = %e, "Message handler failed");
+                        Some(e.to_string())
+                    })
+                }
+                None => return Ok(false),
+            },
+        };
+
+        if let Some(reply) = reply {
+            let chat_id = message.chat.id.to_string();
+            self.client
+                .send_message(&reply, &[("chat_id", chat_id.as_str())])?;
+        }
+        Ok(true)
+    }
+
+    /// Run the handler of a command and return its reply, `None` if there's no handler
+    fn run_command(&self, command: Command, message: &Message) -> Option<Option<String>> {
+        if let (Some(addressed), Some(own)) = (command.bot_username(), &self.bot_username) {
+            if !addressed.eq_ignore_ascii_case(own) {
+                debug!(
+                    command = command.name(),
+                    bot = addressed,
+                    "Ignoring command for another bot"
+                );
+                return None;
+            }
+        }
+        let Some(handler) = self.handlers.get(command.name()) else {
+            debug!(command = command.name(), "Ignoring unknown command");
+            return None;
+        };
+
+        debug!(
+            command = command.name(),
+            chat_id = message.chat.id,
+            "Running command"
+        );
+        let context = CommandContext {
+            command,
+            message,
+            client: &self.client,
+        };
+        let reply = match handler(&context) {
+            Ok(reply) => reply,
+            Err(e) => {
+                warn!(command = context.command.name(), error = %e, "Command failed");
+                Some(e.to_string())
+            }
+        };
+        Some(reply)
+    }
+}
+
+impl UpdateHandler for CommandDispatcher {
+    fn handle(&self, update: Update) {
+        if let Err(e) = self.dispatch(&update) {
+            warn!(update_id = update.update_id, error = %e, "Could not reply to command");
+        }
+    }
+}
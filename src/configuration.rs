@@ -1,11 +1,35 @@
+use crate::aggregation::{self, AggregationRule};
 use crate::error::Error;
-use std::sync::{Arc, Mutex, Once};
+use crate::escalation::EscalationPolicy;
+use crate::filter::{ContentFilters, FilterDecision};
+use crate::receipt::{DeliveryReceipt, ReceiptCallbacks};
+use crate::redaction::{Redaction, RedactionRule};
+use crate::secret::SecretString;
+use crate::url_filter::UrlFilter;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, RwLock};
+use std::time::Duration;
 
-static INSTANCE: Once = Once::new();
-static mut CONFIGURATION: Option<Arc<Mutex<Configuration>>> = None;
+/// Global configuration; readers take a cheap `Arc` snapshot
+static CONFIGURATION: OnceLock<RwLock<Arc<Configuration>>> = OnceLock::new();
+
+/// Serializes writers so concurrent `configure` calls never lose updates
+static WRITER: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// Stack of scoped overrides active on the current thread (innermost last)
+    static OVERRIDES: RefCell<Vec<Arc<Configuration>>> = const { RefCell::new(Vec::new()) };
+}
 
 /// Formatting options for message processing
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FormattingOptions {
     /// Whether to escape Markdown special characters
     pub escape_markdown: bool,
@@ -13,8 +37,22 @@ pub struct FormattingOptions {
     pub obfuscate_emails: bool,
     /// Whether to escape HTML special characters
     pub escape_html: bool,
+    /// Whether to sanitize HTML to the tags Telegram supports instead of escaping it
+    pub sanitize_html: bool,
     /// Maximum message length (Telegram limit is 4096)
+    #[serde(deserialize_with = "deserialize_truncate")]
     pub truncate: Option<usize>,
+    /// Text marking where truncated messages were cut, e.g. `… [truncated]`
+    pub truncation_suffix: String,
+    /// Which part of a message truncation keeps
+    pub truncate_mode: TruncateMode,
+    /// Keep only the last N lines of a message, before the length limit applies
+    #[serde(deserialize_with = "deserialize_truncate")]
+    pub truncate_lines: Option<usize>,
+    /// Whether to mask credentials like API keys and tokens
+    pub scrub_secrets: bool,
+    /// Allow/deny lists and rewrites for the URLs of a message
+    pub url_filter: Option<UrlFilter>,
 }
 
 impl Default for FormattingOptions {
@@ -23,135 +61,874 @@ impl Default for FormattingOptions {
             escape_markdown: true,
             obfuscate_emails: false,
             escape_html: false,
-            truncate: Some(4096),
+            sanitize_html: false,
+            truncate: Some(crate::formatter::MAX_MESSAGE_LENGTH),
+            truncation_suffix: "...".to_string(),
+            truncate_mode: TruncateMode::KeepHead,
+            truncate_lines: None,
+            scrub_secrets: false,
+            url_filter: None,
         }
     }
 }
 
 /// HTTP client options for API requests
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ClientOptions {
-    /// Request timeout in seconds
-    pub timeout: u64,
+    /// Seconds to wait for a connection to be established
+    pub connect_timeout: u64,
+    /// Total request timeout in seconds, including connecting
+    #[serde(alias = "timeout")]
+    pub request_timeout: u64,
     /// Number of retries for failed requests
     pub retry_count: u32,
     /// Delay between retries in seconds
     pub retry_delay: u64,
+    /// How the delay grows from one retry to the next
+    pub backoff: BackoffStrategy,
+    /// Longest delay in seconds an exponential backoff may reach
+    pub max_retry_delay: u64,
+    /// Seconds a request may take in total, including all retries and
+    /// rate-limit waits (`None` for no limit). A `send_message` shares it with
+    /// its username lookup, parse mode fallbacks and escalation.
+    pub send_deadline: Option<u64>,
+    /// Additional trusted root certificates (PEM files, bundles allowed),
+    /// e.g. the CA of a TLS-intercepting corporate proxy
+    pub root_certificates: Vec<PathBuf>,
+    /// Whether to trust the platform's built-in root certificates
+    pub tls_built_in_root_certs: bool,
+    /// Accept invalid TLS certificates (dangerous, for debugging only)
+    pub danger_accept_invalid_certs: bool,
+    /// Maximum idle connections kept per host (`None` for the backend default)
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Seconds an idle pooled connection is kept (`None` keeps it forever)
+    pub pool_idle_timeout: Option<u64>,
+    /// TCP keep-alive interval in seconds (`None` disables keep-alive probes)
+    pub tcp_keepalive: Option<u64>,
+    /// Seconds during which identical sends are suppressed as duplicates (`None` disables)
+    pub idempotency_window: Option<u64>,
+    /// What happens to duplicates within the idempotency window
+    pub duplicate_strategy: DuplicateStrategy,
+    /// Messages the whole process may send per minute, protecting chats from
+    /// runaway loops (`None` for no limit)
+    pub max_messages_per_minute: Option<u32>,
+    /// What happens to messages over `max_messages_per_minute`
+    pub throttle_policy: ThrottlePolicy,
 }
 
 impl Default for ClientOptions {
     fn default() -> Self {
         ClientOptions {
-            timeout: 30,
+            connect_timeout: 10,
+            request_timeout: 30,
             retry_count: 3,
             retry_delay: 1,
+            backoff: BackoffStrategy::Fixed,
+            max_retry_delay: 30,
+            send_deadline: None,
+            root_certificates: Vec::new(),
+            tls_built_in_root_certs: true,
+            danger_accept_invalid_certs: false,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: Some(90),
+            tcp_keepalive: None,
+            idempotency_window: None,
+            duplicate_strategy: DuplicateStrategy::Suppress,
+            max_messages_per_minute: None,
+            throttle_policy: ThrottlePolicy::Drop,
+        }
+    }
+}
+
+/// Delay before a retry, given the retry number (starting at 1) and the `retry_delay`
+pub type BackoffFn = Arc<dyn Fn(u32, Duration) -> Duration + Send + Sync>;
+
+/// How the delay between retries of failed requests grows
+///
+/// Flood control waits always follow the `retry_after` Telegram asks for.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackoffStrategy {
+    /// Wait `retry_delay` before every retry
+    #[default]
+    Fixed,
+    /// Double the delay after every retry, up to `max_retry_delay`
+    Exponential,
+    /// Wait a random delay between zero and the exponential one ("full jitter"),
+    /// so that clients sharing an egress IP don't retry in lockstep
+    ExponentialJitter,
+    /// Compute the delay with a custom function (not available in files)
+    #[serde(skip)]
+    Custom(BackoffFn),
+}
+
+impl BackoffStrategy {
+    /// Strategy computing delays with `backoff`
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use telegrama_rs::configuration::BackoffStrategy;
+    ///
+    /// // 1s, 3s, 5s, ...
+    /// let linear = BackoffStrategy::custom(|retry, base| base * (2 * retry - 1));
+    /// assert_eq!(
+    ///     linear.delay(3, Duration::from_secs(1), Duration::from_secs(60)),
+    ///     Duration::from_secs(5)
+    /// );
+    /// ```
+    pub fn custom<F>(backoff: F) -> Self
+    where
+        F: Fn(u32, Duration) -> Duration + Send + Sync + 'static,
+    {
+        BackoffStrategy::Custom(Arc::new(backoff))
+    }
+
+    /// Delay before retry number `retry` (starting at 1)
+    ///
+    /// Exponential delays are capped at `max_delay`; custom ones are used as-is.
+    pub fn delay(&self, retry: u32, base: Duration, max_delay: Duration) -> Duration {
+        let exponential = || {
+            let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+            base.saturating_mul(factor).min(max_delay)
+        };
+        match self {
+            BackoffStrategy::Fixed => base,
+            BackoffStrategy::Exponential => exponential(),
+            BackoffStrategy::ExponentialJitter => exponential().mul_f64(random_fraction()),
+            BackoffStrategy::Custom(backoff) => backoff(retry, base),
+        }
+    }
+}
+
+impl std::fmt::Debug for BackoffStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackoffStrategy::Fixed => f.write_str("Fixed"),
+            BackoffStrategy::Exponential => f.write_str("Exponential"),
+            BackoffStrategy::ExponentialJitter => f.write_str("ExponentialJitter"),
+            BackoffStrategy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl PartialEq for BackoffStrategy {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BackoffStrategy::Custom(a), BackoffStrategy::Custom(b)) => Arc::ptr_eq(a, b),
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+}
+
+impl std::str::FromStr for BackoffStrategy {
+    type Err = Error;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy.to_lowercase().as_str() {
+            "fixed" => Ok(BackoffStrategy::Fixed),
+            "exponential" => Ok(BackoffStrategy::Exponential),
+            "exponential_jitter" | "exponential-jitter" | "jitter" => {
+                Ok(BackoffStrategy::ExponentialJitter)
+            }
+            _ => Err(Error::configuration(format!(
+                "Unknown backoff strategy '{}' (expected fixed, exponential or exponential_jitter)",
+                strategy
+            ))),
+        }
+    }
+}
+
+/// Random number in `[0, 1)`, good enough to spread retries
+fn random_fraction() -> f64 {
+    // Every `RandomState` is seeded differently
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// How Telegram renders the preview of links in a message
+///
+/// Mirrors the Bot API `LinkPreviewOptions` object; unset fields are omitted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinkPreviewOptions {
+    /// Disable the link preview
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_disabled: Option<bool>,
+    /// URL to preview instead of the first link in the text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Shrink the preview media
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefer_small_media: Option<bool>,
+    /// Enlarge the preview media
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefer_large_media: Option<bool>,
+    /// Show the preview above the message text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_above_text: Option<bool>,
+}
+
+impl LinkPreviewOptions {
+    /// Whether no field is set, so the options can be left out of requests
+    pub fn is_empty(&self) -> bool {
+        *self == LinkPreviewOptions::default()
+    }
+}
+
+/// Destination of messages sent with the `route` option
+///
+/// Routes name where a kind of message goes, e.g. deploy notices to a forum
+/// topic of the ops chat through the reports bot, so call sites don't
+/// hard-code chats. Explicit `chat_id`, `bot` and `message_thread_id` options
+/// win over the route's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    /// Chat ID, @username or chat alias
+    pub chat_id: String,
+    /// Bot profile to send with instead of the default bot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot: Option<String>,
+    /// Forum topic of the chat to post in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_thread_id: Option<i64>,
+}
+
+impl Route {
+    /// Route messages to `chat_id` with the default bot
+    pub fn new<S: Into<String>>(chat_id: S) -> Self {
+        Route {
+            chat_id: chat_id.into(),
+            bot: None,
+            message_thread_id: None,
+        }
+    }
+
+    /// Send with the named bot profile
+    pub fn bot<S: Into<String>>(mut self, name: S) -> Self {
+        self.bot = Some(name.into());
+        self
+    }
+
+    /// Post in a forum topic of the chat
+    pub fn thread(mut self, message_thread_id: i64) -> Self {
+        self.message_thread_id = Some(message_thread_id);
+        self
+    }
+}
+
+/// Formatting mode a message is sent in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ParseMode {
+    /// Telegram's MarkdownV2
+    #[serde(rename = "MarkdownV2")]
+    MarkdownV2,
+    /// Telegram's legacy Markdown, sent without MarkdownV2 escaping
+    #[serde(rename = "Markdown")]
+    MarkdownLegacy,
+    /// Telegram's HTML subset
+    #[serde(rename = "HTML", alias = "Html")]
+    Html,
+    /// Plain text without entities
+    #[serde(rename = "plain", alias = "Plain")]
+    Plain,
+}
+
+impl ParseMode {
+    /// Value of the Bot API `parse_mode` parameter, `None` for plain text
+    pub fn as_api_str(self) -> Option<&'static str> {
+        match self {
+            ParseMode::MarkdownV2 => Some("MarkdownV2"),
+            ParseMode::MarkdownLegacy => Some("Markdown"),
+            ParseMode::Html => Some("HTML"),
+            ParseMode::Plain => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_api_str().unwrap_or("plain"))
+    }
+}
+
+impl std::str::FromStr for ParseMode {
+    type Err = Error;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.trim().to_lowercase().as_str() {
+            "markdownv2" => Ok(ParseMode::MarkdownV2),
+            "markdown" | "markdownlegacy" => Ok(ParseMode::MarkdownLegacy),
+            "html" => Ok(ParseMode::Html),
+            "plain" | "none" | "" => Ok(ParseMode::Plain),
+            _ => Err(Error::configuration(format!(
+                "Unknown parse mode '{}' (expected MarkdownV2, Markdown, HTML or plain)",
+                mode
+            ))),
+        }
+    }
+}
+
+/// What happens to a message sent again within the idempotency window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateStrategy {
+    /// Fail with [`Error::Duplicate`] without sending anything
+    #[default]
+    Suppress,
+    /// Edit the original message to append a counter, e.g. `Disk full (×3)`
+    Coalesce,
+}
+
+impl std::str::FromStr for DuplicateStrategy {
+    type Err = Error;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy.to_lowercase().as_str() {
+            "suppress" => Ok(DuplicateStrategy::Suppress),
+            "coalesce" => Ok(DuplicateStrategy::Coalesce),
+            _ => Err(Error::configuration(format!(
+                "Unknown duplicate strategy '{}' (expected suppress or coalesce)",
+                strategy
+            ))),
+        }
+    }
+}
+
+/// What happens to messages over the process-wide flood throttle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottlePolicy {
+    /// Fail with [`Error::Throttled`] and report the number of dropped messages
+    /// with the next message that gets through
+    #[default]
+    Drop,
+    /// Block until the throttle accepts the message, at most until the send deadline
+    Wait,
+}
+
+impl std::str::FromStr for ThrottlePolicy {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.to_lowercase().as_str() {
+            "drop" => Ok(ThrottlePolicy::Drop),
+            "wait" => Ok(ThrottlePolicy::Wait),
+            _ => Err(Error::configuration(format!(
+                "Unknown throttle policy '{}' (expected drop or wait)",
+                policy
+            ))),
+        }
+    }
+}
+
+/// What to do when Telegram rejects a message in its parse mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackPolicy {
+    /// Retry MarkdownV2 and Markdown as HTML, then as plain text
+    #[default]
+    Auto,
+    /// Return the error instead of sending a differently formatted message
+    Strict,
+    /// Retry as plain text only
+    PlainOnly,
+}
+
+impl std::str::FromStr for FallbackPolicy {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.to_lowercase().as_str() {
+            "auto" => Ok(FallbackPolicy::Auto),
+            "strict" => Ok(FallbackPolicy::Strict),
+            "plain_only" | "plain-only" | "plain" => Ok(FallbackPolicy::PlainOnly),
+            _ => Err(Error::configuration(format!(
+                "Unknown fallback policy '{}' (expected auto, strict or plain_only)",
+                policy
+            ))),
+        }
+    }
+}
+
+/// Which part of a message truncation keeps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncateMode {
+    /// Keep the beginning and cut the end
+    #[default]
+    KeepHead,
+    /// Keep the end and cut the beginning, e.g. for log tails
+    KeepTail,
+    /// Keep the beginning and the end and cut the middle, e.g. for stack traces
+    KeepBothEnds,
+}
+
+impl std::str::FromStr for TruncateMode {
+    type Err = Error;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_lowercase().replace('-', "_").as_str() {
+            "keep_head" | "head" => Ok(TruncateMode::KeepHead),
+            "keep_tail" | "tail" => Ok(TruncateMode::KeepTail),
+            "keep_both_ends" | "both_ends" | "middle" => Ok(TruncateMode::KeepBothEnds),
+            _ => Err(Error::configuration(format!(
+                "Unknown truncate mode '{}' (expected keep_head, keep_tail or keep_both_ends)",
+                mode
+            ))),
         }
     }
 }
 
 /// Configuration for the Telegrama client
-#[derive(Debug, Clone)]
+///
+/// Implements `Serialize`/`Deserialize` so it can be embedded in application
+/// config loaded through `config`, `figment` and similar crates. Missing fields
+/// take their default values, and bot tokens are redacted when serializing or
+/// `Debug`-printing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Configuration {
     /// Telegram Bot API token
-    bot_token: Option<String>,
+    bot_token: Option<SecretString>,
+    /// Named bot profiles (e.g. "alerts" -> token) selectable per message
+    bots: HashMap<String, SecretString>,
     /// Default chat ID for sending messages
     chat_id: Option<String>,
-    /// Default parse mode (MarkdownV2, HTML, or None)
+    /// Default parse mode (MarkdownV2, Markdown, HTML, or None)
     default_parse_mode: Option<String>,
+    /// What to do when a message is rejected in its parse mode
+    fallback_policy: FallbackPolicy,
+    /// Parse modes to retry with, in order, replacing the fallback policy
+    fallback_chain: Option<Vec<ParseMode>>,
     /// Whether to disable web page previews by default
     disable_web_page_preview: bool,
+    /// Default link preview options (`disable_web_page_preview` maps to `is_disabled`)
+    link_preview_options: Option<LinkPreviewOptions>,
     /// Optional prefix to prepend to all messages
     message_prefix: Option<String>,
     /// Optional suffix to append to all messages
     message_suffix: Option<String>,
+    /// Optional strftime pattern for a timestamp appended to all messages
+    timestamp_format: Option<String>,
+    /// Timezone used to render the timestamp (UTC, Local, or an IANA name)
+    timestamp_timezone: Option<String>,
     /// Formatting options
     formatting_options: FormattingOptions,
     /// HTTP client options
     client_options: ClientOptions,
+    /// Named aliases for chat IDs (e.g. "ops" -> "-100123")
+    chat_aliases: HashMap<String, String>,
+    /// Named destinations selected with the `route` option
+    routes: HashMap<String, Route>,
+    /// Whether log events may contain message texts and response bodies
+    log_message_text: bool,
+    /// Patterns redacted from every message, applied in order
+    redactions: Vec<RedactionRule>,
+    /// Aggregation windows by key pattern, first match wins
+    aggregations: Vec<AggregationRule>,
+    /// Callbacks deciding whether and how a message is sent
+    #[serde(skip)]
+    content_filters: ContentFilters,
+    /// Callbacks receiving the outcome of every send
+    #[serde(skip)]
+    receipt_callbacks: ReceiptCallbacks,
+    /// Chat receiving quarantined messages for review
+    quarantine_chat_id: Option<String>,
+    /// Where repeated delivery failures are escalated
+    escalation: Option<EscalationPolicy>,
 }
 
 impl Default for Configuration {
     fn default() -> Self {
         Configuration {
             bot_token: None,
+            bots: HashMap::new(),
             chat_id: None,
             default_parse_mode: Some("MarkdownV2".to_string()),
+            fallback_policy: FallbackPolicy::default(),
+            fallback_chain: None,
             disable_web_page_preview: true,
+            link_preview_options: None,
             message_prefix: None,
             message_suffix: None,
+            timestamp_format: None,
+            timestamp_timezone: None,
             formatting_options: FormattingOptions::default(),
             client_options: ClientOptions::default(),
+            chat_aliases: HashMap::new(),
+            routes: HashMap::new(),
+            log_message_text: false,
+            redactions: Vec::new(),
+            aggregations: Vec::new(),
+            content_filters: ContentFilters::default(),
+            receipt_callbacks: ReceiptCallbacks::default(),
+            quarantine_chat_id: None,
+            escalation: None,
         }
     }
 }
 
 impl Configuration {
-    /// Get a shared reference to the global configuration instance
+    /// Build a configuration from `TELEGRAMA_*` environment variables
+    ///
+    /// Settings that are not present in the environment keep their defaults.
+    /// See [`Configuration::apply_env`] for the list of recognised variables.
+    pub fn from_env() -> Result<Configuration, Error> {
+        let mut config = Configuration::default();
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Override settings from `TELEGRAMA_*` environment variables
+    ///
+    /// Recognised variables:
     ///
-    /// # TODO
+    /// - chats and formatting: `TELEGRAMA_BOT_TOKEN`, `TELEGRAMA_CHAT_ID`,
+    ///   `TELEGRAMA_QUARANTINE_CHAT_ID`, `TELEGRAMA_PARSE_MODE`,
+    ///   `TELEGRAMA_FALLBACK_POLICY`, `TELEGRAMA_FALLBACK_CHAIN`,
+    ///   `TELEGRAMA_DISABLE_WEB_PAGE_PREVIEW`, `TELEGRAMA_LOG_MESSAGE_TEXT`,
+    ///   `TELEGRAMA_MESSAGE_PREFIX`, `TELEGRAMA_MESSAGE_SUFFIX`,
+    ///   `TELEGRAMA_TIMESTAMP_FORMAT`, `TELEGRAMA_TIMESTAMP_TIMEZONE`,
+    ///   `TELEGRAMA_ESCAPE_MARKDOWN`, `TELEGRAMA_OBFUSCATE_EMAILS`,
+    ///   `TELEGRAMA_ESCAPE_HTML`, `TELEGRAMA_SANITIZE_HTML`, `TELEGRAMA_SCRUB_SECRETS`
+    /// - truncation: `TELEGRAMA_TRUNCATE`, `TELEGRAMA_TRUNCATION_SUFFIX`,
+    ///   `TELEGRAMA_TRUNCATE_MODE`, `TELEGRAMA_TRUNCATE_LINES`
+    /// - HTTP client: `TELEGRAMA_CONNECT_TIMEOUT`, `TELEGRAMA_REQUEST_TIMEOUT`
+    ///   (or its older name `TELEGRAMA_TIMEOUT`), `TELEGRAMA_RETRY_COUNT`,
+    ///   `TELEGRAMA_RETRY_DELAY`, `TELEGRAMA_BACKOFF`, `TELEGRAMA_MAX_RETRY_DELAY`,
+    ///   `TELEGRAMA_SEND_DEADLINE`, `TELEGRAMA_POOL_MAX_IDLE_PER_HOST`,
+    ///   `TELEGRAMA_POOL_IDLE_TIMEOUT`, `TELEGRAMA_TCP_KEEPALIVE`
+    /// - delivery: `TELEGRAMA_IDEMPOTENCY_WINDOW`, `TELEGRAMA_DUPLICATE_STRATEGY`,
+    ///   `TELEGRAMA_MAX_MESSAGES_PER_MINUTE`, `TELEGRAMA_THROTTLE_POLICY`
     ///
-    /// This function is unsafe because it uses a static mut reference to the configuration instance.
-    #[allow(static_mut_refs)]
-    pub fn get_instance() -> Arc<Mutex<Configuration>> {
-        unsafe {
-            INSTANCE.call_once(|| {
-                CONFIGURATION = Some(Arc::new(Mutex::new(Configuration::default())));
-            });
-            CONFIGURATION.clone().unwrap()
+    /// Nothing is changed if any variable fails to parse.
+    pub fn apply_env(&mut self) -> Result<(), Error> {
+        let mut config = self.clone();
+
+        if let Some(token) = env_var("BOT_TOKEN") {
+            config.set_bot_token(token);
         }
+        if let Some(chat_id) = env_var("CHAT_ID") {
+            config.set_chat_id(chat_id);
+        }
+        if let Some(chat_id) = env_var("QUARANTINE_CHAT_ID") {
+            config.set_quarantine_chat_id(chat_id);
+        }
+        if let Some(mode) = env_var("PARSE_MODE") {
+            config.set_default_parse_mode(mode);
+        }
+        if let Some(policy) = env_parse::<FallbackPolicy>("FALLBACK_POLICY")? {
+            config.set_fallback_policy(policy);
+        }
+        if let Some(chain) = env_var("FALLBACK_CHAIN") {
+            // Comma-separated modes, e.g. "HTML,plain"; empty disables fallbacks
+            let chain = chain
+                .split(',')
+                .filter(|mode| !mode.trim().is_empty())
+                .map(|mode| parse_env_value::<ParseMode>("FALLBACK_CHAIN", mode))
+                .collect::<Result<Vec<_>, _>>()?;
+            config.set_fallback_chain(chain);
+        }
+        if let Some(disable) = env_parse::<bool>("DISABLE_WEB_PAGE_PREVIEW")? {
+            config.set_disable_web_page_preview(disable);
+        }
+        if let Some(enabled) = env_parse::<bool>("LOG_MESSAGE_TEXT")? {
+            config.set_log_message_text(enabled);
+        }
+        if let Some(prefix) = env_var("MESSAGE_PREFIX") {
+            config.set_message_prefix(prefix);
+        }
+        if let Some(suffix) = env_var("MESSAGE_SUFFIX") {
+            config.set_message_suffix(suffix);
+        }
+        if let Some(format) = env_var("TIMESTAMP_FORMAT") {
+            config.set_timestamp_format(format);
+        }
+        if let Some(timezone) = env_var("TIMESTAMP_TIMEZONE") {
+            config.set_timestamp_timezone(timezone);
+        }
+
+        if let Some(escape) = env_parse::<bool>("ESCAPE_MARKDOWN")? {
+            config.formatting_options.escape_markdown = escape;
+        }
+        if let Some(obfuscate) = env_parse::<bool>("OBFUSCATE_EMAILS")? {
+            config.formatting_options.obfuscate_emails = obfuscate;
+        }
+        if let Some(escape) = env_parse::<bool>("ESCAPE_HTML")? {
+            config.formatting_options.escape_html = escape;
+        }
+        if let Some(sanitize) = env_parse::<bool>("SANITIZE_HTML")? {
+            config.formatting_options.sanitize_html = sanitize;
+        }
+        if let Some(scrub) = env_parse::<bool>("SCRUB_SECRETS")? {
+            config.formatting_options.scrub_secrets = scrub;
+        }
+        if let Some(truncate) = env_var("TRUNCATE") {
+            // "0" or "none" disables truncation
+            config.formatting_options.truncate = match truncate.to_lowercase().as_str() {
+                "0" | "none" => None,
+                _ => Some(parse_env_value("TRUNCATE", &truncate)?),
+            };
+        }
+        if let Some(suffix) = env_var("TRUNCATION_SUFFIX") {
+            config.formatting_options.truncation_suffix = suffix;
+        }
+        if let Some(mode) = env_parse::<TruncateMode>("TRUNCATE_MODE")? {
+            config.formatting_options.truncate_mode = mode;
+        }
+        if let Some(lines) = env_var("TRUNCATE_LINES") {
+            // "0" or "none" keeps all lines
+            config.formatting_options.truncate_lines = match lines.to_lowercase().as_str() {
+                "0" | "none" => None,
+                _ => Some(parse_env_value("TRUNCATE_LINES", &lines)?),
+            };
+        }
+
+        if let Some(timeout) = env_parse::<u64>("CONNECT_TIMEOUT")? {
+            config.client_options.connect_timeout = timeout;
+        }
+        // TELEGRAMA_TIMEOUT is the older name of TELEGRAMA_REQUEST_TIMEOUT
+        if let Some(timeout) = env_parse::<u64>("TIMEOUT")? {
+            config.client_options.request_timeout = timeout;
+        }
+        if let Some(timeout) = env_parse::<u64>("REQUEST_TIMEOUT")? {
+            config.client_options.request_timeout = timeout;
+        }
+        if let Some(retry_count) = env_parse::<u32>("RETRY_COUNT")? {
+            config.client_options.retry_count = retry_count;
+        }
+        if let Some(retry_delay) = env_parse::<u64>("RETRY_DELAY")? {
+            config.client_options.retry_delay = retry_delay;
+        }
+        if let Some(backoff) = env_parse::<BackoffStrategy>("BACKOFF")? {
+            config.client_options.backoff = backoff;
+        }
+        if let Some(max_delay) = env_parse::<u64>("MAX_RETRY_DELAY")? {
+            config.client_options.max_retry_delay = max_delay;
+        }
+        if let Some(deadline) = env_parse::<u64>("SEND_DEADLINE")? {
+            config.client_options.send_deadline = Some(deadline).filter(|&s| s > 0);
+        }
+        if let Some(max_idle) = env_parse::<usize>("POOL_MAX_IDLE_PER_HOST")? {
+            config.client_options.pool_max_idle_per_host = Some(max_idle);
+        }
+        // "0" keeps idle connections forever and disables keep-alive probes
+        if let Some(idle_timeout) = env_parse::<u64>("POOL_IDLE_TIMEOUT")? {
+            config.client_options.pool_idle_timeout = Some(idle_timeout).filter(|&s| s > 0);
+        }
+        if let Some(keepalive) = env_parse::<u64>("TCP_KEEPALIVE")? {
+            config.client_options.tcp_keepalive = Some(keepalive).filter(|&s| s > 0);
+        }
+        if let Some(window) = env_parse::<u64>("IDEMPOTENCY_WINDOW")? {
+            config.client_options.idempotency_window = Some(window).filter(|&s| s > 0);
+        }
+        if let Some(strategy) = env_parse::<DuplicateStrategy>("DUPLICATE_STRATEGY")? {
+            config.client_options.duplicate_strategy = strategy;
+        }
+        if let Some(limit) = env_parse::<u32>("MAX_MESSAGES_PER_MINUTE")? {
+            config.client_options.max_messages_per_minute = Some(limit).filter(|&n| n > 0);
+        }
+        if let Some(policy) = env_parse::<ThrottlePolicy>("THROTTLE_POLICY")? {
+            config.client_options.throttle_policy = policy;
+        }
+
+        *self = config;
+        Ok(())
     }
 
-    /// Get a clone of the current configuration (avoids mutex deadlocks)
-    pub fn get_cloned_instance() -> Result<Configuration, Error> {
-        let instance = Self::get_instance();
-        // Use try_lock to avoid deadlocks
-        let result = match instance.try_lock() {
-            Ok(config) => config.clone(),
-            Err(_) => {
-                // If we can't get the lock, return a default configuration
-                // This is better than deadlocking
-                Configuration::default()
+    /// Load a configuration from a TOML or YAML file
+    ///
+    /// The format is chosen by extension (`.toml`, `.yaml`/`.yml`); TOML needs the
+    /// `toml` feature (enabled by default) and YAML the `yaml` feature. Settings
+    /// missing from the file keep their defaults.
+    ///
+    /// ```toml
+    /// bot_token = "123:ABC"
+    /// chat_id = "-100123"
+    /// default_parse_mode = "HTML"
+    ///
+    /// [formatting_options]
+    /// obfuscate_emails = true
+    /// truncate = 2000
+    ///
+    /// [client_options]
+    /// retry_count = 5
+    ///
+    /// [bots]
+    /// reports = "456:DEF"
+    ///
+    /// [chat_aliases]
+    /// ops = "-100456"
+    ///
+    /// [routes.deploys]
+    /// chat_id = "ops"
+    /// bot = "reports"
+    /// message_thread_id = 42
+    /// ```
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Configuration, Error> {
+        let mut config = Configuration::default();
+        config.apply_file(path)?;
+        Ok(config)
+    }
+
+    /// Override settings with the ones present in a TOML or YAML file
+    ///
+    /// Nothing is changed if the file cannot be read or parsed.
+    pub fn apply_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            Error::configuration(format!(
+                "Failed to read configuration file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        let file = match extension.as_str() {
+            "toml" => ConfigFile::from_toml(&contents)?,
+            "yaml" | "yml" => ConfigFile::from_yaml(&contents)?,
+            _ => {
+                return Err(Error::configuration(format!(
+                    "Unsupported configuration file format: '{}'",
+                    path.display()
+                )))
             }
         };
-        Ok(result)
+
+        let mut config = self.clone();
+        file.apply(&mut config);
+        *self = config;
+        Ok(())
     }
 
-    /// Modify the global configuration instance
-    pub fn get_instance_mut<F>(config_fn: F)
+    /// Get a snapshot of the global configuration
+    ///
+    /// Scoped overrides of the current thread are ignored; use
+    /// [`Configuration::snapshot`] to get the configuration sends will use.
+    pub fn get_instance() -> Arc<Configuration> {
+        global()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Get a snapshot of the effective configuration
+    ///
+    /// Returns the innermost scoped override of the current thread, if any,
+    /// otherwise the global configuration. Taking a snapshot only clones an `Arc`.
+    pub fn snapshot() -> Arc<Configuration> {
+        OVERRIDES
+            .with(|overrides| overrides.borrow().last().cloned())
+            .unwrap_or_else(Self::get_instance)
+    }
+
+    /// Get an owned copy of the effective configuration
+    pub fn get_cloned_instance() -> Result<Configuration, Error> {
+        Ok(Self::snapshot().as_ref().clone())
+    }
+
+    /// Override the configuration for the current thread until the guard is dropped
+    ///
+    /// The closure receives a copy of the currently effective configuration.
+    /// Overrides nest, and other threads keep seeing the global configuration.
+    pub fn push_override<F>(config_fn: F) -> ConfigOverrideGuard
     where
         F: FnOnce(&mut Configuration),
     {
-        let instance = Self::get_instance();
+        let mut config = Self::snapshot().as_ref().clone();
+        config_fn(&mut config);
+        Self::push_snapshot(Arc::new(config))
+    }
+
+    /// Make `config` the effective configuration of the current thread until the guard is dropped
+    pub(crate) fn push_snapshot(config: Arc<Configuration>) -> ConfigOverrideGuard {
+        OVERRIDES.with(|overrides| overrides.borrow_mut().push(config));
+        let depth = OVERRIDES.with(|overrides| overrides.borrow().len());
 
-        // First try to get the lock
-        if let Ok(mut config) = instance.try_lock() {
-            config_fn(&mut config);
-            return;
+        ConfigOverrideGuard {
+            depth,
+            _not_send: PhantomData,
         }
+    }
+
+    /// Modify the global configuration instance
+    ///
+    /// Concurrent calls are applied one after another, and in-flight sends keep
+    /// the snapshot they started with. The closure runs without holding the read
+    /// lock, so it may read the configuration, but must not call `configure` itself.
+    pub fn get_instance_mut<F>(config_fn: F)
+    where
+        F: FnOnce(&mut Configuration),
+    {
+        let _writer = WRITER.lock().unwrap_or_else(PoisonError::into_inner);
 
-        // If we can't get the lock, use a temporary configuration
-        let mut temp_config = Configuration::default();
-        config_fn(&mut temp_config);
+        let mut config = Self::get_instance().as_ref().clone();
+        config_fn(&mut config);
 
-        // Try again to update the global config
-        if let Ok(mut config) = Self::get_instance().try_lock() {
-            *config = temp_config;
-        }
-        // If still can't lock, just continue with configuration saved in the closure
+        *global().write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
     }
 
     /// Set the Telegram Bot API token
     pub fn set_bot_token<S: AsRef<str>>(&mut self, token: S) {
-        self.bot_token = Some(token.as_ref().to_string());
+        self.bot_token = Some(SecretString::from(token.as_ref()));
     }
 
     /// Get the Telegram Bot API token
     pub fn bot_token(&self) -> Result<&str, Error> {
         self.bot_token
-            .as_deref()
+            .as_ref()
+            .map(SecretString::expose_secret)
             .ok_or_else(|| Error::configuration("Bot token not configured"))
     }
 
+    /// Register a named bot profile that can be selected with the `bot` send option
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use telegrama_rs::Telegrama;
+    ///
+    /// Telegrama::configure(|config| {
+    ///     config.add_bot("alerts", "ALERTS_BOT_TOKEN");
+    ///     config.add_bot("reports", "REPORTS_BOT_TOKEN");
+    /// });
+    /// ```
+    pub fn add_bot<N: AsRef<str>, S: AsRef<str>>(&mut self, name: N, token: S) {
+        self.bots.insert(
+            name.as_ref().to_string(),
+            SecretString::from(token.as_ref()),
+        );
+    }
+
+    /// Get the token of a named bot profile
+    pub fn bot(&self, name: &str) -> Result<&str, Error> {
+        self.bots
+            .get(name)
+            .map(SecretString::expose_secret)
+            .ok_or_else(|| Error::configuration(format!("Bot profile '{}' not configured", name)))
+    }
+
+    /// Get the names of all configured bot profiles
+    pub fn bot_names(&self) -> impl Iterator<Item = &str> {
+        self.bots.keys().map(String::as_str)
+    }
+
+    /// Resolve the token to use: the named profile if given, otherwise the default token
+    pub fn resolve_bot_token(&self, name: Option<&str>) -> Result<&str, Error> {
+        match name {
+            Some(name) => self.bot(name),
+            None => self.bot_token(),
+        }
+    }
+
     /// Set the default chat ID
     pub fn set_chat_id<S: AsRef<str>>(&mut self, chat_id: S) {
         self.chat_id = Some(chat_id.as_ref().to_string());
@@ -164,6 +941,67 @@ impl Configuration {
             .ok_or_else(|| Error::configuration("Chat ID not configured"))
     }
 
+    /// Run `filter` on the unformatted text of every message before it is sent
+    ///
+    /// Filters run in the order they were added; each one sees the text
+    /// replaced by the previous ones. The first [`FilterDecision::Drop`] or
+    /// [`FilterDecision::Quarantine`] stops the message from reaching its chat,
+    /// and the send fails with [`Error::Filtered`].
+    pub fn add_content_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&str) -> FilterDecision + Send + Sync + 'static,
+    {
+        self.content_filters.push(Arc::new(filter));
+    }
+
+    /// Get the registered content filters
+    pub(crate) fn content_filters(&self) -> &ContentFilters {
+        &self.content_filters
+    }
+
+    /// Call `callback` with the receipt of every message delivered to Telegram
+    pub fn on_delivered<F>(&mut self, callback: F)
+    where
+        F: Fn(&DeliveryReceipt) + Send + Sync + 'static,
+    {
+        self.receipt_callbacks.delivered.push(Arc::new(callback));
+    }
+
+    /// Call `callback` with the receipt and error of every message that could not be sent
+    ///
+    /// Suppressed duplicates are not failures and don't trigger it.
+    pub fn on_failed<F>(&mut self, callback: F)
+    where
+        F: Fn(&DeliveryReceipt, &Error) + Send + Sync + 'static,
+    {
+        self.receipt_callbacks.failed.push(Arc::new(callback));
+    }
+
+    /// Get the registered receipt callbacks
+    pub(crate) fn receipt_callbacks(&self) -> &ReceiptCallbacks {
+        &self.receipt_callbacks
+    }
+
+    /// Set the chat (ID, @username or alias) receiving quarantined messages
+    pub fn set_quarantine_chat_id<S: AsRef<str>>(&mut self, chat_id: S) {
+        self.quarantine_chat_id = Some(chat_id.as_ref().to_string());
+    }
+
+    /// Get the chat receiving quarantined messages, if any
+    pub fn quarantine_chat_id(&self) -> Option<&str> {
+        self.quarantine_chat_id.as_deref()
+    }
+
+    /// Escalate repeated delivery failures to a secondary chat
+    pub fn set_escalation(&mut self, policy: EscalationPolicy) {
+        self.escalation = Some(policy);
+    }
+
+    /// Get the escalation policy, if any
+    pub fn escalation(&self) -> Option<&EscalationPolicy> {
+        self.escalation.as_ref()
+    }
+
     /// Set the default parse mode
     pub fn set_default_parse_mode<S: AsRef<str>>(&mut self, mode: S) {
         let mode_str = mode.as_ref().to_string();
@@ -179,9 +1017,38 @@ impl Configuration {
         self.default_parse_mode.as_deref()
     }
 
+    /// Set what happens when Telegram rejects a message in its parse mode
+    pub fn set_fallback_policy(&mut self, policy: FallbackPolicy) {
+        self.fallback_policy = policy;
+    }
+
+    /// Get the fallback policy
+    pub fn fallback_policy(&self) -> FallbackPolicy {
+        self.fallback_policy
+    }
+
+    /// Set the parse modes a rejected message is retried with, in order
+    ///
+    /// Replaces the [`FallbackPolicy`]; the mode the message was first sent in
+    /// is skipped and an empty chain disables fallbacks.
+    pub fn set_fallback_chain<I: IntoIterator<Item = ParseMode>>(&mut self, chain: I) {
+        self.fallback_chain = Some(chain.into_iter().collect());
+    }
+
+    /// Get the fallback chain, if one replaces the fallback policy
+    pub fn fallback_chain(&self) -> Option<&[ParseMode]> {
+        self.fallback_chain.as_deref()
+    }
+
     /// Set whether to disable web page previews by default
+    ///
+    /// Kept for compatibility; equivalent to setting `is_disabled` of the
+    /// [`LinkPreviewOptions`].
     pub fn set_disable_web_page_preview(&mut self, disable: bool) {
         self.disable_web_page_preview = disable;
+        if let Some(options) = &mut self.link_preview_options {
+            options.is_disabled = Some(disable);
+        }
     }
 
     /// Get whether web page previews are disabled by default
@@ -189,6 +1056,37 @@ impl Configuration {
         self.disable_web_page_preview
     }
 
+    /// Set the default link preview options
+    pub fn set_link_preview_options(&mut self, options: LinkPreviewOptions) {
+        self.disable_web_page_preview = options.is_disabled.unwrap_or(false);
+        self.link_preview_options = Some(options);
+    }
+
+    /// Get the default link preview options
+    ///
+    /// Without explicit options, they are derived from `disable_web_page_preview`.
+    pub fn link_preview_options(&self) -> LinkPreviewOptions {
+        self.link_preview_options
+            .clone()
+            .unwrap_or_else(|| LinkPreviewOptions {
+                is_disabled: self.disable_web_page_preview.then_some(true),
+                ..LinkPreviewOptions::default()
+            })
+    }
+
+    /// Set whether log events may contain message texts and response bodies
+    ///
+    /// Off by default, so logs only show their length; turn it on to debug
+    /// formatting issues.
+    pub fn set_log_message_text(&mut self, enabled: bool) {
+        self.log_message_text = enabled;
+    }
+
+    /// Get whether log events may contain message texts and response bodies
+    pub fn log_message_text(&self) -> bool {
+        self.log_message_text
+    }
+
     /// Set the message prefix
     pub fn set_message_prefix<S: AsRef<str>>(&mut self, prefix: S) {
         let prefix_str = prefix.as_ref().to_string();
@@ -219,6 +1117,41 @@ impl Configuration {
         self.message_suffix.as_deref()
     }
 
+    /// Set the strftime pattern of the timestamp appended to every message
+    ///
+    /// An empty pattern disables the timestamp.
+    pub fn set_timestamp_format<S: AsRef<str>>(&mut self, format: S) {
+        let format_str = format.as_ref().to_string();
+        self.timestamp_format = if format_str.is_empty() {
+            None
+        } else {
+            Some(format_str)
+        };
+    }
+
+    /// Get the timestamp format
+    pub fn timestamp_format(&self) -> Option<&str> {
+        self.timestamp_format.as_deref()
+    }
+
+    /// Set the timezone used to render timestamps
+    ///
+    /// Accepts `UTC`, `Local`, or an IANA name such as `Europe/Berlin`
+    /// (the latter requires the `chrono-tz` feature). Defaults to UTC.
+    pub fn set_timestamp_timezone<S: AsRef<str>>(&mut self, timezone: S) {
+        let timezone_str = timezone.as_ref().to_string();
+        self.timestamp_timezone = if timezone_str.is_empty() {
+            None
+        } else {
+            Some(timezone_str)
+        };
+    }
+
+    /// Get the timestamp timezone
+    pub fn timestamp_timezone(&self) -> Option<&str> {
+        self.timestamp_timezone.as_deref()
+    }
+
     /// Set formatting options
     pub fn set_formatting_options(&mut self, options: FormattingOptions) {
         self.formatting_options = options;
@@ -239,21 +1172,463 @@ impl Configuration {
         &self.client_options
     }
 
+    /// Register an alias that can be used in place of a chat ID
+    pub fn add_chat_alias<A: AsRef<str>, C: AsRef<str>>(&mut self, alias: A, chat_id: C) {
+        self.chat_aliases
+            .insert(alias.as_ref().to_string(), chat_id.as_ref().to_string());
+    }
+
+    /// Get the registered chat aliases
+    pub fn chat_aliases(&self) -> &HashMap<String, String> {
+        &self.chat_aliases
+    }
+
+    /// Resolve a chat alias to its chat ID, returning the input if it isn't an alias
+    pub fn resolve_chat<'a>(&'a self, chat: &'a str) -> &'a str {
+        self.chat_aliases
+            .get(chat)
+            .map(String::as_str)
+            .unwrap_or(chat)
+    }
+
+    /// Register a named route, replacing an earlier one with the same name
+    ///
+    /// ```
+    /// use telegrama_rs::{Route, Telegrama};
+    ///
+    /// Telegrama::configure(|config| {
+    ///     config.add_bot("reports", "REPORTS_BOT_TOKEN");
+    ///     config.add_route("deploys", Route::new("-100123").bot("reports").thread(42));
+    /// });
+    /// // Telegrama::send_message("Deployed v1.4", &[("route", "deploys")])
+    /// ```
+    pub fn add_route<N: Into<String>>(&mut self, name: N, route: Route) {
+        self.routes.insert(name.into(), route);
+    }
+
+    /// Get the registered routes
+    pub fn routes(&self) -> &HashMap<String, Route> {
+        &self.routes
+    }
+
+    /// Get a registered route by name
+    pub fn route(&self, name: &str) -> Result<&Route, Error> {
+        self.routes
+            .get(name)
+            .ok_or_else(|| Error::configuration(format!("Route '{}' not configured", name)))
+    }
+
+    /// Redact matches of the regular expression `pattern` from every message
+    ///
+    /// Rules run in the order they were added, before any escaping; a rule
+    /// with the same name replaces the earlier one. Fails if the pattern
+    /// isn't a valid regular expression.
+    pub fn add_redaction<N: Into<String>>(
+        &mut self,
+        name: N,
+        pattern: &str,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.add_redaction_rule(RedactionRule::new(name, pattern, redaction)?);
+        Ok(())
+    }
+
+    /// Register a prepared [`RedactionRule`], replacing a rule of the same name
+    pub fn add_redaction_rule(&mut self, rule: RedactionRule) {
+        match self.redactions.iter_mut().find(|r| r.name() == rule.name()) {
+            Some(existing) => *existing = rule,
+            None => self.redactions.push(rule),
+        }
+    }
+
+    /// Get the registered redaction rules, in the order they are applied
+    pub fn redactions(&self) -> &[RedactionRule] {
+        &self.redactions
+    }
+
+    /// Merge messages whose `aggregation_key` matches `pattern` for `window`
+    ///
+    /// The first matching rule decides the window of a key; a rule with the
+    /// same pattern replaces the earlier one, and a zero window disables
+    /// aggregation. Fails if the pattern isn't a valid regular expression.
+    pub fn add_aggregation(&mut self, pattern: &str, window: Duration) -> Result<(), Error> {
+        self.add_aggregation_rule(AggregationRule::new(pattern, window)?);
+        Ok(())
+    }
+
+    /// Register a prepared [`AggregationRule`], replacing a rule of the same pattern
+    pub fn add_aggregation_rule(&mut self, rule: AggregationRule) {
+        match self
+            .aggregations
+            .iter_mut()
+            .find(|r| r.pattern() == rule.pattern())
+        {
+            Some(existing) => *existing = rule,
+            None => self.aggregations.push(rule),
+        }
+    }
+
+    /// Get the registered aggregation rules, in the order they are matched
+    pub fn aggregations(&self) -> &[AggregationRule] {
+        &self.aggregations
+    }
+
+    /// How long messages with the aggregation key `key` are merged
+    pub fn aggregation_window(&self, key: &str) -> Duration {
+        aggregation::window(&self.aggregations, key)
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> Result<(), Error> {
-        // Check required fields
-        self.bot_token()?;
+        // Check required fields (a default token or at least one bot profile)
+        if self.bots.is_empty() {
+            self.bot_token()?;
+        }
 
         // Validate parse mode if set
         if let Some(mode) = self.default_parse_mode() {
-            if mode.is_empty() || (mode != "MarkdownV2" && mode != "HTML") {
+            if !matches!(mode, "MarkdownV2" | "Markdown" | "HTML") {
                 return Err(Error::configuration(format!(
-                    "Invalid parse mode: '{}'. Must be 'MarkdownV2' or 'HTML'",
+                    "Invalid parse mode: '{}'. Must be 'MarkdownV2', 'Markdown' or 'HTML'",
                     mode
                 )));
             }
         }
 
+        // Routes may only use configured bot profiles
+        for (name, route) in &self.routes {
+            if let Some(bot) = &route.bot {
+                self.bot(bot).map_err(|_| {
+                    Error::configuration(format!(
+                        "Route '{}' uses bot profile '{}', which is not configured",
+                        name, bot
+                    ))
+                })?;
+            }
+        }
+
+        // Validate the timestamp settings by rendering one
+        if let Some(format) = self.timestamp_format() {
+            crate::formatter::Formatter::timestamp(format, self.timestamp_timezone())?;
+        }
+
         Ok(())
     }
 }
+
+/// Deserialize a truncation limit, treating 0 as "no truncation"
+fn deserialize_truncate<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    let limit = Option::<usize>::deserialize(deserializer)?;
+    Ok(limit.filter(|limit| *limit > 0))
+}
+
+/// Prefix shared by all environment variables read by the configuration
+const ENV_PREFIX: &str = "TELEGRAMA_";
+
+/// Read a `TELEGRAMA_*` environment variable
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(format!("{}{}", ENV_PREFIX, name)).ok()
+}
+
+/// Read and parse a `TELEGRAMA_*` environment variable
+fn env_parse<T: std::str::FromStr>(name: &str) -> Result<Option<T>, Error> {
+    env_var(name)
+        .map(|value| parse_env_value(name, &value))
+        .transpose()
+}
+
+/// Parse an environment value, reporting the offending variable on failure
+fn parse_env_value<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, Error> {
+    value.trim().to_lowercase().parse::<T>().map_err(|_| {
+        Error::configuration(format!(
+            "Invalid value for {}{}: '{}'",
+            ENV_PREFIX, name, value
+        ))
+    })
+}
+
+/// Lazily initialized global configuration cell
+fn global() -> &'static RwLock<Arc<Configuration>> {
+    CONFIGURATION.get_or_init(|| RwLock::new(Arc::new(Configuration::default())))
+}
+
+/// Guard returned by [`Configuration::push_override`]
+///
+/// Restores the previous configuration of the current thread when dropped.
+#[must_use = "the override is removed as soon as the guard is dropped"]
+pub struct ConfigOverrideGuard {
+    /// Stack depth right after this override was pushed
+    depth: usize,
+    /// Overrides are thread-local, so the guard must stay on its thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ConfigOverrideGuard {
+    fn drop(&mut self) {
+        // Also drops inner overrides whose guards were leaked
+        OVERRIDES.with(|overrides| overrides.borrow_mut().truncate(self.depth - 1));
+    }
+}
+
+/// On-disk representation of the configuration, every setting optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    bot_token: Option<String>,
+    bots: Option<HashMap<String, String>>,
+    chat_id: Option<String>,
+    default_parse_mode: Option<String>,
+    fallback_policy: Option<FallbackPolicy>,
+    fallback_chain: Option<Vec<ParseMode>>,
+    disable_web_page_preview: Option<bool>,
+    link_preview_options: Option<LinkPreviewOptions>,
+    message_prefix: Option<String>,
+    message_suffix: Option<String>,
+    timestamp_format: Option<String>,
+    timestamp_timezone: Option<String>,
+    formatting_options: Option<FormattingOptionsFile>,
+    client_options: Option<ClientOptionsFile>,
+    chat_aliases: Option<HashMap<String, String>>,
+    routes: Option<HashMap<String, Route>>,
+    log_message_text: Option<bool>,
+    redactions: Option<Vec<RedactionRule>>,
+    aggregations: Option<Vec<AggregationRule>>,
+    quarantine_chat_id: Option<String>,
+    escalation: Option<EscalationPolicy>,
+}
+
+/// On-disk representation of [`FormattingOptions`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormattingOptionsFile {
+    escape_markdown: Option<bool>,
+    obfuscate_emails: Option<bool>,
+    escape_html: Option<bool>,
+    sanitize_html: Option<bool>,
+    /// Maximum message length, 0 disables truncation
+    truncate: Option<usize>,
+    truncation_suffix: Option<String>,
+    truncate_mode: Option<TruncateMode>,
+    /// Number of trailing lines to keep, 0 keeps all lines
+    truncate_lines: Option<usize>,
+    scrub_secrets: Option<bool>,
+    url_filter: Option<UrlFilter>,
+}
+
+/// On-disk representation of [`ClientOptions`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClientOptionsFile {
+    connect_timeout: Option<u64>,
+    #[serde(alias = "timeout")]
+    request_timeout: Option<u64>,
+    retry_count: Option<u32>,
+    retry_delay: Option<u64>,
+    backoff: Option<BackoffStrategy>,
+    max_retry_delay: Option<u64>,
+    /// Total time budget of a request in seconds, 0 disables it
+    send_deadline: Option<u64>,
+    root_certificates: Option<Vec<PathBuf>>,
+    tls_built_in_root_certs: Option<bool>,
+    danger_accept_invalid_certs: Option<bool>,
+    pool_max_idle_per_host: Option<usize>,
+    /// Idle connection lifetime in seconds, 0 keeps idle connections forever
+    pool_idle_timeout: Option<u64>,
+    /// Keep-alive interval in seconds, 0 disables keep-alive probes
+    tcp_keepalive: Option<u64>,
+    /// Duplicate suppression window in seconds, 0 disables it
+    idempotency_window: Option<u64>,
+    duplicate_strategy: Option<DuplicateStrategy>,
+    /// Messages per minute for the whole process, 0 disables the throttle
+    max_messages_per_minute: Option<u32>,
+    throttle_policy: Option<ThrottlePolicy>,
+}
+
+impl ConfigFile {
+    #[cfg(feature = "toml")]
+    fn from_toml(contents: &str) -> Result<Self, Error> {
+        toml::from_str(contents)
+            .map_err(|e| Error::configuration(format!("Invalid TOML configuration: {}", e)))
+    }
+
+    #[cfg(not(feature = "toml"))]
+    fn from_toml(_contents: &str) -> Result<Self, Error> {
+        Err(Error::configuration(
+            "TOML configuration files require the `toml` feature",
+        ))
+    }
+
+    #[cfg(feature = "yaml")]
+    fn from_yaml(contents: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(contents)
+            .map_err(|e| Error::configuration(format!("Invalid YAML configuration: {}", e)))
+    }
+
+    #[cfg(not(feature = "yaml"))]
+    fn from_yaml(_contents: &str) -> Result<Self, Error> {
+        Err(Error::configuration(
+            "YAML configuration files require the `yaml` feature",
+        ))
+    }
+
+    /// Apply every setting present in the file to a configuration
+    fn apply(self, config: &mut Configuration) {
+        if let Some(token) = self.bot_token {
+            config.set_bot_token(token);
+        }
+        if let Some(bots) = self.bots {
+            for (name, token) in bots {
+                config.add_bot(name, token);
+            }
+        }
+        if let Some(chat_id) = self.chat_id {
+            config.set_chat_id(chat_id);
+        }
+        if let Some(chat_id) = self.quarantine_chat_id {
+            config.set_quarantine_chat_id(chat_id);
+        }
+        if let Some(policy) = self.escalation {
+            config.set_escalation(policy);
+        }
+        if let Some(mode) = self.default_parse_mode {
+            config.set_default_parse_mode(mode);
+        }
+        if let Some(policy) = self.fallback_policy {
+            config.set_fallback_policy(policy);
+        }
+        if let Some(chain) = self.fallback_chain {
+            config.set_fallback_chain(chain);
+        }
+        if let Some(disable) = self.disable_web_page_preview {
+            config.set_disable_web_page_preview(disable);
+        }
+        if let Some(options) = self.link_preview_options {
+            config.set_link_preview_options(options);
+        }
+        if let Some(enabled) = self.log_message_text {
+            config.set_log_message_text(enabled);
+        }
+        if let Some(prefix) = self.message_prefix {
+            config.set_message_prefix(prefix);
+        }
+        if let Some(suffix) = self.message_suffix {
+            config.set_message_suffix(suffix);
+        }
+        if let Some(format) = self.timestamp_format {
+            config.set_timestamp_format(format);
+        }
+        if let Some(timezone) = self.timestamp_timezone {
+            config.set_timestamp_timezone(timezone);
+        }
+
+        if let Some(formatting) = self.formatting_options {
+            let options = &mut config.formatting_options;
+            if let Some(escape) = formatting.escape_markdown {
+                options.escape_markdown = escape;
+            }
+            if let Some(obfuscate) = formatting.obfuscate_emails {
+                options.obfuscate_emails = obfuscate;
+            }
+            if let Some(escape) = formatting.escape_html {
+                options.escape_html = escape;
+            }
+            if let Some(sanitize) = formatting.sanitize_html {
+                options.sanitize_html = sanitize;
+            }
+            if let Some(truncate) = formatting.truncate {
+                options.truncate = if truncate == 0 { None } else { Some(truncate) };
+            }
+            if let Some(suffix) = formatting.truncation_suffix {
+                options.truncation_suffix = suffix;
+            }
+            if let Some(mode) = formatting.truncate_mode {
+                options.truncate_mode = mode;
+            }
+            if let Some(lines) = formatting.truncate_lines {
+                options.truncate_lines = if lines == 0 { None } else { Some(lines) };
+            }
+            if let Some(scrub) = formatting.scrub_secrets {
+                options.scrub_secrets = scrub;
+            }
+            if let Some(filter) = formatting.url_filter {
+                options.url_filter = Some(filter);
+            }
+        }
+
+        if let Some(client) = self.client_options {
+            let options = &mut config.client_options;
+            if let Some(timeout) = client.connect_timeout {
+                options.connect_timeout = timeout;
+            }
+            if let Some(timeout) = client.request_timeout {
+                options.request_timeout = timeout;
+            }
+            if let Some(retry_count) = client.retry_count {
+                options.retry_count = retry_count;
+            }
+            if let Some(retry_delay) = client.retry_delay {
+                options.retry_delay = retry_delay;
+            }
+            if let Some(backoff) = client.backoff {
+                options.backoff = backoff;
+            }
+            if let Some(max_delay) = client.max_retry_delay {
+                options.max_retry_delay = max_delay;
+            }
+            if let Some(deadline) = client.send_deadline {
+                options.send_deadline = Some(deadline).filter(|&s| s > 0);
+            }
+            if let Some(certificates) = client.root_certificates {
+                options.root_certificates = certificates;
+            }
+            if let Some(built_in) = client.tls_built_in_root_certs {
+                options.tls_built_in_root_certs = built_in;
+            }
+            if let Some(accept) = client.danger_accept_invalid_certs {
+                options.danger_accept_invalid_certs = accept;
+            }
+            if let Some(max_idle) = client.pool_max_idle_per_host {
+                options.pool_max_idle_per_host = Some(max_idle);
+            }
+            if let Some(idle_timeout) = client.pool_idle_timeout {
+                options.pool_idle_timeout = Some(idle_timeout).filter(|&s| s > 0);
+            }
+            if let Some(keepalive) = client.tcp_keepalive {
+                options.tcp_keepalive = Some(keepalive).filter(|&s| s > 0);
+            }
+            if let Some(window) = client.idempotency_window {
+                options.idempotency_window = Some(window).filter(|&s| s > 0);
+            }
+            if let Some(strategy) = client.duplicate_strategy {
+                options.duplicate_strategy = strategy;
+            }
+            if let Some(limit) = client.max_messages_per_minute {
+                options.max_messages_per_minute = Some(limit).filter(|&n| n > 0);
+            }
+            if let Some(policy) = client.throttle_policy {
+                options.throttle_policy = policy;
+            }
+        }
+
+        if let Some(aliases) = self.chat_aliases {
+            for (alias, chat_id) in aliases {
+                config.add_chat_alias(alias, chat_id);
+            }
+        }
+        if let Some(routes) = self.routes {
+            for (name, route) in routes {
+                config.add_route(name, route);
+            }
+        }
+
+        for rule in self.redactions.into_iter().flatten() {
+            config.add_redaction_rule(rule);
+        }
+
+        for rule in self.aggregations.into_iter().flatten() {
+            config.add_aggregation_rule(rule);
+        }
+    }
+}
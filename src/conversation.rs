@@ -0,0 +1,167 @@
+//! Per-chat state of multi-step conversations.
+//!
+//! [`Conversations`] remembers where each chat is in an interaction such as
+//! "which environment?" followed by "confirm?". States are any serde type and
+//! live in a [`StateStore`]: in memory by default, or in a store of your own to
+//! survive restarts or share them between instances.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use telegrama_rs::Conversations;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! enum Deploy {
+//!     ChooseEnvironment,
+//!     Confirm { environment: String },
+//! }
+//!
+//! let conversations = Conversations::<Deploy>::new();
+//! conversations.set(42, &Deploy::ChooseEnvironment).unwrap();
+//!
+//! // Later, when the chat answers "prod"
+//! if let Some(Deploy::ChooseEnvironment) = conversations.get(42) {
+//!     let next = Deploy::Confirm { environment: "prod".to_string() };
+//!     conversations.set(42, &next).unwrap();
+//! }
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::error::Error;
+
+/// Storage of conversation states, keyed by chat ID
+pub trait StateStore: Send + Sync {
+    /// State of a chat, if it's in a conversation
+    fn load(&self, chat_id: i64) -> Option<Value>;
+
+    /// Store the state of a chat
+    fn save(&self, chat_id: i64, state: Value);
+
+    /// Forget the state of a chat
+    fn remove(&self, chat_id: i64);
+}
+
+/// In-memory [`StateStore`], the default
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    states: Mutex<HashMap<i64, Value>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn states(&self) -> std::sync::MutexGuard<'_, HashMap<i64, Value>> {
+        self.states.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn load(&self, chat_id: i64) -> Option<Value> {
+        self.states().get(&chat_id).cloned()
+    }
+
+    fn save(&self, chat_id: i64, state: Value) {
+        self.states().insert(chat_id, state);
+    }
+
+    fn remove(&self, chat_id: i64) {
+        self.states().remove(&chat_id);
+    }
+}
+
+/// Conversation state of every chat
+///
+/// Clones share the same store, so command and message handlers can each
+/// hold one.
+pub struct Conversations<S> {
+    store: Arc<dyn StateStore>,
+    _state: PhantomData<fn() -> S>,
+}
+
+impl<S> Clone for Conversations<S> {
+    fn clone(&self) -> Self {
+        Conversations {
+            store: Arc::clone(&self.store),
+            _state: PhantomData,
+        }
+    }
+}
+
+impl<S: Serialize + DeserializeOwned> Default for Conversations<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Serialize + DeserializeOwned> Conversations<S> {
+    /// Keep states in memory
+    pub fn new() -> Self {
+        Self::with_store(MemoryStore::new())
+    }
+
+    /// Keep states in a custom store
+    pub fn with_store<T: StateStore + 'static>(store: T) -> Self {
+        Conversations {
+            store: Arc::new(store),
+            _state: PhantomData,
+        }
+    }
+
+    /// State of a chat, `None` if it isn't in a conversation
+    ///
+    /// A stored state that no longer deserializes (e.g. after the state type
+    /// changed) is logged and treated as no conversation.
+    pub fn get(&self, chat_id: i64) -> Option<S> {
+        let value = self.store.load(chat_id)?;
+        match serde_json::from_value(value) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                warn!(chat_id, error = %e, "Ignoring unreadable conversation state");
+                None
+            }
+        }
+    }
+
+    /// Move a chat to a new state
+    pub fn set(&self, chat_id: i64, state: &S) -> Result<(), Error> {
+        let value = serde_json::to_value(state)
+            .map_err(|e| Error::other(format!("Failed to serialize conversation state: {}", e)))?;
+        self.store.save(chat_id, value);
+        Ok(())
+    }
+
+    /// End the conversation of a chat, returning its last state
+    pub fn finish(&self, chat_id: i64) -> Option<S> {
+        let state = self.get(chat_id);
+        self.store.remove(chat_id);
+        state
+    }
+
+    /// Compute the next state of a chat from its current one
+    ///
+    /// Returning `None` ends the conversation.
+    pub fn update<F>(&self, chat_id: i64, transition: F) -> Result<Option<S>, Error>
+    where
+        F: FnOnce(Option<S>) -> Option<S>,
+    {
+        match transition(self.get(chat_id)) {
+            Some(next) => {
+                self.set(chat_id, &next)?;
+                Ok(Some(next))
+            }
+            None => {
+                self.store.remove(chat_id);
+                Ok(None)
+            }
+        }
+    }
+}
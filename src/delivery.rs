@@ -0,0 +1,152 @@
+//! Secondary delivery channels for messages Telegram could not deliver.
+//!
+//! Register a [`FallbackDelivery`] with
+//! [`Client::with_fallback_delivery`](crate::Client::with_fallback_delivery) so
+//! critical alerts still reach somewhere during a Telegram outage. It is invoked
+//! once a send has finally failed, after all retries and parse mode fallbacks.
+//! [`WebhookDelivery`] posts to a Slack, Mattermost or similar incoming webhook:
+//!
+//! ```
+//! use telegrama_rs::{Client, WebhookDelivery};
+//!
+//! let client = Client::new()
+//!     .with_fallback_delivery(WebhookDelivery::new("https://hooks.slack.com/services/T000/B000/XXXX"));
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// Timeout of webhook requests unless set with [`WebhookDelivery::with_timeout`]
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A message Telegram could not deliver
+#[derive(Debug)]
+pub struct FailedDelivery<'a> {
+    /// Target chat
+    pub chat_id: &'a str,
+    /// Unformatted message text
+    pub text: &'a str,
+    /// Why Telegram delivery failed
+    pub error: &'a Error,
+}
+
+/// Channel delivering messages when Telegram can't
+pub trait FallbackDelivery: Send + Sync {
+    /// Deliver `failed` through this channel
+    fn deliver(&self, failed: &FailedDelivery<'_>) -> Result<(), Error>;
+}
+
+/// Builds the JSON body of a webhook request
+type PayloadFn = Arc<dyn Fn(&FailedDelivery<'_>) -> serde_json::Value + Send + Sync>;
+
+/// Fallback posting a JSON payload to an incoming webhook
+///
+/// The default payload, `{"text": "..."}`, is understood by Slack, Mattermost,
+/// Rocket.Chat and Discord-compatible (`/slack`) webhooks. Use
+/// [`WebhookDelivery::with_payload`] for other services.
+#[derive(Clone)]
+pub struct WebhookDelivery {
+    url: String,
+    timeout: Duration,
+    payload: PayloadFn,
+}
+
+impl WebhookDelivery {
+    /// Fallback posting to the webhook at `url`
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        WebhookDelivery {
+            url: url.into(),
+            timeout: DEFAULT_TIMEOUT,
+            payload: Arc::new(
+                |failed: &FailedDelivery<'_>| serde_json::json!({ "text": default_text(failed) }),
+            ),
+        }
+    }
+
+    /// Give up on webhook requests after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Build the JSON body of the request with `payload`
+    pub fn with_payload<F>(mut self, payload: F) -> Self
+    where
+        F: Fn(&FailedDelivery<'_>) -> serde_json::Value + Send + Sync + 'static,
+    {
+        self.payload = Arc::new(payload);
+        self
+    }
+
+    #[cfg(feature = "reqwest")]
+    fn post(&self, body: &serde_json::Value) -> Result<(), Error> {
+        let request = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()?
+            .post(&self.url)
+            .json(body);
+        let response = crate::transport::block_on(request.send())
+            // The webhook URL is a credential
+            .map_err(reqwest::Error::without_url)?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(Error::transport(format!(
+                "Webhook answered with status {}",
+                status.as_u16()
+            ))),
+        }
+    }
+
+    #[cfg(all(not(feature = "reqwest"), feature = "ureq"))]
+    fn post(&self, body: &serde_json::Value) -> Result<(), Error> {
+        let result = ureq::post(&self.url)
+            .timeout(self.timeout)
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string());
+        match result {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, _)) => Err(Error::transport(format!(
+                "Webhook answered with status {}",
+                status
+            ))),
+            // The webhook URL is a credential
+            Err(ureq::Error::Transport(e)) => Err(Error::transport(crate::secret::redact(
+                &e.to_string(),
+                &self.url,
+            ))),
+        }
+    }
+
+    #[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+    fn post(&self, body: &serde_json::Value) -> Result<(), Error> {
+        let _ = (&self.url, self.timeout, body);
+        Err(Error::configuration(
+            "Webhook delivery requires the `reqwest` or `ureq` feature",
+        ))
+    }
+}
+
+impl FallbackDelivery for WebhookDelivery {
+    fn deliver(&self, failed: &FailedDelivery<'_>) -> Result<(), Error> {
+        self.post(&(self.payload)(failed))
+    }
+}
+
+impl std::fmt::Debug for WebhookDelivery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The URL is left out, it usually embeds a secret
+        f.debug_struct("WebhookDelivery")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Text of the default webhook payload
+fn default_text(failed: &FailedDelivery<'_>) -> String {
+    format!(
+        "⚠️ Telegram delivery to {} failed ({})\n\n{}",
+        failed.chat_id, failed.error, failed.text
+    )
+}
@@ -8,28 +8,121 @@ pub enum Error {
     Configuration(String),
 
     /// HTTP client error
+    #[cfg(any(feature = "reqwest", feature = "wasm"))]
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
+    /// Network or protocol error reported by a non-reqwest transport
+    #[error("Transport error: {0}")]
+    Transport(String),
+
     /// API error from Telegram
     #[error("Telegram API error: {0}")]
     Api(String),
 
+    /// Telegram rejected the request because of flood control (HTTP 429)
+    #[error("Telegram API rate limit exceeded, retry after {retry_after}s")]
+    RateLimited {
+        /// Seconds to wait before the next request
+        retry_after: u64,
+    },
+
+    /// The message was already sent within the idempotency window and was suppressed
+    #[error("Duplicate message suppressed: already sent within the idempotency window")]
+    Duplicate,
+
+    /// The process-wide flood throttle kept the message from being sent
+    #[error("Message suppressed by the flood throttle, retry after {retry_after}s")]
+    Throttled {
+        /// Seconds until the throttle accepts another message
+        retry_after: u64,
+    },
+
+    /// A content filter stopped the message from reaching its chat
+    #[error("Message blocked by a content filter: {reason}")]
+    Filtered {
+        /// Reason given by the filter
+        reason: String,
+        /// Whether the message was sent to the quarantine chat instead
+        quarantined: bool,
+    },
+
     /// Error related to message formatting
     #[error("Formatting error: {0}")]
     Formatting(String),
 
+    /// Telegram couldn't parse the entities of a formatted message
+    #[error("Telegram API error: {description} (near \"{snippet}\")")]
+    BadEntities {
+        /// Telegram's description, e.g. `Bad Request: can't parse entities: ...`
+        description: String,
+        /// Byte offset of the problem in the text passed to the client, if it lies there
+        ///
+        /// `None` if Telegram gave no offset, or it points at the configured
+        /// prefix, suffix or timestamp.
+        offset: Option<usize>,
+        /// Text around the problem, from the original text if `offset` is known
+        snippet: String,
+    },
+
+    /// The formatted text is longer than Telegram accepts, so it wasn't sent
+    #[error("Message too long: {length} characters, Telegram accepts at most {limit}")]
+    MessageTooLong {
+        /// Length of the text as Telegram counts it, in UTF-16 code units
+        length: usize,
+        /// Longest text accepted in the context, e.g. 4096 for messages
+        limit: usize,
+    },
+
     /// Other errors
     #[error("{0}")]
     Other(String),
 }
 
+/// Why a chat permanently stopped accepting messages from the bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StaleChatReason {
+    /// The user blocked the bot
+    BotBlocked,
+    /// The bot was removed from the group, supergroup or channel
+    BotKicked,
+    /// The chat does not exist (or the bot never had access to it)
+    ChatNotFound,
+    /// The user account was deleted
+    UserDeactivated,
+}
+
+impl StaleChatReason {
+    /// Classify a Telegram error description, e.g. `"Forbidden: bot was blocked by the user"`
+    pub fn from_description(description: &str) -> Option<Self> {
+        let description = description.to_lowercase();
+        if description.contains("bot was blocked by the user") {
+            Some(StaleChatReason::BotBlocked)
+        } else if description.contains("bot was kicked")
+            || description.contains("bot is not a member")
+        {
+            Some(StaleChatReason::BotKicked)
+        } else if description.contains("chat not found") {
+            Some(StaleChatReason::ChatNotFound)
+        } else if description.contains("user is deactivated") {
+            Some(StaleChatReason::UserDeactivated)
+        } else {
+            None
+        }
+    }
+}
+
 impl Error {
     /// Create a new configuration error
     pub fn configuration<S: AsRef<str>>(message: S) -> Self {
         Error::Configuration(message.as_ref().to_string())
     }
 
+    /// Create a new transport error
+    pub fn transport<S: AsRef<str>>(message: S) -> Self {
+        Error::Transport(message.as_ref().to_string())
+    }
+
     /// Create a new API error
     pub fn api<S: AsRef<str>>(message: S) -> Self {
         Error::Api(message.as_ref().to_string())
@@ -44,4 +137,62 @@ impl Error {
     pub fn other<S: AsRef<str>>(message: S) -> Self {
         Error::Other(message.as_ref().to_string())
     }
+
+    /// Short machine-readable name of the error variant, e.g. `"rate_limited"`
+    ///
+    /// Stable across releases, so it can be used as a metric or log label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Configuration(_) => "configuration",
+            #[cfg(any(feature = "reqwest", feature = "wasm"))]
+            Error::Http(_) => "http",
+            Error::Transport(_) => "transport",
+            Error::Api(_) => "api",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::Duplicate => "duplicate",
+            Error::Throttled { .. } => "throttled",
+            Error::Filtered { .. } => "filtered",
+            Error::Formatting(_) => "formatting",
+            Error::BadEntities { .. } => "bad_entities",
+            Error::MessageTooLong { .. } => "message_too_long",
+            Error::Other(_) => "other",
+        }
+    }
+
+    /// Why the target chat can no longer receive messages, if this error says so
+    pub fn stale_chat_reason(&self) -> Option<StaleChatReason> {
+        match self {
+            Error::Api(description) => StaleChatReason::from_description(description),
+            _ => None,
+        }
+    }
+
+    /// Mask every occurrence of `secret` (e.g. the bot token) in the error message
+    pub(crate) fn redact(self, secret: &str) -> Self {
+        use crate::secret::redact;
+
+        match self {
+            Error::Configuration(message) => Error::Configuration(redact(&message, secret)),
+            #[cfg(any(feature = "reqwest", feature = "wasm"))]
+            Error::Http(e) => Error::Http(e.without_url()),
+            Error::Transport(message) => Error::Transport(redact(&message, secret)),
+            Error::Api(message) => Error::Api(redact(&message, secret)),
+            Error::Formatting(message) => Error::Formatting(redact(&message, secret)),
+            Error::BadEntities {
+                description,
+                offset,
+                snippet,
+            } => Error::BadEntities {
+                description: redact(&description, secret),
+                offset,
+                snippet: redact(&snippet, secret),
+            },
+            Error::Other(message) => Error::Other(redact(&message, secret)),
+            error @ (Error::RateLimited { .. }
+            | Error::Duplicate
+            | Error::Throttled { .. }
+            | Error::Filtered { .. }
+            | Error::MessageTooLong { .. }) => error,
+        }
+    }
 }
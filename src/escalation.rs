@@ -0,0 +1,105 @@
+//! Escalation to a secondary chat when deliveries keep failing.
+//!
+//! With an [`EscalationPolicy`] set, a target chat that fails `max_failures`
+//! times within `window` seconds triggers an escalation message to the
+//! policy's chat. No further escalation for the same target is sent during the
+//! `cooldown`, so a broken primary chat can't cause an escalation loop:
+//!
+//! ```
+//! use telegrama_rs::{EscalationPolicy, Telegrama};
+//!
+//! Telegrama::configure(|config| {
+//!     config.set_escalation(EscalationPolicy {
+//!         chat_id: "-100777".to_string(),
+//!         max_failures: 3,
+//!         window: 300,
+//!         ..EscalationPolicy::default()
+//!     });
+//! });
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::formatter::format_duration;
+use crate::time::Instant;
+
+/// When and where failed deliveries are escalated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EscalationPolicy {
+    /// Chat (ID, @username or alias) receiving escalation messages
+    pub chat_id: String,
+    /// Failed deliveries to one target that trigger an escalation
+    pub max_failures: u32,
+    /// Seconds within which the failures must occur
+    pub window: u64,
+    /// Seconds after an escalation during which the target is not escalated again
+    pub cooldown: u64,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        EscalationPolicy {
+            chat_id: String::new(),
+            max_failures: 3,
+            window: 300,
+            cooldown: 1800,
+        }
+    }
+}
+
+/// Recent failures of a target and when it was last escalated
+#[derive(Default)]
+struct Tracker {
+    failures: VecDeque<Instant>,
+    escalated_at: Option<Instant>,
+}
+
+static TRACKERS: Mutex<Option<HashMap<String, Tracker>>> = Mutex::new(None);
+
+/// Record a failed delivery to `target`, returning the escalation message if one is due
+pub(crate) fn record_failure(
+    policy: &EscalationPolicy,
+    target: &str,
+    error: &Error,
+) -> Option<String> {
+    let now = Instant::now();
+    let window = Duration::from_secs(policy.window);
+    let mut trackers = TRACKERS.lock().unwrap_or_else(PoisonError::into_inner);
+    let tracker = trackers
+        .get_or_insert_with(HashMap::new)
+        .entry(target.to_string())
+        .or_default();
+
+    while tracker
+        .failures
+        .front()
+        .is_some_and(|&failed| now.duration_since(failed) >= window)
+    {
+        tracker.failures.pop_front();
+    }
+    tracker.failures.push_back(now);
+
+    let cooling_down = tracker.escalated_at.is_some_and(|escalated| {
+        now.duration_since(escalated) < Duration::from_secs(policy.cooldown)
+    });
+    if cooling_down || tracker.failures.len() < policy.max_failures.max(1) as usize {
+        return None;
+    }
+
+    let failures = tracker.failures.len();
+    tracker.failures.clear();
+    tracker.escalated_at = Some(now);
+    Some(format!(
+        "🚨 Delivery to {} failed {} times within {}. Last error: {}",
+        target,
+        failures,
+        format_duration(window),
+        error
+    ))
+}
@@ -0,0 +1,82 @@
+//! Content filters deciding what happens to a message before it is sent.
+//!
+//! Register filters with
+//! [`Configuration::add_content_filter`](crate::Configuration::add_content_filter),
+//! e.g. for profanity filters or compliance checks. They run in order on the
+//! unformatted text of every message:
+//!
+//! ```
+//! use telegrama_rs::{FilterDecision, Telegrama};
+//!
+//! Telegrama::configure(|config| {
+//!     config.add_content_filter(|text: &str| {
+//!         if text.contains("CONFIDENTIAL") {
+//!             FilterDecision::Quarantine("confidential content".to_string())
+//!         } else {
+//!             FilterDecision::Replace(text.replace("darn", "d**n"))
+//!         }
+//!     });
+//!     config.set_quarantine_chat_id("-100999");
+//! });
+//! ```
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// What a content filter decided for a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    /// Send the message unchanged
+    Allow,
+    /// Send this text instead
+    Replace(String),
+    /// Don't send the message, for the given reason
+    Drop(String),
+    /// Send the message to the quarantine chat for review instead of its
+    /// target, for the given reason; dropped if no quarantine chat is set
+    Quarantine(String),
+}
+
+/// A content filter callback
+pub(crate) type ContentFilterFn = Arc<dyn Fn(&str) -> FilterDecision + Send + Sync>;
+
+/// Filters registered on a configuration, applied in order
+#[derive(Clone, Default)]
+pub(crate) struct ContentFilters(Vec<ContentFilterFn>);
+
+/// Outcome of all content filters for a message
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Verdict<'a> {
+    /// Send the (possibly replaced) text
+    Send(Cow<'a, str>),
+    /// Don't send the message
+    Drop(String),
+    /// Send the (possibly replaced) text to the quarantine chat
+    Quarantine(Cow<'a, str>, String),
+}
+
+impl ContentFilters {
+    pub(crate) fn push(&mut self, filter: ContentFilterFn) {
+        self.0.push(filter);
+    }
+
+    /// Run every filter on `text`, stopping at the first drop or quarantine
+    pub(crate) fn run<'a>(&self, text: &'a str) -> Verdict<'a> {
+        let mut text = Cow::Borrowed(text);
+        for filter in &self.0 {
+            match filter(&text) {
+                FilterDecision::Allow => {}
+                FilterDecision::Replace(replaced) => text = Cow::Owned(replaced),
+                FilterDecision::Drop(reason) => return Verdict::Drop(reason),
+                FilterDecision::Quarantine(reason) => return Verdict::Quarantine(text, reason),
+            }
+        }
+        Verdict::Send(text)
+    }
+}
+
+impl std::fmt::Debug for ContentFilters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{} content filter(s)]", self.0.len())
+    }
+}
@@ -0,0 +1,92 @@
+//! Fingerprints grouping alerts that report the same problem.
+//!
+//! Two alerts with the same [`Fingerprint`] are occurrences of one problem,
+//! e.g. `Payment capture failed for order 1812` and `... for order 1813`. A
+//! fingerprint is derived from a template string chosen by the application, or
+//! from the callsite and the [normalized](normalize) message:
+//!
+//! ```
+//! use telegrama_rs::fingerprint::{normalize, Fingerprint};
+//!
+//! assert_eq!(
+//!     normalize("Order 1812 failed after 3 retries (request 5f0c2a9e)"),
+//!     "Order <n> failed after <n> retries (request <hex>)"
+//! );
+//! assert_eq!(
+//!     Fingerprint::from_message("payments.rs:42", "Capture failed for order 1812"),
+//!     Fingerprint::from_message("payments.rs:42", "Capture failed for order 1813"),
+//! );
+//! assert_eq!(
+//!     Fingerprint::from_template("payment-capture"),
+//!     Fingerprint::from_template("payment-capture"),
+//! );
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// UUIDs, long hex strings with a digit, and numbers, in order of precedence
+static VARIABLE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b|\b(?:0x)?[0-9a-f]*[0-9][0-9a-f]*\b",
+    )
+    .expect("valid variable pattern")
+});
+
+/// Identity of the problem an alert reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Fingerprint of alerts sharing the template `template`, e.g. `payment-capture`
+    pub fn from_template(template: &str) -> Self {
+        Self::hash(("template", template))
+    }
+
+    /// Fingerprint of a message logged at `callsite` (e.g. `file.rs:42`), ignoring variable parts
+    pub fn from_message(callsite: &str, message: &str) -> Self {
+        Self::hash(("message", callsite, normalize(message)))
+    }
+
+    /// The fingerprint as a number
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    fn hash<T: Hash>(value: T) -> Self {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        Fingerprint(hasher.finish())
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// `message` with UUIDs, hex strings and numbers replaced by placeholders
+///
+/// Hex strings need at least 8 characters, so words like `cafe` are kept.
+pub fn normalize(message: &str) -> String {
+    VARIABLE
+        .replace_all(message, |captures: &regex::Captures<'_>| {
+            let matched = &captures[0];
+            if matched.len() == 36 && matched.contains('-') {
+                "<uuid>".to_string()
+            } else if matched.bytes().all(|b| b.is_ascii_digit()) {
+                "<n>".to_string()
+            } else if matched.len() >= 8 {
+                "<hex>".to_string()
+            } else {
+                // Short mixes like `v2` or `3d` are words, not IDs
+                matched.to_string()
+            }
+        })
+        .into_owned()
+}
@@ -1,11 +1,20 @@
-use crate::configuration::Configuration;
+use crate::client::Client;
+use crate::configuration::{Configuration, ParseMode, TruncateMode};
 use crate::error::Error;
-use log::{error, trace};
+use crate::markdown::{self, EntityKind};
+use crate::secret::REDACTED;
+use crate::url_filter::UrlFilter;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, Utc};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::str::FromStr;
+use tracing::{error, instrument, trace};
 
 /// Special characters that need escaping in MarkdownV2 format
-const MARKDOWN_SPECIAL_CHARS: &[char] = &[
+pub(crate) const MARKDOWN_SPECIAL_CHARS: &[char] = &[
     '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
 ];
 
@@ -13,321 +22,1838 @@ const MARKDOWN_SPECIAL_CHARS: &[char] = &[
 static EMAIL_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b").unwrap());
 
-/// Regex for identifying HTML entities
-static HTML_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"[<>&]").unwrap());
+/// Regex for common credential shapes; the `key` and `scheme` labels are kept
+static SECRET_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        // AWS access key IDs
+        r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+        // AWS secret keys assigned in config dumps and environment listings
+        r"|(?P<key>(?i:aws_secret_access_key)\s*[=:]\s*)[A-Za-z0-9/+=]{40}",
+        // Authorization headers
+        r"|(?P<scheme>(?i:\bbearer)\s+)[A-Za-z0-9._~+/-]{8,}=*",
+        // GitHub tokens
+        r"|\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})\b",
+        // Telegram bot tokens
+        r"|\b\d{8,10}:[A-Za-z0-9_-]{35}",
+        // Long hex strings (API keys, session IDs, digests)
+        r"|\b[0-9a-fA-F]{32,}\b",
+    ))
+    .unwrap()
+});
+
+/// Lookup table of the ASCII bytes that need escaping in MarkdownV2 text
+static MARKDOWN_ESCAPE_TABLE: [bool; 128] = ascii_table(MARKDOWN_SPECIAL_CHARS);
+
+/// Lookup table of the ASCII bytes that need escaping in HTML text
+static HTML_ESCAPE_TABLE: [bool; 128] = ascii_table(&['<', '>', '&']);
 
 /// Regex for identifying markdown links
 static LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap());
 
+/// Maximum length of a message text accepted by Telegram
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Maximum length of a media caption accepted by Telegram
+pub const MAX_CAPTION_LENGTH: usize = 1024;
+
+/// What a text is formatted for, deciding the length limit Telegram applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FormatContext {
+    /// Text of a message, at most [`MAX_MESSAGE_LENGTH`]
+    Message,
+    /// Caption of a photo, video, document or audio file, at most [`MAX_CAPTION_LENGTH`]
+    Caption,
+}
+
+impl FormatContext {
+    /// Longest text Telegram accepts in this context
+    pub fn max_length(self) -> usize {
+        match self {
+            FormatContext::Message => MAX_MESSAGE_LENGTH,
+            FormatContext::Caption => MAX_CAPTION_LENGTH,
+        }
+    }
+}
+
+/// Where [`Formatter::split_message`] prefers to end a part
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SplitMode {
+    /// Fill each part, splitting at a line break in its second half or else at a space
+    #[default]
+    Compact,
+    /// Split at blank lines, then line breaks, and number the parts like `(2/5)`
+    Lines,
+}
+
+impl FromStr for SplitMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "compact" => Ok(SplitMode::Compact),
+            "lines" => Ok(SplitMode::Lines),
+            _ => Err(Error::configuration(format!(
+                "Unknown split mode '{}', expected compact or lines",
+                s
+            ))),
+        }
+    }
+}
+
 /// Formatter for Telegram messages
 pub struct Formatter;
 
+/// Problem found by [`Formatter::validate_markdown_v2`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownV2Error {
+    /// Byte offset of the problem in the validated text
+    pub byte_offset: usize,
+    /// Character offset of the problem in the validated text
+    pub char_offset: usize,
+    /// Human-readable explanation
+    pub message: String,
+}
+
+impl std::fmt::Display for MarkdownV2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at byte {} (character {})",
+            self.message, self.byte_offset, self.char_offset
+        )
+    }
+}
+
+impl std::error::Error for MarkdownV2Error {}
+
+impl From<MarkdownV2Error> for Error {
+    fn from(error: MarkdownV2Error) -> Self {
+        Error::formatting(error.to_string())
+    }
+}
+
+/// What [`Formatter::preview`] finds a message would be sent as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    /// The formatted text, as it would be sent
+    pub text: String,
+    /// Parse mode it would be sent with
+    pub parse_mode: ParseMode,
+    /// Entities of the text, outer ones before those they contain
+    pub entities: Vec<PreviewEntity>,
+    /// Length as Telegram counts it, see [`Formatter::text_length`]
+    pub length: usize,
+    /// Whether the text was shortened by the `truncate` option
+    pub truncated: bool,
+    /// Whether the text is longer than a message may be, so it would have to be split
+    pub exceeds_limit: bool,
+}
+
+/// An entity found by [`Formatter::preview`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewEntity {
+    /// What the entity is
+    pub kind: EntityKind,
+    /// Byte range in the formatted text, including markup
+    pub range: Range<usize>,
+}
+
 impl Formatter {
     /// Main formatting function that applies all configured transformations
-    pub fn format(
+    ///
+    /// Every pass borrows its input and only allocates when it actually changes
+    /// something, so plain messages without prefix/suffix are returned as-is.
+    pub fn format<'a>(
+        text: &'a str,
+        formatting_options: Option<FormattingOptions>,
+    ) -> Result<Cow<'a, str>, Error> {
+        Self::format_with_limit(text, formatting_options, None)
+    }
+
+    /// Format a media caption with the same pipeline as message texts
+    ///
+    /// Captions are always truncated to [`MAX_CAPTION_LENGTH`], or to the
+    /// configured `truncate` limit if it is lower.
+    pub fn format_caption<'a>(
+        text: &'a str,
+        formatting_options: Option<FormattingOptions>,
+    ) -> Result<Cow<'a, str>, Error> {
+        Self::format_for(text, formatting_options, FormatContext::Caption)
+    }
+
+    /// Format a text for `context`, truncating it to the context's limit
+    ///
+    /// A configured `truncate` limit applies if it is lower.
+    pub fn format_for<'a>(
+        text: &'a str,
+        formatting_options: Option<FormattingOptions>,
+        context: FormatContext,
+    ) -> Result<Cow<'a, str>, Error> {
+        Self::format_with_limit(text, formatting_options, Some(context.max_length()))
+    }
+
+    /// Format a text for `context`, splitting off what exceeds the limit instead of truncating it
+    ///
+    /// Returns the formatted text that fits and the unformatted rest, if any,
+    /// e.g. to send a long caption's overflow as follow-up messages (see
+    /// [`Formatter::split_message`]). The text is split at a line break or
+    /// space near the limit, never inside an escape sequence, HTML tag or HTML
+    /// entity, and a ``` code fence spanning the split is closed and reopened
+    /// in the rest. A `truncate` limit below the context's applies to the
+    /// head, and `truncate_lines` to the whole text.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::{FormatContext, Formatter, FormattingOptions};
+    ///
+    /// let caption = format!("Weekly report\n{}", "details ".repeat(200));
+    /// let (caption, overflow) =
+    ///     Formatter::split_for(&caption, None, FormatContext::Caption)?;
+    /// assert!(caption.encode_utf16().count() <= 1024);
+    /// assert!(overflow.unwrap().starts_with("details"));
+    /// # Ok::<(), telegrama_rs::Error>(())
+    /// ```
+    pub fn split_for(
         text: &str,
         formatting_options: Option<FormattingOptions>,
-    ) -> Result<String, Error> {
-        trace!("Original message: {}", text);
-
-        // Get the configuration clone to avoid mutex deadlocks
-        let config = match Configuration::get_cloned_instance() {
-            Ok(cfg) => cfg,
-            Err(e) => {
-                error!("Failed to get configuration: {}", e);
-                return Err(e);
-            }
+        context: FormatContext,
+    ) -> Result<(String, Option<String>), Error> {
+        let options = formatting_options.unwrap_or_else(|| {
+            FormattingOptions::from(Configuration::snapshot().formatting_options())
+        });
+        let max_length = options.truncate.map_or(context.max_length(), |truncate| {
+            truncate.min(context.max_length())
+        });
+        let text = match options.truncate_lines {
+            Some(lines) => Self::last_lines(text, lines, &options.truncation_suffix),
+            None => Cow::Borrowed(text),
+        };
+        let measure = FormattingOptions {
+            truncate: None,
+            truncate_lines: None,
+            ..options
         };
+        let formatted_length = |part: &str| -> Result<usize, Error> {
+            Ok(utf16_len(&Self::format_with_limit(
+                part,
+                Some(measure.clone()),
+                None,
+            )?))
+        };
+        if formatted_length(&text)? <= max_length {
+            let formatted = Self::format_with_limit(&text, Some(measure), None)?;
+            return Ok((formatted.into_owned(), None));
+        }
+
+        let (head, fence, rest) = next_part(
+            &text,
+            None,
+            "",
+            max_length,
+            SplitMode::Compact,
+            &formatted_length,
+        )?;
+        let rest = match fence {
+            Some(fence) => format!("{}\n{}", fence, rest),
+            None => rest.to_string(),
+        };
+        Ok((
+            Self::format_with_limit(&head, Some(measure), None)?.into_owned(),
+            Some(rest).filter(|rest| !rest.trim().is_empty()),
+        ))
+    }
+
+    /// Split a text into parts that each fit in one message of `context` once formatted
+    ///
+    /// The parts are returned unformatted, to be sent one by one with the same
+    /// options (see [`Client::send_long_message`]). They are measured formatted:
+    /// escaping and the configured prefix, suffix and timestamp count, and so does
+    /// a `truncate` limit below the context's. `truncate_lines` applies to the whole
+    /// text. A text that fits is returned as is, in a single part. A ``` code
+    /// fence spanning parts is closed at the end of one and reopened, with its
+    /// language, at the start of the next.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::{FormatContext, Formatter, SplitMode};
+    /// use telegrama_rs::{FormattingOptions, Telegrama};
+    ///
+    /// Telegrama::configure(|config| {
+    ///     config.set_formatting_options(FormattingOptions {
+    ///         truncate: Some(40),
+    ///         ..FormattingOptions::default()
+    ///     });
+    /// });
+    ///
+    /// let report = "Disk usage\n/: 91%\n\nMemory\nswap: 80%\n\nLoad\n1m: 7.2";
+    /// let parts = Formatter::split_message(report, None, FormatContext::Message, SplitMode::Lines)?;
+    /// assert_eq!(
+    ///     parts,
+    ///     ["(1/2) Disk usage\n/: 91%", "(2/2) Memory\nswap: 80%\n\nLoad\n1m: 7.2"]
+    /// );
+    /// # Ok::<(), telegrama_rs::Error>(())
+    /// ```
+    pub fn split_message(
+        text: &str,
+        formatting_options: Option<FormattingOptions>,
+        context: FormatContext,
+        mode: SplitMode,
+    ) -> Result<Vec<String>, Error> {
+        let options = formatting_options.unwrap_or_else(|| {
+            FormattingOptions::from(Configuration::snapshot().formatting_options())
+        });
+        let max_length = options.truncate.map_or(context.max_length(), |truncate| {
+            truncate.min(context.max_length())
+        });
+        let text = match options.truncate_lines {
+            Some(lines) => Self::last_lines(text, lines, &options.truncation_suffix),
+            None => Cow::Borrowed(text),
+        };
+        let measure = FormattingOptions {
+            truncate: None,
+            truncate_lines: None,
+            ..options
+        };
+        let formatted_length = |part: &str| -> Result<usize, Error> {
+            Ok(utf16_len(&Self::format_with_limit(
+                part,
+                Some(measure.clone()),
+                None,
+            )?))
+        };
+        if formatted_length(&text)? <= max_length {
+            return Ok(vec![text.into_owned()]);
+        }
+
+        // Reserve room for the widest part marker, growing it with the number of parts
+        let mut digits = 1;
+        loop {
+            let widest = "9".repeat(digits);
+            let placeholder = match mode {
+                SplitMode::Compact => String::new(),
+                SplitMode::Lines => part_marker(&widest, &widest),
+            };
+
+            let mut parts = Vec::new();
+            let mut rest = text.as_ref();
+            // Code fence left open by the previous part, reopened in the next one
+            let mut fence: Option<String> = None;
+            while !rest.is_empty() {
+                let (part, open, tail) = next_part(
+                    rest,
+                    fence.as_deref(),
+                    &placeholder,
+                    max_length,
+                    mode,
+                    &formatted_length,
+                )?;
+                parts.push(part);
+                fence = open;
+                rest = tail;
+            }
+
+            if mode == SplitMode::Compact {
+                return Ok(parts);
+            }
+            let count = parts.len().to_string();
+            if count.len() > digits {
+                digits = count.len();
+                continue;
+            }
+            return Ok(parts
+                .into_iter()
+                .enumerate()
+                .map(|(i, part)| format!("{}{}", part_marker(&(i + 1).to_string(), &count), part))
+                .collect());
+        }
+    }
+
+    /// Run the formatting pipeline, truncating to at most `max_length` if given
+    #[instrument(name = "telegrama.format", level = "debug", skip_all)]
+    fn format_with_limit<'a>(
+        text: &'a str,
+        formatting_options: Option<FormattingOptions>,
+        max_length: Option<usize>,
+    ) -> Result<Cow<'a, str>, Error> {
+        // Get a snapshot of the effective configuration
+        let config = Configuration::snapshot();
+
+        if config.log_message_text() {
+            trace!(text, "Original message");
+        } else {
+            trace!(length = text.len(), "Original message");
+        }
 
         // Determine formatting options
         let default_options = config.formatting_options();
-        let options = formatting_options.unwrap_or(FormattingOptions {
-            escape_markdown: default_options.escape_markdown,
-            obfuscate_emails: default_options.obfuscate_emails,
-            escape_html: default_options.escape_html,
-            truncate: default_options.truncate,
-        });
+        let options =
+            formatting_options.unwrap_or_else(|| FormattingOptions::from(default_options));
+
+        let mut text = Cow::Borrowed(text);
 
-        let mut text = text.to_string();
+        // Redact sensitive content first, patterns match the raw text
+        for rule in config.redactions() {
+            text = apply_pass(text, |text| rule.apply(text));
+        }
+        if options.scrub_secrets {
+            text = apply_pass(text, Self::scrub_secrets);
+        }
+        if let Some(filter) = &options.url_filter {
+            text = apply_pass(text, |text| filter.apply(text));
+        }
+
+        // Keep the last lines of the message itself, before anything is added to it
+        if let Some(lines) = options.truncate_lines {
+            text = apply_pass(text, |text| {
+                Self::last_lines(text, lines, &options.truncation_suffix)
+            });
+        }
 
         // Apply prefix and suffix if configured
         if let Some(prefix) = config.message_prefix() {
-            text = format!("{}{}", prefix, text);
+            text = Cow::Owned(format!("{}{}", prefix, text));
         }
 
         if let Some(suffix) = config.message_suffix() {
-            text = format!("{}{}", text, suffix);
+            text.to_mut().push_str(suffix);
         }
 
-        // Apply HTML escaping if enabled
-        if options.escape_html {
-            text = Self::escape_html(&text);
+        // Append a timestamp on its own line if configured
+        if let Some(timestamp_format) = config.timestamp_format() {
+            let timestamp = Self::timestamp(timestamp_format, config.timestamp_timezone())?;
+            let text = text.to_mut();
+            text.push('\n');
+            text.push_str(&timestamp);
+        }
+
+        // Apply HTML sanitizing or escaping if enabled
+        if options.sanitize_html {
+            text = apply_pass(text, Self::sanitize_html);
+        } else if options.escape_html {
+            text = apply_pass(text, Self::escape_html);
         }
 
         // Apply email obfuscation if enabled
         if options.obfuscate_emails {
-            text = Self::obfuscate_emails(&text);
+            text = apply_pass(text, Self::obfuscate_emails);
         }
 
         // Apply Markdown escaping if enabled
         if options.escape_markdown {
-            text = match Self::escape_markdown_v2(&text) {
+            text = apply_pass(text, |text| match Self::escape_markdown_v2(text) {
                 Ok(escaped) => escaped,
                 Err(e) => {
-                    error!("Markdown escaping failed: {}", e);
-                    Self::strip_markdown(&text)
+                    error!(error = %e, "Markdown escaping failed, stripping Markdown");
+                    Cow::Owned(Self::strip_markdown(text))
                 }
-            };
+            });
         }
 
-        // Apply truncation if enabled
-        if let Some(max_length) = options.truncate {
-            text = Self::truncate(&text, max_length);
+        // Apply truncation if enabled, never exceeding the hard limit
+        let max_length = match (options.truncate, max_length) {
+            (Some(truncate), Some(limit)) => Some(truncate.min(limit)),
+            (truncate, limit) => truncate.or(limit),
+        };
+        if let Some(max_length) = max_length {
+            let suffix = truncation_suffix(&options);
+            text = apply_pass(text, |text| {
+                Self::truncate_with(text, max_length, &suffix, options.truncate_mode)
+            });
         }
 
-        trace!("Formatted message: {}", text);
+        if config.log_message_text() {
+            trace!(text = %text, "Formatted message");
+        } else {
+            trace!(length = text.len(), "Formatted message");
+        }
 
         Ok(text)
     }
 
     /// Escape special characters for MarkdownV2 format while preserving formatting
-    pub fn escape_markdown_v2(text: &str) -> Result<String, Error> {
-        if text.is_empty() {
-            return Ok(String::new());
+    ///
+    /// The text is read with [`markdown::parse_lenient`] and rendered again:
+    /// entities (bold, italic, code, complete `[text](url)` links, ...) are
+    /// kept, every other special character is escaped. Delimiters without a
+    /// closing counterpart are escaped as plain text, and characters that are
+    /// already escaped stay escaped. Borrows the input when nothing needs
+    /// escaping.
+    pub fn escape_markdown_v2(text: &str) -> Result<Cow<'_, str>, Error> {
+        // Nothing to escape or format
+        if !text
+            .bytes()
+            .any(|b| b == b'\\' || is_special(&MARKDOWN_ESCAPE_TABLE, b))
+        {
+            return Ok(Cow::Borrowed(text));
         }
 
-        // First pre-process links to handle them specially
-        let text = Self::pre_process_links(text);
+        let escaped = markdown::render(&markdown::parse_lenient(text));
+        trace!(
+            original = text.len(),
+            escaped = escaped.len(),
+            "Markdown escaped"
+        );
+        Ok(Cow::Owned(escaped))
+    }
 
-        // Process with state tracking
-        let mut result = String::with_capacity(text.len() * 2);
+    /// Check that text is valid MarkdownV2 before sending it
+    ///
+    /// Reports the first unescaped reserved character, unbalanced or badly
+    /// nested entity, unterminated code block or malformed link, with its
+    /// offset, instead of Telegram's vague "can't parse entities" error.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// assert!(Formatter::validate_markdown_v2("*Deploy* finished\\!").is_ok());
+    ///
+    /// let error = Formatter::validate_markdown_v2("Version 1.2").unwrap_err();
+    /// assert_eq!(error.byte_offset, 9);
+    /// ```
+    pub fn validate_markdown_v2(text: &str) -> Result<(), MarkdownV2Error> {
+        markdown::parse(text).map(|_| ())
+    }
 
-        // State tracking
-        let mut in_code_block = false;
-        let mut in_pre_block = false;
-        let mut in_bold = false;
-        let mut in_italic = false;
+    /// Escape plain text for MarkdownV2, so it shows exactly as given
+    ///
+    /// Unlike [`Formatter::escape_markdown_v2`], no formatting is kept: every
+    /// reserved character and `\` is escaped. Use it for values put into
+    /// hand-built messages, e.g. `format!("*{}*", Formatter::escape_markdown_text(name))`.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// assert_eq!(Formatter::escape_markdown_text("v1.2 *beta*"), "v1\\.2 \\*beta\\*");
+    /// ```
+    pub fn escape_markdown_text(text: &str) -> Cow<'_, str> {
+        escape_chars(text, |c| c == '\\' || MARKDOWN_SPECIAL_CHARS.contains(&c))
+    }
 
-        // Track consecutive chars to handle pattern like [text](url)
-        let mut in_link_text = false;
-        let mut in_link_url = false;
+    /// Escape a URL for the `(...)` part of a MarkdownV2 link, where only `)` and `\` are special
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// let url = Formatter::escape_markdown_url("https://en.wikipedia.org/wiki/Rust_(language)");
+    /// assert_eq!(url, "https://en.wikipedia.org/wiki/Rust_(language\\)");
+    /// ```
+    pub fn escape_markdown_url(url: &str) -> Cow<'_, str> {
+        escape_chars(url, |c| c == ')' || c == '\\')
+    }
 
-        let mut chars = text.chars().peekable();
+    /// Escape text for a MarkdownV2 code span or block, where only `` ` `` and `\` are special
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// assert_eq!(Formatter::escape_markdown_code("echo `date` > *.log"), "echo \\`date\\` > *.log");
+    /// ```
+    pub fn escape_markdown_code(text: &str) -> Cow<'_, str> {
+        escape_chars(text, |c| c == '`' || c == '\\')
+    }
 
-        while let Some(c) = chars.next() {
-            match c {
-                // Handle code blocks (no escaping within code blocks)
+    /// Wrap text in a MarkdownV2 code block, e.g. for logs or command output
+    ///
+    /// The block is kept verbatim by [`Formatter::escape_markdown_v2`], so it can
+    /// be embedded in a message that is escaped as a whole.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// assert_eq!(Formatter::code_block("echo `date`"), "```\necho \\`date\\`\n```");
+    /// ```
+    pub fn code_block(text: &str) -> String {
+        format!("```\n{}\n```", Self::escape_markdown_code(text))
+    }
+
+    /// Mention a user by ID, so they get notified even without a username
+    ///
+    /// Produces `[name](tg://user?id=123)` in MarkdownV2 and
+    /// `<a href="tg://user?id=123">name</a>` in HTML, with the display name
+    /// escaped. MarkdownV2 mentions are kept as-is by
+    /// [`Formatter::escape_markdown_v2`], so they can be embedded in a message
+    /// that is escaped as a whole; HTML mentions need the `sanitize_html`
+    /// option. Plain text only contains the name.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    /// use telegrama_rs::ParseMode;
+    ///
+    /// assert_eq!(
+    ///     Formatter::mention(42, "J_Doe [ops]", ParseMode::MarkdownV2),
+    ///     "[J\\_Doe \\[ops\\]](tg://user?id=42)"
+    /// );
+    /// assert_eq!(
+    ///     Formatter::mention(42, "Tom & Jerry", ParseMode::Html),
+    ///     "<a href=\"tg://user?id=42\">Tom &amp; Jerry</a>"
+    /// );
+    /// ```
+    pub fn mention(user_id: i64, display_name: &str, parse_mode: ParseMode) -> String {
+        // Telegram drops links with an empty text
+        let user_id_text = user_id.to_string();
+        let name = match display_name.trim() {
+            "" => user_id_text.as_str(),
+            name => name,
+        };
+
+        match parse_mode {
+            ParseMode::MarkdownV2 => format!(
+                "[{}](tg://user?id={})",
+                Self::escape_markdown_text(name),
+                user_id
+            ),
+            ParseMode::Html => format!(
+                "<a href=\"tg://user?id={}\">{}</a>",
+                user_id,
+                Self::escape_html(name)
+            ),
+            // Legacy Markdown can't escape inside link texts
+            ParseMode::MarkdownLegacy => format!(
+                "[{}](tg://user?id={})",
+                name.replace(['[', ']', '*', '_', '`'], ""),
+                user_id
+            ),
+            ParseMode::Plain => name.to_string(),
+        }
+    }
+
+    /// Mention many users, grouped into lists of at most `max_length` UTF-16 code units
+    ///
+    /// Each list joins the [`Formatter::mention`]s with `", "`, so every list can
+    /// be sent as its own message. A single mention longer than `max_length`
+    /// gets a list of its own.
+    pub fn mentions<S: AsRef<str>>(
+        users: &[(i64, S)],
+        parse_mode: ParseMode,
+        max_length: usize,
+    ) -> Vec<String> {
+        let mut lists = Vec::new();
+        let mut current = String::new();
+
+        for (user_id, display_name) in users {
+            let mention = Self::mention(*user_id, display_name.as_ref(), parse_mode);
+            if !current.is_empty() && utf16_len(&current) + 2 + utf16_len(&mention) > max_length {
+                lists.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push_str(", ");
+            }
+            current.push_str(&mention);
+        }
+
+        if !current.is_empty() {
+            lists.push(current);
+        }
+        lists
+    }
+
+    /// Strip all Markdown formatting from text
+    pub fn strip_markdown(text: &str) -> String {
+        // Remove bold, italic, code, and links
+        let no_format = text.replace(['*', '_', '`'], "");
+
+        // Remove links in format [text](url)
+        LINK_REGEX.replace_all(&no_format, "$1").into_owned()
+    }
+
+    /// Obfuscate email addresses in text
+    pub fn obfuscate_emails(text: &str) -> Cow<'_, str> {
+        EMAIL_REGEX.replace_all(text, |caps: &regex::Captures| {
+            let email = &caps[0];
+            let parts: Vec<&str> = email.split('@').collect();
+
+            if parts.len() != 2 {
+                trace!("Not a standard email format");
+                return email.to_string();
+            }
+
+            let username = parts[0];
+            let domain = parts[1];
+
+            if username.len() <= 3 {
+                trace!("Username too short to obfuscate");
+                return email.to_string();
+            }
+
+            // Keep first three chars and last one of username
+            let visible_prefix = &username[0..3];
+            let visible_suffix = &username[username.len() - 1..];
+
+            let result = format!("{}...{}@{}", visible_prefix, visible_suffix, domain);
+            trace!("Obfuscated email");
+
+            result
+        })
+    }
+
+    /// Mask common credential shapes with `[REDACTED]`
+    ///
+    /// Covers AWS access keys and `aws_secret_access_key` assignments,
+    /// `Bearer` tokens, GitHub tokens (`ghp_`, `github_pat_`, ...), Telegram
+    /// bot tokens and hex strings of 32 or more digits, so stack traces and
+    /// config dumps pasted into alerts don't leak credentials. Git commit
+    /// hashes are long hex strings too and get masked as well.
+    pub fn scrub_secrets(text: &str) -> Cow<'_, str> {
+        SECRET_REGEX.replace_all(text, |caps: &regex::Captures| {
+            let label = caps
+                .name("key")
+                .or_else(|| caps.name("scheme"))
+                .map_or("", |label| label.as_str());
+            format!("{}{}", label, REDACTED)
+        })
+    }
+
+    /// Fill the `{}` placeholders of a MarkdownV2 template with escaped values
+    ///
+    /// The template is kept as written, so it must already be valid MarkdownV2;
+    /// only the values are escaped, for the context of their placeholder: as
+    /// code inside `` ` `` or ```` ``` ````, as a URL inside the `(...)` of a
+    /// link, and as plain text everywhere else. Fails if the number of
+    /// placeholders and values differ. Send the result with the
+    /// `escape_markdown` option turned off.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// let text = Formatter::interpolate(
+    ///     "*Deploy* of `{}` to {} {}",
+    ///     &["my-crate", "prod-eu.1", "failed!"],
+    /// )?;
+    /// assert_eq!(text, "*Deploy* of `my-crate` to prod\\-eu\\.1 failed\\!");
+    /// # Ok::<(), telegrama_rs::Error>(())
+    /// ```
+    pub fn interpolate<S: AsRef<str>>(template: &str, values: &[S]) -> Result<String, Error> {
+        let mut result = String::with_capacity(template.len() + values.len() * 16);
+        let mut values = values.iter();
+        let mut placeholders = 0;
+        let mut fence: Option<&str> = None;
+        let mut in_url = false;
+        // Whether the last thing copied was an unescaped `]` closing a link text
+        let mut link_text_closed = false;
+
+        let mut i = 0;
+        while i < template.len() {
+            let rest = &template[i..];
+            if rest.starts_with("{}") {
+                placeholders += 1;
+                let Some(value) = values.next() else {
+                    return Err(Error::formatting(format!(
+                        "Template has more placeholders than the {} value(s) given",
+                        placeholders - 1
+                    )));
+                };
+                let value = value.as_ref();
+                result.push_str(&if fence.is_some() {
+                    Self::escape_markdown_code(value)
+                } else if in_url {
+                    Self::escape_markdown_url(value)
+                } else {
+                    Self::escape_markdown_text(value)
+                });
+                link_text_closed = false;
+                i += 2;
+                continue;
+            }
+
+            let c = rest.chars().next().unwrap_or_default();
+            let len = match c {
+                // Escaped characters are literal, including the backslash
+                '\\' => 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
                 '`' => {
-                    // Check for triple backtick (code block)
-                    if !in_code_block
-                        && chars.peek() == Some(&'`')
-                        && chars.clone().nth(1) == Some('`')
-                    {
-                        // Start/end of triple backtick block
-                        result.push(c);
-                        result.push(chars.next().unwrap()); // consume second backtick
-                        result.push(chars.next().unwrap()); // consume third backtick
-                        in_pre_block = !in_pre_block;
-                        trace!("Triple backtick: in_pre_block={}", in_pre_block);
-                    } else {
-                        // Single backtick (inline code)
-                        result.push(c);
-                        in_code_block = !in_code_block;
-                        trace!("Single backtick: in_code_block={}", in_code_block);
-                    }
+                    let marker = if rest.starts_with("```") { "```" } else { "`" };
+                    fence = match fence {
+                        Some(open) if open == marker => None,
+                        None if !in_url => Some(marker),
+                        open => open,
+                    };
+                    marker.len()
                 }
-
-                // Handle bold formatting
-                '*' if !in_code_block && !in_pre_block => {
-                    // Toggle bold state
-                    in_bold = !in_bold;
-                    result.push(c);
+                '(' if link_text_closed => {
+                    in_url = true;
+                    1
                 }
-
-                // Handle italic formatting
-                '_' if !in_code_block && !in_pre_block => {
-                    // Toggle italic state
-                    in_italic = !in_italic;
-                    result.push(c);
+                ')' if in_url => {
+                    in_url = false;
+                    1
                 }
+                _ => c.len_utf8(),
+            };
+            link_text_closed = c == ']' && fence.is_none();
+            result.push_str(&rest[..len]);
+            i += len;
+        }
 
-                // Handle link text start
-                '[' if !in_code_block && !in_pre_block && !in_link_text => {
-                    in_link_text = true;
-                    result.push(c);
-                }
+        let unused = values.len();
+        if unused > 0 {
+            return Err(Error::formatting(format!(
+                "Template has {} placeholder(s) but {} more value(s) were given",
+                placeholders, unused
+            )));
+        }
+        Ok(result)
+    }
 
-                // Handle link text end
-                ']' if !in_code_block && !in_pre_block && in_link_text => {
-                    in_link_text = false;
-                    result.push(c);
+    /// Escape an HTML attribute value for use inside double quotes
+    ///
+    /// Escapes `"` besides `<`, `>` and `&`, e.g. for the `href` of a hand-built link.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// let href = Formatter::escape_html_attribute("https://example.com/?q=\"a\"&page=2");
+    /// assert_eq!(href, "https://example.com/?q=&quot;a&quot;&amp;page=2");
+    /// ```
+    pub fn escape_html_attribute(value: &str) -> Cow<'_, str> {
+        if !value.contains(['&', '"', '<', '>']) {
+            return Cow::Borrowed(value);
+        }
+        Cow::Owned(
+            value
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+        )
+    }
 
-                    // Check if followed by opening parenthesis for URL
-                    if chars.peek() == Some(&'(') {
-                        in_link_url = true;
-                    }
-                }
+    /// Escape HTML special characters
+    pub fn escape_html(text: &str) -> Cow<'_, str> {
+        let bytes = text.as_bytes();
+        let Some(first) = bytes
+            .iter()
+            .position(|&b| is_special(&HTML_ESCAPE_TABLE, b))
+        else {
+            return Cow::Borrowed(text);
+        };
 
-                // Handle link URL start
-                '(' if !in_code_block && !in_pre_block && !in_link_text && in_link_url => {
-                    result.push(c);
-                }
+        let mut result = String::with_capacity(text.len() + 16);
+        let mut copied = 0;
+        for (i, &b) in bytes.iter().enumerate().skip(first) {
+            let entity = match b {
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'&' => "&amp;",
+                _ => continue,
+            };
+            result.push_str(&text[copied..i]);
+            result.push_str(entity);
+            copied = i + 1;
+        }
+        result.push_str(&text[copied..]);
 
-                // Handle link URL end
-                ')' if !in_code_block && !in_pre_block && in_link_url => {
-                    in_link_url = false;
-                    result.push(c);
-                }
+        Cow::Owned(result)
+    }
 
-                // Escape special characters outside formatting contexts
-                _ if !in_code_block
-                    && !in_pre_block
-                    && !in_bold
-                    && !in_italic
-                    && !in_link_text
-                    && !in_link_url
-                    && MARKDOWN_SPECIAL_CHARS.contains(&c) =>
-                {
-                    result.push('\\');
-                    result.push(c);
-                    trace!("Escaped character: \\{}", c);
-                }
+    /// Reduce arbitrary HTML to the subset Telegram accepts
+    ///
+    /// Supported tags (`b`, `i`, `u`, `s`, `a`, `code`, `pre`, `blockquote`,
+    /// `tg-spoiler`, `tg-emoji` and their aliases) are kept with only the
+    /// attributes Telegram understands. Other tags are stripped but their text
+    /// is kept, except for `script` and `style` whose content is dropped, and
+    /// `<br>` becomes a newline. Stray `<`, `>` and `&` are escaped. Borrows the
+    /// input when it is already valid.
+    pub fn sanitize_html(text: &str) -> Cow<'_, str> {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        // Spoiler spans still open, so that closing tags of stripped spans are dropped too
+        let mut open_spans = 0usize;
+
+        while let Some(position) = rest.find(['<', '>', '&']) {
+            result.push_str(&rest[..position]);
+            let tail = &rest[position..];
 
-                // Regular character or character within a formatting context
-                _ => {
-                    result.push(c);
+            match tail.as_bytes()[0] {
+                b'&' => {
+                    let entity_len = html_entity_len(tail);
+                    if entity_len > 0 {
+                        result.push_str(&tail[..entity_len]);
+                        rest = &tail[entity_len..];
+                    } else {
+                        result.push_str("&amp;");
+                        rest = &tail[1..];
+                    }
+                }
+                b'>' => {
+                    result.push_str("&gt;");
+                    rest = &tail[1..];
                 }
+                _ => match parse_html_tag(tail) {
+                    Some(tag) => {
+                        rest = &tail[tag.len..];
+                        if tag.name == "span" && tag.closing && open_spans == 0 {
+                            // Closes a span that was stripped
+                        } else if let Some(kept) = sanitize_html_tag(&tag) {
+                            if tag.name == "span" {
+                                open_spans = if tag.closing {
+                                    open_spans - 1
+                                } else {
+                                    open_spans + 1
+                                };
+                            }
+                            result.push_str(&kept);
+                        } else if tag.name == "br" {
+                            result.push('\n');
+                        } else if !tag.closing && matches!(tag.name.as_str(), "script" | "style") {
+                            // Skip everything up to the matching closing tag
+                            let closing = format!("</{}", tag.name);
+                            rest = match rest.to_ascii_lowercase().find(&closing) {
+                                Some(end) => {
+                                    let after = &rest[end..];
+                                    after.find('>').map_or("", |close| &after[close + 1..])
+                                }
+                                None => "",
+                            };
+                        }
+                    }
+                    None => {
+                        result.push_str("&lt;");
+                        rest = &tail[1..];
+                    }
+                },
             }
         }
-        Ok(result)
+        result.push_str(rest);
+
+        if result == text {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(result)
+        }
     }
 
-    /// Pre-process links to handle them as special entities
-    fn pre_process_links(text: &str) -> String {
-        // This method will identify complete markdown links [text](url) and handle them specially
-        let mut result = text.to_string();
-
-        // First process complete markdown links
-        result = LINK_REGEX
-            .replace_all(&result, |caps: &regex::Captures| {
-                let link_text = &caps[1];
-                let url = &caps[2];
-
-                // Escape special characters in the link text
-                let escaped_text = link_text
-                    .chars()
-                    .map(|c| {
-                        if MARKDOWN_SPECIAL_CHARS.contains(&c) {
-                            format!("\\{}", c)
-                        } else {
-                            c.to_string()
+    /// Render the current time with a strftime pattern in the given timezone
+    ///
+    /// The timezone may be `UTC` (the default), `Local`, or an IANA name such as
+    /// `Europe/Berlin` when the `chrono-tz` feature is enabled.
+    pub fn timestamp(format: &str, timezone: Option<&str>) -> Result<String, Error> {
+        let items: Vec<Item> = StrftimeItems::new(format).collect();
+        if items.iter().any(|item| matches!(item, Item::Error)) {
+            return Err(Error::formatting(format!(
+                "Invalid timestamp format: '{}'",
+                format
+            )));
+        }
+
+        let timestamp = match timezone {
+            None => Utc::now().format_with_items(items.iter()).to_string(),
+            Some(tz) if tz.eq_ignore_ascii_case("UTC") => {
+                Utc::now().format_with_items(items.iter()).to_string()
+            }
+            Some(tz) if tz.eq_ignore_ascii_case("Local") => {
+                Local::now().format_with_items(items.iter()).to_string()
+            }
+            Some(tz) => Self::timestamp_in_zone(&items, tz)?,
+        };
+
+        Ok(timestamp)
+    }
+
+    #[cfg(feature = "chrono-tz")]
+    fn timestamp_in_zone(items: &[Item], timezone: &str) -> Result<String, Error> {
+        let tz: chrono_tz::Tz = timezone
+            .parse()
+            .map_err(|_| Error::configuration(format!("Unknown timezone: '{}'", timezone)))?;
+        Ok(Utc::now()
+            .with_timezone(&tz)
+            .format_with_items(items.iter())
+            .to_string())
+    }
+
+    #[cfg(not(feature = "chrono-tz"))]
+    fn timestamp_in_zone(_items: &[Item], timezone: &str) -> Result<String, Error> {
+        Err(Error::configuration(format!(
+            "Timezone '{}' requires the `chrono-tz` feature",
+            timezone
+        )))
+    }
+
+    /// Length of a formatted text as Telegram counts it, in UTF-16 code units
+    ///
+    /// Telegram counts the text left after parsing `parse_mode`: HTML tags,
+    /// Markdown markers, escaping backslashes and link URLs don't count, and
+    /// an HTML entity counts as one character.
+    ///
+    /// ```
+    /// use telegrama_rs::{Formatter, ParseMode};
+    ///
+    /// assert_eq!(Formatter::text_length("<b>Déploy</b> &amp; 🚀", ParseMode::Html), 11);
+    /// assert_eq!(Formatter::text_length("*Done* \\(v1\\.2\\)", ParseMode::MarkdownV2), 11);
+    /// assert_eq!(Formatter::text_length("[docs](https://example.com)", ParseMode::MarkdownV2), 4);
+    /// ```
+    pub fn text_length(text: &str, parse_mode: ParseMode) -> usize {
+        let mut length = 0;
+        let mut in_code = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (parse_mode, c) {
+                (ParseMode::Html, '<') => {
+                    chars.by_ref().find(|&c| c == '>');
+                }
+                (ParseMode::Html, '&') => {
+                    // An entity like `&amp;` or `&#128640;`, else a lone ampersand
+                    let entity: String = chars.clone().take(10).collect();
+                    match entity.find(';') {
+                        Some(end)
+                            if entity[..end]
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '#') =>
+                        {
+                            chars.nth(end);
+                            length += 1;
+                        }
+                        _ => length += 1,
+                    }
+                }
+                (ParseMode::MarkdownV2, '\\') => {
+                    if let Some(escaped) = chars.next() {
+                        length += escaped.len_utf16();
+                    }
+                }
+                // Markers are literal inside code
+                (ParseMode::MarkdownV2 | ParseMode::MarkdownLegacy, '`') => in_code = !in_code,
+                _ if in_code => length += c.len_utf16(),
+                (ParseMode::MarkdownV2 | ParseMode::MarkdownLegacy, ']')
+                    if chars.peek() == Some(&'(') =>
+                {
+                    // The URL of a link isn't shown
+                    let mut escaped = false;
+                    for c in chars.by_ref() {
+                        match c {
+                            ')' if !escaped => break,
+                            '\\' => escaped = !escaped,
+                            _ => escaped = false,
                         }
-                    })
-                    .collect::<String>();
-
-                // For the URL, don't escape the protocol part
-                let mut escaped_url = url.to_string();
-                for ch in MARKDOWN_SPECIAL_CHARS {
-                    if *ch != '/' && *ch != ':' && *ch != '.' && *ch != '-' {
-                        escaped_url = escaped_url.replace(*ch, &format!("\\{}", ch));
                     }
                 }
+                (ParseMode::MarkdownV2, '*' | '_' | '~' | '|' | '[')
+                | (ParseMode::MarkdownLegacy, '*' | '_' | '[') => {}
+                _ => length += c.len_utf16(),
+            }
+        }
+        length
+    }
+
+    /// Check that a formatted text fits Telegram's limit in `context`
+    ///
+    /// Fails with [`Error::MessageTooLong`] if it doesn't, see
+    /// [`Formatter::text_length`].
+    pub fn check_length(
+        text: &str,
+        parse_mode: ParseMode,
+        context: FormatContext,
+    ) -> Result<(), Error> {
+        let length = Self::text_length(text, parse_mode);
+        let limit = context.max_length();
+        if length > limit {
+            return Err(Error::MessageTooLong { length, limit });
+        }
+        Ok(())
+    }
 
-                format!("[{}]({})", escaped_text, escaped_url)
-            })
-            .to_string();
+    /// Show what [`Client::send_message`](crate::Client::send_message) would send for `text`
+    ///
+    /// Takes the same `parse_mode` and formatting options, falling back to the
+    /// configuration like sending does, and formats `text` without sending it.
+    /// Content filters don't run. Entities are found for MarkdownV2 and HTML.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    /// use telegrama_rs::markdown::EntityKind;
+    /// use telegrama_rs::{ParseMode, Telegrama};
+    ///
+    /// let preview = Telegrama::with_config(
+    ///     |config| {
+    ///         config.set_message_prefix("");
+    ///         config.set_message_suffix("");
+    ///     },
+    ///     || Formatter::preview("*Deploy* of v1.2 done", &[("parse_mode", "MarkdownV2")]),
+    /// )?;
+    /// assert_eq!(preview.text, "*Deploy* of v1\\.2 done");
+    /// assert_eq!(preview.parse_mode, ParseMode::MarkdownV2);
+    /// assert_eq!(preview.entities[0].kind, EntityKind::Bold);
+    /// assert_eq!(preview.length, 19);
+    /// assert!(!preview.truncated && !preview.exceeds_limit);
+    /// # Ok::<(), telegrama_rs::Error>(())
+    /// ```
+    pub fn preview(text: &str, options: &[(&str, &str)]) -> Result<Preview, Error> {
+        let config = Configuration::snapshot();
+        let parse_mode = Client::extract_parse_mode(options, &config);
+        let formatting_options = Client::formatting_for(
+            parse_mode,
+            &Client::extract_formatting_options(
+                options,
+                FormattingOptions::from(config.formatting_options()),
+            ),
+        );
 
-        result
+        // Truncate separately to tell whether it happened
+        let max_length = formatting_options.truncate;
+        let full = Self::format_with_limit(
+            text,
+            Some(FormattingOptions {
+                truncate: None,
+                ..formatting_options.clone()
+            }),
+            None,
+        )?;
+        let truncated = max_length.is_some_and(|max_length| utf16_len(&full) > max_length)
+            || formatting_options
+                .truncate_lines
+                .is_some_and(|lines| Self::last_lines(text, lines, "").len() < text.len());
+        let suffix = truncation_suffix(&formatting_options);
+        let text = match max_length {
+            Some(max_length) => {
+                Self::truncate_with(&full, max_length, &suffix, formatting_options.truncate_mode)
+                    .into_owned()
+            }
+            None => full.into_owned(),
+        };
+
+        let entities = match parse_mode {
+            ParseMode::MarkdownV2 => {
+                let nodes =
+                    markdown::parse(&text).unwrap_or_else(|_| markdown::parse_lenient(&text));
+                let mut entities = Vec::new();
+                flatten_entities(nodes, &mut entities);
+                entities
+            }
+            ParseMode::Html => html_entities(&text),
+            ParseMode::MarkdownLegacy | ParseMode::Plain => Vec::new(),
+        };
+        let length = Self::text_length(&text, parse_mode);
+
+        Ok(Preview {
+            parse_mode,
+            entities,
+            length,
+            truncated,
+            exceeds_limit: length > MAX_MESSAGE_LENGTH,
+            text,
+        })
     }
 
-    /// Strip all Markdown formatting from text
-    pub fn strip_markdown(text: &str) -> String {
-        // Remove bold, italic, code, and links
-        let no_format = text.replace(['*', '_', '`'], "");
+    /// Keep the last `lines` lines of text, regardless of their length
+    ///
+    /// When lines are dropped, `marker` is put on a line of its own in their place.
+    /// A trailing line break doesn't count as an extra line.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// let log = "booting\nlistening on :8080\nGET /health 200\nGET /orders 500\n";
+    /// assert_eq!(
+    ///     Formatter::last_lines(log, 2, "..."),
+    ///     "...\nGET /health 200\nGET /orders 500\n"
+    /// );
+    /// assert_eq!(Formatter::last_lines(log, 4, "..."), log);
+    /// ```
+    pub fn last_lines<'a>(text: &'a str, lines: usize, marker: &str) -> Cow<'a, str> {
+        let body = text.strip_suffix('\n').unwrap_or(text);
+        let start = match lines {
+            0 => text.len(),
+            _ => match body.rmatch_indices('\n').nth(lines - 1) {
+                Some((newline, _)) => newline + 1,
+                None => return Cow::Borrowed(text),
+            },
+        };
+        if marker.is_empty() {
+            Cow::Borrowed(&text[start..])
+        } else {
+            Cow::Owned(format!("{}\n{}", marker, &text[start..]))
+        }
+    }
 
-        // Remove links in format [text](url)
-        let link_regex = Regex::new(r"\[([^\]]+)\]\([^)]+\)").unwrap_or_else(|_| {
-            error!("Failed to compile link regex");
-            Regex::new(r"").unwrap()
+    /// Truncate text to a maximum length, ending it with `...`
+    pub fn truncate(text: &str, max_length: usize) -> Cow<'_, str> {
+        Self::truncate_with_suffix(text, max_length, "...")
+    }
+
+    /// Truncate text to a maximum length, ending it with `suffix`
+    ///
+    /// The suffix counts towards `max_length`; it is cut itself if it doesn't fit.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    ///
+    /// let text = Formatter::truncate_with_suffix("disk full on db-1 and db-2", 20, " [cut]");
+    /// assert_eq!(text, "disk full on [cut]");
+    /// ```
+    pub fn truncate_with_suffix<'a>(
+        text: &'a str,
+        max_length: usize,
+        suffix: &str,
+    ) -> Cow<'a, str> {
+        Self::truncate_with(text, max_length, suffix, TruncateMode::KeepHead)
+    }
+
+    /// Truncate text to a maximum length, keeping the part `mode` asks for
+    ///
+    /// Lengths are in UTF-16 code units, as Telegram counts them. `suffix`
+    /// marks the cut: at the end when keeping the head, at the start when
+    /// keeping the tail, and in the middle when keeping both ends. It counts
+    /// towards `max_length`. Cuts are made at line breaks or spaces
+    /// where possible, and never inside escape sequences, HTML tags or entities.
+    ///
+    /// ```
+    /// use telegrama_rs::formatter::Formatter;
+    /// use telegrama_rs::TruncateMode;
+    ///
+    /// let log = "start\nstep 1\nstep 2\nstep 3\npanicked at main.rs";
+    /// assert_eq!(
+    ///     Formatter::truncate_with(log, 30, "...\n", TruncateMode::KeepTail),
+    ///     "...\nstep 3\npanicked at main.rs"
+    /// );
+    /// assert_eq!(
+    ///     Formatter::truncate_with(log, 30, "\n...\n", TruncateMode::KeepBothEnds),
+    ///     "start\nstep\n...\nat main.rs"
+    /// );
+    /// ```
+    pub fn truncate_with<'a>(
+        text: &'a str,
+        max_length: usize,
+        suffix: &str,
+        mode: TruncateMode,
+    ) -> Cow<'a, str> {
+        if utf16_len(text) <= max_length {
+            return Cow::Borrowed(text);
+        }
+
+        // Leave room for the suffix
+        let budget = max_length.saturating_sub(utf16_len(suffix));
+        if budget == 0 {
+            return Cow::Owned(suffix[..utf16_prefix_end(suffix, max_length)].to_string());
+        }
+
+        Cow::Owned(match mode {
+            TruncateMode::KeepHead => format!("{}{}", &text[..head_end(text, budget)], suffix),
+            TruncateMode::KeepTail => format!("{}{}", suffix, &text[tail_start(text, budget)..]),
+            TruncateMode::KeepBothEnds => {
+                let head = &text[..head_end(text, budget / 2)];
+                let tail = &text[tail_start(text, budget - budget / 2)..];
+                format!("{}{}{}", head, suffix, tail)
+            }
+        })
+    }
+}
+
+/// Characters of context on each side of a [`Error::BadEntities`] snippet
+const SNIPPET_CONTEXT: usize = 20;
+
+/// Turn an entity parse error of Telegram into [`Error::BadEntities`]
+///
+/// The byte offset in `description` points into `formatted`, the text
+/// that was sent. It's traced back through escaping to `original` (the text
+/// the caller passed), which was formatted with `prefix` and `suffix` around it.
+/// Returns `None` if `description` isn't an entity parse error.
+pub(crate) fn bad_entities(
+    description: &str,
+    formatted: &str,
+    original: &str,
+    prefix: &str,
+    suffix: &str,
+) -> Option<Error> {
+    if !description.contains("can't parse entities") {
+        return None;
+    }
+    let Some(sent_offset) = description
+        .split_once("byte offset ")
+        .and_then(|(_, rest)| {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            rest[..digits].parse::<usize>().ok()
+        })
+    else {
+        return Some(Error::BadEntities {
+            description: description.to_string(),
+            offset: None,
+            snippet: String::new(),
         });
+    };
 
-        let result = link_regex.replace_all(&no_format, "$1").to_string();
+    let source = format!("{}{}{}", prefix, original, suffix);
+    let offset = original_offset(formatted, &source, sent_offset)
+        .checked_sub(prefix.len())
+        .filter(|&offset| offset < original.len());
+    let snippet = match offset {
+        Some(offset) => snippet(original, offset),
+        None => snippet(formatted, sent_offset),
+    };
+    Some(Error::BadEntities {
+        description: description.to_string(),
+        offset,
+        snippet,
+    })
+}
 
-        result
+/// Byte offset in `source` that `offset` in its formatted form `formatted` came from
+///
+/// Walks both texts together: equal characters advance both, Markdown escapes
+/// and HTML entities stand for one character of `source`, and anything else
+/// was added by formatting.
+fn original_offset(formatted: &str, source: &str, offset: usize) -> usize {
+    let (mut f, mut s) = (0, 0);
+    while f < offset.min(formatted.len()) {
+        let rest = &formatted[f..];
+        let Some(c) = source[s..].chars().next() else {
+            break;
+        };
+        let entity = match c {
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '&' => "&amp;",
+            _ => "",
+        };
+        let step =
+            if !entity.is_empty() && rest.starts_with(entity) && !source[s..].starts_with(entity) {
+                entity.len()
+            } else if rest.starts_with('\\') && rest[1..].starts_with(c) {
+                1 + c.len_utf8()
+            } else if rest.starts_with(c) {
+                c.len_utf8()
+            } else {
+                f += rest.chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+        // An offset inside an escape or entity points at its character
+        if f + step > offset {
+            break;
+        }
+        f += step;
+        s += c.len_utf8();
     }
+    s
+}
 
-    /// Obfuscate email addresses in text
-    pub fn obfuscate_emails(text: &str) -> String {
-        let result = EMAIL_REGEX
-            .replace_all(text, |caps: &regex::Captures| {
-                let email = &caps[0];
-                let parts: Vec<&str> = email.split('@').collect();
-
-                if parts.len() != 2 {
-                    trace!("Not a standard email format: {}", email);
-                    return email.to_string();
-                }
+/// Up to [`SNIPPET_CONTEXT`] characters on each side of `offset`, marked with `⟨here⟩`
+fn snippet(text: &str, offset: usize) -> String {
+    let mut at = offset.min(text.len());
+    while !text.is_char_boundary(at) {
+        at -= 1;
+    }
+    let (before, after) = text.split_at(at);
+    let start = before
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map_or(0, |(i, _)| i);
+    let end = after
+        .char_indices()
+        .nth(SNIPPET_CONTEXT)
+        .map_or(after.len(), |(i, _)| i);
+    format!("{}⟨here⟩{}", &before[start..], &after[..end])
+}
 
-                let username = parts[0];
-                let domain = parts[1];
+/// Take the next part of `rest` that fits in `max_length` once formatted, see [`Formatter::split_message`]
+///
+/// `fence` is the code fence left open by the previous part, reopened at the
+/// start of this one, and `placeholder` the room kept for a part marker.
+/// Returns the part, the code fence it leaves open and the rest of the text.
+fn next_part<'a>(
+    rest: &'a str,
+    fence: Option<&str>,
+    placeholder: &str,
+    max_length: usize,
+    mode: SplitMode,
+    formatted_length: &dyn Fn(&str) -> Result<usize, Error>,
+) -> Result<(String, Option<String>, &'a str), Error> {
+    let reopen = fence.map_or(String::new(), |fence| format!("{}\n", fence));
 
-                if username.len() <= 3 {
-                    trace!("Username too short to obfuscate: {}", username);
-                    return email.to_string();
-                }
+    // Shrink the part by what formatting adds until it fits
+    let mut budget = max_length;
+    let (at, part) = loop {
+        let at = match mode {
+            _ if utf16_len(rest) <= budget => rest.len(),
+            SplitMode::Compact => split_point(rest, budget),
+            SplitMode::Lines => line_split_point(rest, budget),
+        };
+        let at = at.max(rest.chars().next().map_or(0, char::len_utf8));
+        let mut part = format!("{}{}", reopen, &rest[..at]);
+        if open_code_fence(&part).is_some() {
+            part.push_str("\n```");
+        }
+        let length = formatted_length(&format!("{}{}", placeholder, part))?;
+        if length <= max_length || budget == 1 {
+            break (at, part);
+        }
+        budget = budget.saturating_sub(length - max_length).max(1);
+    };
 
-                // Keep first three chars and last one of username
-                let visible_prefix = &username[0..3];
-                let visible_suffix = &username[username.len() - 1..];
+    let open = open_code_fence(&format!("{}{}", reopen, &rest[..at]))
+        .map(|language| format!("```{}", language));
+    let tail = &rest[at..];
+    let tail = match tail.strip_prefix(' ') {
+        Some(tail) => tail,
+        None => tail.trim_start_matches('\n'),
+    };
+    Ok((part, open, tail))
+}
 
-                let result = format!("{}...{}@{}", visible_prefix, visible_suffix, domain);
-                trace!("Obfuscated email: {} -> {}", email, result);
+/// Byte offset to split formatted `text` at so the head has at most `max_length` UTF-16 code units
+///
+/// Prefers the last line break in the second half, then the last space. The split moves back
+/// out of escape sequences, HTML tags and entities, unless that would leave the head empty.
+fn split_point(text: &str, max_length: usize) -> usize {
+    let at = utf16_prefix_end(text, max_length);
+    let head = &text[..at];
+    // A line break early on would leave little in the head
+    let split = head
+        .rfind('\n')
+        .filter(|&newline| utf16_len(&head[..newline]) >= utf16_len(head) / 2)
+        .or_else(|| head.rfind(' ').filter(|&space| space > 0))
+        .unwrap_or(at);
+    match retreat_from_markup(text, split) {
+        0 => split,
+        safe => safe,
+    }
+}
 
-                result
-            })
-            .to_string();
+/// Like [`split_point`], but preferring a blank line in the second half, then any line break
+fn line_split_point(text: &str, max_length: usize) -> usize {
+    let head = &text[..utf16_prefix_end(text, max_length)];
+    if let Some(blank) = head
+        .rfind("\n\n")
+        .filter(|&blank| utf16_len(&head[..blank]) >= utf16_len(head) / 2)
+    {
+        return blank;
+    }
+    if let Some(newline) = head.rfind('\n').filter(|&newline| newline > 0) {
+        return newline;
+    }
+    split_point(text, max_length)
+}
 
-        result
+/// Language of the ``` code fence left open at the end of `text`, empty if it has none
+fn open_code_fence(text: &str) -> Option<&str> {
+    let mut open = None;
+    let mut rest = text;
+    while let Some(at) = rest.find("```") {
+        rest = &rest[at + 3..];
+        open = match open {
+            Some(_) => None,
+            None => {
+                // The language is the rest of the opening line, if it is a single word
+                let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+                Some(if line.contains(['`', ' ']) { "" } else { line })
+            }
+        };
     }
+    open
+}
 
-    /// Escape HTML special characters
-    pub fn escape_html(text: &str) -> String {
-        let result = HTML_REGEX
-            .replace_all(text, |caps: &regex::Captures| match &caps[0] {
-                "<" => {
-                    trace!("Escaped HTML: < -> &lt;");
-                    "&lt;".to_string()
-                }
-                ">" => {
-                    trace!("Escaped HTML: > -> &gt;");
-                    "&gt;".to_string()
-                }
-                "&" => {
-                    trace!("Escaped HTML: & -> &amp;");
-                    "&amp;".to_string()
-                }
-                _ => caps[0].to_string(),
-            })
-            .to_string();
+/// Marker put before each part of a message split in [`SplitMode::Lines`]
+fn part_marker(number: &str, count: &str) -> String {
+    format!("({}/{}) ", number, count)
+}
+
+/// Build a lookup table flagging the given ASCII characters
+const fn ascii_table(chars: &[char]) -> [bool; 128] {
+    let mut table = [false; 128];
+    let mut i = 0;
+    while i < chars.len() {
+        table[chars[i] as usize] = true;
+        i += 1;
+    }
+    table
+}
+
+/// Whether a byte is flagged in an ASCII lookup table (non-ASCII never is)
+#[inline]
+fn is_special(table: &[bool; 128], b: u8) -> bool {
+    b < 128 && table[b as usize]
+}
+
+/// An HTML tag found by [`parse_html_tag`]
+struct HtmlTag {
+    /// Lowercased tag name
+    name: String,
+    /// Lowercased attribute names with their unquoted values
+    attributes: Vec<(String, Option<String>)>,
+    /// Whether this is a closing tag (`</b>`)
+    closing: bool,
+    /// Length of the tag in the source, including the angle brackets
+    len: usize,
+}
+
+/// Parse the tag at the start of `text`, or `None` if `<` doesn't open one
+fn parse_html_tag(text: &str) -> Option<HtmlTag> {
+    let end = text.find('>')?;
+    let inner = &text[1..end];
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner),
+    };
+    let inner = inner.trim_end_matches('/').trim_end();
+
+    let name_end = inner
+        .find(|c: char| c.is_ascii_whitespace())
+        .unwrap_or(inner.len());
+    let name = &inner[..name_end];
+    if name.is_empty()
+        || !name.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return None;
+    }
+
+    let mut attributes = Vec::new();
+    let mut rest = inner[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let close = after[1..].find(quote)? + 1;
+                        (&after[1..close], &after[close + 1..])
+                    }
+                    _ => {
+                        let value_end = after
+                            .find(|c: char| c.is_ascii_whitespace())
+                            .unwrap_or(after.len());
+                        (&after[..value_end], &after[value_end..])
+                    }
+                };
+                rest = remaining.trim_start();
+                Some(value.to_string())
+            }
+            None => None,
+        };
+        attributes.push((key, value));
+    }
 
-        result
+    Some(HtmlTag {
+        name: name.to_ascii_lowercase(),
+        attributes,
+        closing,
+        len: end + 1,
+    })
+}
+
+/// Rebuild a tag Telegram supports with only its allowed attributes
+///
+/// Returns `None` for tags that must be removed.
+fn sanitize_html_tag(tag: &HtmlTag) -> Option<String> {
+    let attribute = |name: &str| {
+        tag.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_deref().unwrap_or(""))
+    };
+
+    let name = tag.name.as_str();
+    let allowed = match name {
+        "b" | "strong" | "i" | "em" | "u" | "ins" | "s" | "strike" | "del" | "tg-spoiler"
+        | "pre" | "blockquote" | "a" | "code" | "tg-emoji" => true,
+        "span" => tag.closing || attribute("class") == Some("tg-spoiler"),
+        _ => false,
+    };
+    if !allowed {
+        return None;
     }
+    if tag.closing {
+        return Some(format!("</{}>", name));
+    }
+
+    let kept = match name {
+        "a" => attribute("href")
+            .map(|href| format!(" href=\"{}\"", Formatter::escape_html_attribute(href))),
+        "tg-emoji" => attribute("emoji-id")
+            .map(|id| format!(" emoji-id=\"{}\"", Formatter::escape_html_attribute(id))),
+        "span" => Some(" class=\"tg-spoiler\"".to_string()),
+        "code" => attribute("class")
+            .filter(|class| class.starts_with("language-"))
+            .map(|class| format!(" class=\"{}\"", Formatter::escape_html_attribute(class))),
+        "blockquote" => attribute("expandable").map(|_| " expandable".to_string()),
+        _ => None,
+    };
 
-    /// Truncate text to a maximum length
-    pub fn truncate(text: &str, max_length: usize) -> String {
-        if text.len() <= max_length {
-            return text.to_string();
+    Some(format!("<{}{}>", name, kept.unwrap_or_default()))
+}
+
+/// Length of `text` in UTF-16 code units, the unit of [`Formatter::text_length`] and Telegram's limits
+pub(crate) fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// Byte offset where the longest prefix of `text` with at most `units` UTF-16 code units ends
+fn utf16_prefix_end(text: &str, units: usize) -> usize {
+    let mut length = 0;
+    for (i, c) in text.char_indices() {
+        length += c.len_utf16();
+        if length > units {
+            return i;
         }
+    }
+    text.len()
+}
 
-        // Try to truncate at a space to avoid cutting words
-        if let Some(last_space) = text[..max_length].rfind(' ') {
-            let result = format!("{}...", &text[..last_space]);
-            result
+/// Byte offset where the longest suffix of `text` with at most `units` UTF-16 code units starts
+fn utf16_suffix_start(text: &str, units: usize) -> usize {
+    let mut length = 0;
+    for (i, c) in text.char_indices().rev() {
+        length += c.len_utf16();
+        if length > units {
+            return i + c.len_utf8();
+        }
+    }
+    0
+}
+
+/// End of the head of `text` that fits in `budget` UTF-16 code units when truncating
+///
+/// Cuts at the last space, else between characters, and moves back out of escape
+/// sequences, HTML tags and entities.
+fn head_end(text: &str, budget: usize) -> usize {
+    let end = utf16_prefix_end(text, budget);
+    retreat_from_markup(text, text[..end].rfind(' ').unwrap_or(end))
+}
+
+/// Start of the tail of `text` that fits in `budget` UTF-16 code units when truncating
+///
+/// Prefers a line start in the first half of the tail, then a word start. The cut moves
+/// forward out of escape sequences, HTML tags and entities.
+fn tail_start(text: &str, budget: usize) -> usize {
+    let start = utf16_suffix_start(text, budget);
+    if start == 0 || text[..start].ends_with('\n') {
+        return start;
+    }
+    let tail = &text[start..];
+    // A line break late in the tail would leave little of it
+    let cut = tail
+        .find('\n')
+        .filter(|&newline| utf16_len(&tail[..newline]) < utf16_len(tail) / 2)
+        .or_else(|| tail.find(' ').filter(|&space| space + 1 < tail.len()))
+        .map_or(start, |at| start + at + 1);
+    advance_past_markup(text, cut)
+}
+
+/// Move a cut at byte `at` of formatted `text` back to the start of the HTML tag or
+/// entity it falls in, and off a trailing backslash escaping the next character
+fn retreat_from_markup(text: &str, mut at: usize) -> usize {
+    if let Some(open) = unfinished_markup(&text[..at]) {
+        at = open;
+    }
+    let backslashes = text[..at].bytes().rev().take_while(|&b| b == b'\\').count();
+    if backslashes % 2 == 1 {
+        at -= 1;
+    }
+    at
+}
+
+/// Move a cut at byte `at` of formatted `text` forward past the end of the HTML tag or
+/// entity it falls in, and past a character escaped by a backslash before the cut
+fn advance_past_markup(text: &str, mut at: usize) -> usize {
+    if let Some(open) = unfinished_markup(&text[..at]) {
+        let close = if text[open..].starts_with('<') {
+            '>'
         } else {
-            let result = format!("{}...", &text[..max_length - 3]);
-            result
+            ';'
+        };
+        if let Some(end) = text[at..].find(close) {
+            at += end + 1;
         }
     }
+    let backslashes = text[..at].bytes().rev().take_while(|&b| b == b'\\').count();
+    if backslashes % 2 == 1 {
+        at += text[at..].chars().next().map_or(0, char::len_utf8);
+    }
+    at
+}
+
+/// Byte offset of an HTML tag or entity left unfinished at the end of `head`
+///
+/// A `<` counts as a tag only if a letter or `/` follows, and a `&` as an entity only if
+/// a few letters, digits or `#` follow, so that literal `<` and `&` in MarkdownV2 or plain
+/// text don't pull a cut back.
+fn unfinished_markup(head: &str) -> Option<usize> {
+    let tag = head.rfind('<').filter(|&open| {
+        let inner = &head[open + 1..];
+        !inner.contains('>')
+            && (inner.is_empty()
+                || inner.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/'))
+    });
+    tag.or_else(|| {
+        head.rfind('&').filter(|&open| {
+            let inner = &head[open + 1..];
+            inner.len() < 10 && inner.chars().all(|c| c.is_ascii_alphanumeric() || c == '#')
+        })
+    })
+}
+
+/// The truncation suffix of `options`, escaped like the text it ends
+fn truncation_suffix(options: &FormattingOptions) -> Cow<'_, str> {
+    let suffix = options.truncation_suffix.as_str();
+    if options.escape_markdown {
+        Formatter::escape_markdown_text(suffix)
+    } else if options.escape_html || options.sanitize_html {
+        Formatter::escape_html(suffix)
+    } else {
+        Cow::Borrowed(suffix)
+    }
+}
+
+/// Collect the entities of a MarkdownV2 tree, outer ones first
+fn flatten_entities(nodes: Vec<markdown::Node>, entities: &mut Vec<PreviewEntity>) {
+    for node in nodes {
+        if let markdown::Node::Entity {
+            kind,
+            range,
+            children,
+        } = node
+        {
+            entities.push(PreviewEntity { kind, range });
+            flatten_entities(children, entities);
+        }
+    }
+}
+
+/// Entities of Telegram HTML, outer ones first
+///
+/// Unknown tags and closing tags without an opening one are skipped.
+fn html_entities(text: &str) -> Vec<PreviewEntity> {
+    let mut entities: Vec<PreviewEntity> = Vec::new();
+    // Open tags with the index of their entity
+    let mut open: Vec<(String, usize)> = Vec::new();
+
+    let mut i = 0;
+    while let Some(offset) = text[i..].find('<') {
+        let start = i + offset;
+        let Some(tag) = parse_html_tag(&text[start..]) else {
+            i = start + 1;
+            continue;
+        };
+        i = start + tag.len;
+
+        if tag.closing {
+            if let Some(at) = open.iter().rposition(|(name, _)| *name == tag.name) {
+                entities[open[at].1].range.end = i;
+                open.truncate(at);
+            }
+            continue;
+        }
+
+        let attribute = |name: &str| {
+            tag.attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone().unwrap_or_default())
+        };
+        let kind = match tag.name.as_str() {
+            "b" | "strong" => EntityKind::Bold,
+            "i" | "em" => EntityKind::Italic,
+            "u" | "ins" => EntityKind::Underline,
+            "s" | "strike" | "del" => EntityKind::Strikethrough,
+            "tg-spoiler" => EntityKind::Spoiler,
+            "span" if attribute("class").as_deref() == Some("tg-spoiler") => EntityKind::Spoiler,
+            "code" => EntityKind::Code,
+            "pre" => EntityKind::Pre { language: None },
+            "blockquote" => EntityKind::Blockquote,
+            "a" => EntityKind::TextLink {
+                url: attribute("href").unwrap_or_default(),
+            },
+            "tg-emoji" => EntityKind::CustomEmoji {
+                url: format!(
+                    "tg://emoji?id={}",
+                    attribute("emoji-id").unwrap_or_default()
+                ),
+            },
+            _ => continue,
+        };
+
+        // `<pre><code class="language-rust">` names the language of the block
+        if let (EntityKind::Code, Some(language)) = (
+            &kind,
+            attribute("class").and_then(|class| class.strip_prefix("language-").map(String::from)),
+        ) {
+            if let Some((_, index)) = open.last().filter(|(name, _)| name == "pre") {
+                entities[*index].kind = EntityKind::Pre {
+                    language: Some(language),
+                };
+            }
+        }
+
+        open.push((tag.name, entities.len()));
+        entities.push(PreviewEntity {
+            kind,
+            range: start..text.len(),
+        });
+    }
+    entities
+}
+
+/// Prefix every character of `text` matching `special` with a backslash, borrowing it if none does
+fn escape_chars(text: &str, special: impl Fn(char) -> bool) -> Cow<'_, str> {
+    if !text.contains(&special) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if special(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+/// Length of the HTML entity (`&amp;`, `&#39;`, `&#x27;`) at the start of `text`, or 0
+fn html_entity_len(text: &str) -> usize {
+    let Some(end) = text.find(';') else {
+        return 0;
+    };
+    let body = &text[1..end];
+    let valid = match body.strip_prefix('#') {
+        Some(number) => match number.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => matches!(body, "lt" | "gt" | "amp" | "quot"),
+    };
+    if valid {
+        end + 1
+    } else {
+        0
+    }
+}
+
+/// Run a formatting pass over possibly-borrowed text, keeping the original
+/// buffer when the pass doesn't change anything
+fn apply_pass<'a, F>(text: Cow<'a, str>, pass: F) -> Cow<'a, str>
+where
+    F: for<'b> FnOnce(&'b str) -> Cow<'b, str>,
+{
+    match text {
+        Cow::Borrowed(text) => pass(text),
+        Cow::Owned(text) => {
+            let changed = match pass(&text) {
+                Cow::Owned(changed) => Some(changed),
+                Cow::Borrowed(_) => None,
+            };
+            Cow::Owned(changed.unwrap_or(text))
+        }
+    }
+}
+
+/// Human-readable duration, e.g. `850ms`, `12.3s` or `1h 2m 5s`
+pub(crate) fn format_duration(duration: std::time::Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0 => format!("{}ms", duration.as_millis()),
+        1..=59 => format!("{:.1}s", duration.as_secs_f64()),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!(
+            "{}h {}m {}s",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        ),
+    }
 }
 
 /// Options for message formatting
@@ -339,6 +1865,35 @@ pub struct FormattingOptions {
     pub obfuscate_emails: bool,
     /// Whether to escape HTML special characters
     pub escape_html: bool,
-    /// Maximum message length (Telegram limit is 4096)
+    /// Whether to sanitize HTML to Telegram's supported tags instead of escaping it
+    pub sanitize_html: bool,
+    /// Maximum message length (Telegram limit is [`MAX_MESSAGE_LENGTH`])
     pub truncate: Option<usize>,
+    /// Text marking where truncated messages were cut, escaped for the parse mode and counted towards `truncate`
+    pub truncation_suffix: String,
+    /// Which part of a message truncation keeps
+    pub truncate_mode: TruncateMode,
+    /// Number of trailing lines to keep before `truncate` applies, see [`Formatter::last_lines`]
+    pub truncate_lines: Option<usize>,
+    /// Whether to mask credentials like API keys and tokens, see [`Formatter::scrub_secrets`]
+    pub scrub_secrets: bool,
+    /// Allow/deny lists and rewrites for the URLs of a message
+    pub url_filter: Option<UrlFilter>,
+}
+
+impl From<&crate::configuration::FormattingOptions> for FormattingOptions {
+    fn from(options: &crate::configuration::FormattingOptions) -> Self {
+        FormattingOptions {
+            escape_markdown: options.escape_markdown,
+            obfuscate_emails: options.obfuscate_emails,
+            escape_html: options.escape_html,
+            sanitize_html: options.sanitize_html,
+            truncate: options.truncate,
+            truncation_suffix: options.truncation_suffix.clone(),
+            truncate_mode: options.truncate_mode,
+            truncate_lines: options.truncate_lines,
+            scrub_secrets: options.scrub_secrets,
+            url_filter: options.url_filter.clone(),
+        }
+    }
 }
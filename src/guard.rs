@@ -0,0 +1,96 @@
+//! Reporting scopes that panic or exit without completing.
+//!
+//! [`Telegrama::watch`](crate::Telegrama::watch) returns a [`ScopeGuard`]. If
+//! the guard is dropped during a panic, or before [`ScopeGuard::complete`] was
+//! called (e.g. an early return through `?`), a message with the scope name and
+//! the elapsed time is sent to the configured chat:
+//!
+//! ```no_run
+//! use telegrama_rs::Telegrama;
+//!
+//! fn nightly_import() -> Result<(), std::io::Error> {
+//!     let guard = Telegrama::watch("nightly-import");
+//!     let rows = std::fs::read_to_string("import.csv")?;
+//!     // ... import the rows ...
+//!     guard.complete();
+//!     Ok(())
+//! }
+//! ```
+
+use std::time::Instant;
+use tracing::warn;
+
+use crate::client::Client;
+use crate::formatter::format_duration;
+
+/// Guard sending a failure message unless its scope completes
+pub struct ScopeGuard {
+    name: String,
+    started: Instant,
+    client: Option<Client>,
+    completed: bool,
+}
+
+impl ScopeGuard {
+    /// Watch the scope `name`, sending with a new [`Client`] if it fails
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        ScopeGuard {
+            name: name.into(),
+            started: Instant::now(),
+            client: None,
+            completed: false,
+        }
+    }
+
+    /// Send the failure message with `client`
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Name of the watched scope
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Mark the scope as completed, so dropping the guard sends nothing
+    pub fn complete(mut self) {
+        self.completed = true;
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let reason = if std::thread::panicking() {
+            "panicked"
+        } else if !self.completed {
+            "exited without completing"
+        } else {
+            return;
+        };
+        let text = format!(
+            "💥 {} {} after {}",
+            self.name,
+            reason,
+            format_duration(self.started.elapsed())
+        );
+
+        let result = match &self.client {
+            Some(client) => client.send_message(&text, &[]),
+            None => Client::new().send_message(&text, &[]),
+        };
+        if let Err(e) = result {
+            warn!(scope = %self.name, error = %e, "Failed to report failed scope");
+        }
+    }
+}
+
+impl std::fmt::Debug for ScopeGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopeGuard")
+            .field("name", &self.name)
+            .field("started", &self.started)
+            .field("completed", &self.completed)
+            .finish_non_exhaustive()
+    }
+}
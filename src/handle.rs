@@ -0,0 +1,118 @@
+//! Handles to sent messages, for follow-up edits, deletes, pins and replies.
+
+use crate::client::{Client, Response};
+use crate::error::Error;
+
+/// A message sent by the bot, returned by [`Client::send_message_with_handle`]
+///
+/// Follow-up calls target the same chat and bot the message was sent with and
+/// format texts like [`Client::send_message`].
+#[derive(Clone)]
+pub struct MessageHandle<'a> {
+    client: &'a Client,
+    chat_id: String,
+    message_id: i64,
+    bot: Option<String>,
+}
+
+impl<'a> MessageHandle<'a> {
+    /// Handle to an existing message of `chat_id`, e.g. one stored across restarts
+    pub fn new<S: Into<String>>(client: &'a Client, chat_id: S, message_id: i64) -> Self {
+        MessageHandle {
+            client,
+            chat_id: chat_id.into(),
+            message_id,
+            bot: None,
+        }
+    }
+
+    /// Use the named bot profile of the configuration for follow-up calls
+    pub fn with_bot<S: Into<String>>(mut self, bot: S) -> Self {
+        self.bot = Some(bot.into());
+        self
+    }
+
+    /// Handle to the message created by a send request
+    pub(crate) fn from_response(
+        client: &'a Client,
+        response: &Response,
+        bot: Option<String>,
+    ) -> Result<Self, Error> {
+        let result = response.result.as_ref();
+        let message_id = result
+            .and_then(|message| message.get("message_id"))
+            .and_then(serde_json::Value::as_i64)
+            .ok_or_else(|| Error::api("Send response without a message ID"))?;
+        let chat_id = match result
+            .and_then(|message| message.get("chat"))
+            .and_then(|chat| chat.get("id"))
+        {
+            Some(serde_json::Value::Number(id)) => id.to_string(),
+            Some(serde_json::Value::String(id)) => id.clone(),
+            _ => return Err(Error::api("Send response without a chat ID")),
+        };
+
+        Ok(MessageHandle {
+            client,
+            chat_id,
+            message_id,
+            bot,
+        })
+    }
+
+    /// Chat the message was sent to
+    pub fn chat_id(&self) -> &str {
+        &self.chat_id
+    }
+
+    /// Identifier of the message in its chat
+    pub fn message_id(&self) -> i64 {
+        self.message_id
+    }
+
+    /// Replace the text of the message
+    pub fn edit(&self, text: &str) -> Result<Response, Error> {
+        self.client
+            .edit_message_text(self.message_id, text, &self.options())
+    }
+
+    /// Delete the message
+    pub fn delete(&self) -> Result<Response, Error> {
+        self.client.delete_message(self.message_id, &self.options())
+    }
+
+    /// Pin the message without notifying the chat members
+    pub fn pin(&self) -> Result<Response, Error> {
+        self.client
+            .pin_chat_message(self.message_id, true, &self.options())
+    }
+
+    /// Send `text` as a reply to the message, returning a handle to the reply
+    pub fn reply(&self, text: &str) -> Result<MessageHandle<'a>, Error> {
+        let message_id = self.message_id.to_string();
+        let mut options = self.options();
+        options.push(("reply_to_message_id", &message_id));
+
+        let response = self.client.send_message(text, &options)?;
+        MessageHandle::from_response(self.client, &response, self.bot.clone())
+    }
+
+    /// Options targeting the message's chat and bot
+    fn options(&self) -> Vec<(&str, &str)> {
+        let mut options = vec![("chat_id", self.chat_id.as_str())];
+        if let Some(bot) = &self.bot {
+            options.push(("bot", bot.as_str()));
+        }
+        options
+    }
+}
+
+impl std::fmt::Debug for MessageHandle<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessageHandle")
+            .field("chat_id", &self.chat_id)
+            .field("message_id", &self.message_id)
+            .field("bot", &self.bot)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,248 @@
+//! Periodic "still alive" messages, so a silently dead process gets noticed.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use telegrama_rs::{Client, Heartbeat, HeartbeatOptions};
+//!
+//! let heartbeat = Heartbeat::start(
+//!     Client::new(),
+//!     HeartbeatOptions {
+//!         interval: Duration::from_secs(600),
+//!         service_name: Some("billing-worker".to_string()),
+//!         ..Default::default()
+//!     },
+//! )?;
+//!
+//! heartbeat.set_field("Queue", "12 jobs");
+//! heartbeat.record_event("Processed invoice #42");
+//! # Ok::<(), telegrama_rs::Error>(())
+//! ```
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::client::{Client, Response};
+use crate::error::Error;
+use crate::formatter::format_duration;
+
+/// Default interval between heartbeats
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Settings of a [`Heartbeat`]
+#[derive(Debug, Clone)]
+pub struct HeartbeatOptions {
+    /// Time between two heartbeats
+    pub interval: Duration,
+    /// Name shown in the message, `"Service"` if not set
+    pub service_name: Option<String>,
+    /// Edit the first heartbeat message instead of sending a new one each time
+    pub edit_in_place: bool,
+    /// Chat ID, @username or alias, instead of the configured chat
+    pub chat_id: Option<String>,
+    /// Named bot profile of the configuration to send with
+    pub bot: Option<String>,
+    /// Deliver heartbeats without a notification sound
+    pub silent: bool,
+}
+
+impl Default for HeartbeatOptions {
+    fn default() -> Self {
+        HeartbeatOptions {
+            interval: DEFAULT_INTERVAL,
+            service_name: None,
+            edit_in_place: true,
+            chat_id: None,
+            bot: None,
+            silent: true,
+        }
+    }
+}
+
+/// Background thread reporting that the process is alive
+///
+/// Each heartbeat shows the uptime, the last recorded event and any custom
+/// status fields. With [`HeartbeatOptions::edit_in_place`] the same message
+/// is updated, so a stale timestamp in chat means the process stopped. The
+/// heartbeat stops when dropped.
+pub struct Heartbeat {
+    inner: Arc<Inner>,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+struct Inner {
+    client: Client,
+    options: HeartbeatOptions,
+    started: Instant,
+    status: Mutex<Status>,
+    message_id: Mutex<Option<i64>>,
+}
+
+#[derive(Default)]
+struct Status {
+    fields: Vec<(String, String)>,
+    last_event: Option<(String, Instant)>,
+}
+
+impl Heartbeat {
+    /// Send the first heartbeat and keep sending them in the background
+    ///
+    /// Fails if the first heartbeat can't be sent, e.g. because of a missing
+    /// bot token; later failures are logged and retried on the next interval.
+    pub fn start(client: Client, options: HeartbeatOptions) -> Result<Self, Error> {
+        let interval = options.interval;
+        let inner = Arc::new(Inner {
+            client,
+            options,
+            started: Instant::now(),
+            status: Mutex::new(Status::default()),
+            message_id: Mutex::new(None),
+        });
+
+        // Fail early if the chat can't be reached
+        inner.beat()?;
+
+        let (stop, stop_rx) = mpsc::channel();
+        let beating = Arc::clone(&inner);
+        let handle = std::thread::Builder::new()
+            .name("telegrama-heartbeat".to_string())
+            .spawn(move || {
+                // Runs until a stop is requested or the heartbeat is dropped
+                while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                    if let Err(e) = beating.beat() {
+                        warn!(error = %e, "Failed to send heartbeat");
+                    }
+                }
+            })
+            .map_err(|e| Error::other(format!("Failed to start heartbeat: {}", e)))?;
+
+        Ok(Heartbeat {
+            inner,
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// Show a custom status field, replacing an earlier value of the same name
+    pub fn set_field<N: Into<String>, V: Into<String>>(&self, name: N, value: V) {
+        let (name, value) = (name.into(), value.into());
+        let mut status = self.inner.status();
+        match status.fields.iter_mut().find(|(n, _)| *n == name) {
+            Some(field) => field.1 = value,
+            None => status.fields.push((name, value)),
+        }
+    }
+
+    /// Stop showing a custom status field
+    pub fn remove_field(&self, name: &str) {
+        self.inner.status().fields.retain(|(n, _)| n != name);
+    }
+
+    /// Record the latest notable event, shown with how long ago it happened
+    pub fn record_event<S: Into<String>>(&self, description: S) {
+        self.inner.status().last_event = Some((description.into(), Instant::now()));
+    }
+
+    /// Send a heartbeat right away, without waiting for the interval
+    pub fn beat(&self) -> Result<(), Error> {
+        self.inner.beat()
+    }
+
+    /// Time since the heartbeat was started
+    pub fn uptime(&self) -> Duration {
+        self.inner.started.elapsed()
+    }
+}
+
+impl Inner {
+    fn status(&self) -> std::sync::MutexGuard<'_, Status> {
+        self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Text of the next heartbeat
+    fn message(&self) -> String {
+        let service = self.options.service_name.as_deref().unwrap_or("Service");
+        let mut message = format!(
+            "✅ {} alive\nUptime: {}",
+            service,
+            format_duration(self.started.elapsed())
+        );
+
+        let status = self.status();
+        match &status.last_event {
+            Some((event, at)) => message.push_str(&format!(
+                "\nLast event: {} ({} ago)",
+                event,
+                format_duration(at.elapsed())
+            )),
+            None => message.push_str("\nLast event: none"),
+        }
+        for (name, value) in &status.fields {
+            message.push_str(&format!("\n{}: {}", name, value));
+        }
+        message
+    }
+
+    fn beat(&self) -> Result<(), Error> {
+        let text = self.message();
+        let mut options: Vec<(&str, &str)> = Vec::new();
+        if let Some(chat_id) = &self.options.chat_id {
+            options.push(("chat_id", chat_id));
+        }
+        if let Some(bot) = &self.options.bot {
+            options.push(("bot", bot));
+        }
+
+        // Only one heartbeat at a time, so an edit never races a new message
+        let mut message_id = self
+            .message_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if self.options.edit_in_place {
+            if let Some(id) = *message_id {
+                match self.client.edit_message_text(id, &text, &options) {
+                    Ok(_) => return Ok(()),
+                    // Nothing changed since the last heartbeat
+                    Err(Error::Api(description))
+                        if description.contains("message is not modified") =>
+                    {
+                        return Ok(())
+                    }
+                    Err(e) => {
+                        debug!(error = %e, "Could not edit heartbeat, sending a new one");
+                    }
+                }
+            }
+        }
+
+        if self.options.silent {
+            options.push(("disable_notification", "true"));
+        }
+        let response = self.client.send_message(&text, &options)?;
+        *message_id = sent_message_id(&response);
+        Ok(())
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread up immediately
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// ID of the message created by a send request
+fn sent_message_id(response: &Response) -> Option<i64> {
+    response
+        .result
+        .as_ref()
+        .and_then(|result| result.get("message_id"))
+        .and_then(serde_json::Value::as_i64)
+}
@@ -0,0 +1,96 @@
+//! Recent send failures kept in memory, so the bot can tell why messages stopped arriving.
+//!
+//! Every [`Client`](crate::Client) remembers its last failed sends; read them with
+//! [`Client::recent_errors`](crate::Client::recent_errors), e.g. from a `/errors`
+//! command handler.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+use crate::error::Error;
+use crate::time;
+
+/// Number of failures a client keeps by default
+pub const DEFAULT_CAPACITY: usize = 20;
+
+/// Characters of the message text kept in [`FailedSend::preview`]
+const PREVIEW_LENGTH: usize = 100;
+
+/// A message that could not be delivered
+#[derive(Debug, Clone)]
+pub struct FailedSend {
+    /// When the send gave up
+    pub failed_at: SystemTime,
+    /// Target chat
+    pub chat_id: String,
+    /// Error kind (see [`Error::kind`])
+    pub kind: &'static str,
+    /// Error message
+    pub error: String,
+    /// Beginning of the unformatted message text
+    pub preview: String,
+}
+
+impl fmt::Display for FailedSend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} ({:?})",
+            DateTime::<Utc>::from(self.failed_at).format("%Y-%m-%d %H:%M:%S UTC"),
+            self.chat_id,
+            self.error,
+            self.preview
+        )
+    }
+}
+
+/// Bounded buffer of the last failures of a client
+#[derive(Debug)]
+pub(crate) struct ErrorHistory {
+    capacity: usize,
+    entries: Mutex<VecDeque<FailedSend>>,
+}
+
+impl ErrorHistory {
+    /// Keep the last `capacity` failures (zero disables the history)
+    pub(crate) fn new(capacity: usize) -> Self {
+        ErrorHistory {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Record a failed send of `message` to `chat_id`
+    pub(crate) fn record(&self, chat_id: &str, message: &str, error: &Error) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut preview: String = message.chars().take(PREVIEW_LENGTH).collect();
+        if preview.len() < message.len() {
+            preview.push('…');
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(FailedSend {
+            failed_at: time::system_now(),
+            chat_id: chat_id.to_string(),
+            kind: error.kind(),
+            error: error.to_string(),
+            preview,
+        });
+    }
+
+    /// Recorded failures, oldest first
+    pub(crate) fn entries(&self) -> Vec<FailedSend> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.iter().cloned().collect()
+    }
+}
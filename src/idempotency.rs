@@ -0,0 +1,156 @@
+//! Process-wide store of recently sent messages used to suppress duplicates.
+//!
+//! When a send fails ambiguously (e.g. a timeout after Telegram may already have
+//! delivered the message), retrying it can post the message twice. With
+//! `ClientOptions::idempotency_window` set, every send claims a key (an explicit
+//! `idempotency_key` send option, or the [`fingerprint`] of its text) scoped to
+//! the chat, and sends that reuse a claimed key within the window fail with
+//! [`Error::Duplicate`](crate::Error::Duplicate).
+//!
+//! With [`DuplicateStrategy::Coalesce`](crate::DuplicateStrategy::Coalesce),
+//! repeats edit the original message instead, appending a counter such as `(×3)`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::configuration::ParseMode;
+use crate::time::Instant;
+
+/// Window used for explicit `idempotency_key` options when none is configured
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(600);
+
+/// A claimed key
+struct Claim {
+    expires_at: Instant,
+    /// Delivered message that repeats are coalesced into
+    original: Option<Original>,
+}
+
+/// A delivered message and how often it was sent within the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Original {
+    pub(crate) message_id: i64,
+    /// Formatted text as sent, without counter
+    pub(crate) text: String,
+    pub(crate) parse_mode: ParseMode,
+    /// Sends of the message so far, the original included
+    pub(crate) count: u32,
+}
+
+impl Original {
+    /// Text of the original followed by its counter, e.g. `Disk full (×3)`
+    pub(crate) fn counted_text(&self) -> String {
+        // Parentheses are reserved characters in MarkdownV2
+        match self.parse_mode {
+            ParseMode::MarkdownV2 => format!("{} \\(×{}\\)", self.text, self.count),
+            _ => format!("{} (×{})", self.text, self.count),
+        }
+    }
+}
+
+/// Claimed keys and when they expire
+static CLAIMED: Mutex<Option<HashMap<u64, Claim>>> = Mutex::new(None);
+
+/// Key of an explicit `idempotency_key` send option, scoped to the chat
+pub(crate) fn explicit_key(chat_id: &str, key: &str) -> u64 {
+    hash(&("key", chat_id, key))
+}
+
+/// Key derived from the content of a message, same as an explicit key of its [`fingerprint`]
+pub(crate) fn content_key(chat_id: &str, text: &str) -> u64 {
+    explicit_key(chat_id, &fingerprint(text))
+}
+
+/// Content hash deciding which messages are duplicates without an `idempotency_key`
+///
+/// Texts that only differ in whitespace have the same fingerprint. The result
+/// (16 hex digits of the 64-bit FNV-1a hash of the text with whitespace runs
+/// collapsed to single spaces) is stable across processes and versions, so it
+/// can be stored, e.g. in an outbox table. Passing it as `idempotency_key`
+/// suppresses the same duplicates as content-based suppression.
+///
+/// ```
+/// use telegrama_rs::idempotency::fingerprint;
+///
+/// assert_eq!(fingerprint("Backup  done\n"), fingerprint("Backup done"));
+/// assert_ne!(fingerprint("Backup done"), fingerprint("Backup failed"));
+/// ```
+pub fn fingerprint(text: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    for (i, word) in text.split_whitespace().enumerate() {
+        let separator: &[u8] = if i == 0 { b"" } else { b" " };
+        for byte in separator.iter().chain(word.as_bytes()) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Claim `key` for `window`; returns `false` if it is already claimed
+pub(crate) fn claim(key: u64, window: Duration) -> bool {
+    let now = Instant::now();
+    let mut claimed = CLAIMED.lock().unwrap_or_else(PoisonError::into_inner);
+    let claimed = claimed.get_or_insert_with(HashMap::new);
+
+    claimed.retain(|_, claim| claim.expires_at > now);
+    if claimed.contains_key(&key) {
+        return false;
+    }
+    claimed.insert(
+        key,
+        Claim {
+            expires_at: now + window,
+            original: None,
+        },
+    );
+    true
+}
+
+/// Remember the message delivered for a claimed `key`, so repeats can be coalesced into it
+pub(crate) fn remember(key: u64, message_id: i64, text: String, parse_mode: ParseMode) {
+    let mut claimed = CLAIMED.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(claim) = claimed.as_mut().and_then(|claimed| claimed.get_mut(&key)) {
+        claim.original = Some(Original {
+            message_id,
+            text,
+            parse_mode,
+            count: 1,
+        });
+    }
+}
+
+/// Count a repeat of the message delivered for `key`, returning it with the new count
+///
+/// `None` if the key expired or its message is unknown, e.g. still being sent.
+pub(crate) fn repeat(key: u64) -> Option<Original> {
+    let now = Instant::now();
+    let mut claimed = CLAIMED.lock().unwrap_or_else(PoisonError::into_inner);
+    let claim = claimed.as_mut()?.get_mut(&key)?;
+    if claim.expires_at <= now {
+        return None;
+    }
+    let original = claim.original.as_mut()?;
+    original.count += 1;
+    Some(original.clone())
+}
+
+/// Release a key after a send that definitely did not deliver the message
+pub(crate) fn release(key: u64) {
+    let mut claimed = CLAIMED.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(claimed) = claimed.as_mut() {
+        claimed.remove(&key);
+    }
+}
+
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,153 @@
+//! Log events as JSON lines, for log pipelines that shouldn't parse free text.
+//!
+//! Enabled with the `json-logs` feature. [`JsonLayer`] is a
+//! [`tracing_subscriber`] layer writing one JSON object per event, with the
+//! event's fields next to those of its spans, e.g. for a send:
+//!
+//! ```json
+//! {"timestamp":"2026-10-16T09:12:44.120Z","level":"DEBUG","target":"telegrama_rs::client","span":"telegrama.request","chat_id":"-100123","parse_mode":"MarkdownV2","method":"sendMessage","attempt":0,"latency_ms":84,"status":200,"message":"Bot API request finished"}
+//! ```
+//!
+//! ```no_run
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(telegrama_rs::logging::JsonLayer::new())
+//!     .init();
+//! ```
+//!
+//! Message texts are left out unless
+//! [`Configuration::set_log_message_text`](crate::Configuration::set_log_message_text)
+//! is enabled.
+
+use std::fmt;
+use std::io::Write;
+use std::sync::{Mutex, PoisonError};
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// [`Layer`] writing every event as a line of JSON
+pub struct JsonLayer {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Default for JsonLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonLayer {
+    /// Write events to stderr
+    pub fn new() -> Self {
+        Self::with_writer(std::io::stderr())
+    }
+
+    /// Write events to `writer`, e.g. a file or a buffer in tests
+    pub fn with_writer<W: Write + Send + 'static>(writer: W) -> Self {
+        JsonLayer {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+}
+
+impl fmt::Debug for JsonLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLayer").finish_non_exhaustive()
+    }
+}
+
+/// Fields recorded on a span so far
+struct SpanFields(Map<String, Value>);
+
+impl<S> Layer<S> for JsonLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(&mut JsonVisitor(&mut fields.0));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut object = Map::new();
+        object.insert(
+            "timestamp".to_string(),
+            Utc::now()
+                .to_rfc3339_opts(SecondsFormat::Millis, true)
+                .into(),
+        );
+        object.insert("level".to_string(), metadata.level().to_string().into());
+        object.insert("target".to_string(), metadata.target().into());
+
+        // Outer spans first, so inner fields and then the event's own win
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                object.insert("span".to_string(), span.name().into());
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    object.extend(fields.0.clone());
+                }
+            }
+        }
+        event.record(&mut JsonVisitor(&mut object));
+
+        let mut line = Value::Object(object).to_string();
+        line.push('\n');
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        // Logging must never fail the application
+        let _ = writer.write_all(line.as_bytes());
+    }
+}
+
+/// Collects event and span fields as JSON values
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.0
+            .insert(field.name().to_string(), value.to_string().into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
@@ -0,0 +1,699 @@
+//! Parsing MarkdownV2 into a tree of entities.
+//!
+//! [`parse`] reads text the way Telegram does and returns its [`Node`]s: text
+//! with escapes resolved, and entities (bold, code, links, ...) with their
+//! content and byte range in the source. It fails where Telegram would, with
+//! the offset of the problem. [`parse_lenient`] never fails: markers that don't
+//! form an entity are read as text. [`render`] turns nodes back into
+//! MarkdownV2, escaping the text, so trees can be analyzed, transformed and
+//! sent again:
+//!
+//! ```
+//! use telegrama_rs::markdown::{self, EntityKind, Node};
+//!
+//! let nodes = markdown::parse("*Deploy* of `api` done\\!")?;
+//! assert!(matches!(
+//!     &nodes[0],
+//!     Node::Entity { kind: EntityKind::Bold, range, .. } if *range == (0..8)
+//! ));
+//! assert_eq!(markdown::plain_text(&nodes), "Deploy of api done!");
+//! assert_eq!(markdown::render(&nodes), "*Deploy* of `api` done\\!");
+//!
+//! // Lenient parsing reads stray markers as text
+//! let nodes = markdown::parse_lenient("2 * 3 = 6");
+//! assert_eq!(markdown::render(&nodes), "2 \\* 3 \\= 6");
+//! # Ok::<(), telegrama_rs::MarkdownV2Error>(())
+//! ```
+
+use std::ops::Range;
+
+use crate::formatter::{Formatter, MarkdownV2Error, MARKDOWN_SPECIAL_CHARS};
+
+/// Kind of a MarkdownV2 entity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntityKind {
+    /// `*bold*`
+    Bold,
+    /// `_italic_`
+    Italic,
+    /// `__underline__`
+    Underline,
+    /// `~strikethrough~`
+    Strikethrough,
+    /// `||spoiler||`
+    Spoiler,
+    /// `` `inline code` ``
+    Code,
+    /// Code block, with the language given after the opening fence
+    Pre {
+        /// Language of the block, e.g. `rust`
+        language: Option<String>,
+    },
+    /// `[text](url)`
+    TextLink {
+        /// Target of the link
+        url: String,
+    },
+    /// `![👍](tg://emoji?id=...)`
+    CustomEmoji {
+        /// `tg://emoji?id=...` URL of the emoji
+        url: String,
+    },
+    /// Lines starting with `>`
+    Blockquote,
+}
+
+/// Part of a parsed MarkdownV2 text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// Text as shown, with escapes resolved
+    Text {
+        /// The text
+        text: String,
+        /// Byte range in the source, including escape backslashes
+        range: Range<usize>,
+    },
+    /// An entity and its content
+    Entity {
+        /// What the entity is
+        kind: EntityKind,
+        /// Byte range in the source, including markers
+        range: Range<usize>,
+        /// Content; a single text node for code
+        children: Vec<Node>,
+    },
+}
+
+impl Node {
+    /// Byte range of the node in the parsed text
+    pub fn range(&self) -> &Range<usize> {
+        match self {
+            Node::Text { range, .. } | Node::Entity { range, .. } => range,
+        }
+    }
+}
+
+/// Parse MarkdownV2 like Telegram, failing on the first problem
+///
+/// Reports unescaped reserved characters, unbalanced or badly nested
+/// entities, unterminated code and malformed links with their offset.
+pub fn parse(text: &str) -> Result<Vec<Node>, MarkdownV2Error> {
+    Parser::new(text, false).run()
+}
+
+/// Parse MarkdownV2, reading markers that don't form an entity as text
+///
+/// Unclosed delimiters, brackets without a `(url)` and reserved characters
+/// become text, so [`render`] escapes them. A backslash only escapes a
+/// character that needs escaping, other backslashes are text. Block
+/// quotations aren't recognized.
+pub fn parse_lenient(text: &str) -> Vec<Node> {
+    Parser::new(text, true)
+        .run()
+        .expect("lenient parsing doesn't fail")
+}
+
+/// Render nodes as MarkdownV2, escaping text for its context
+pub fn render(nodes: &[Node]) -> String {
+    let mut rendered = String::new();
+    for node in nodes {
+        render_node(node, &mut rendered);
+    }
+    rendered
+}
+
+/// The text of nodes as shown, without formatting
+pub fn plain_text(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            Node::Text { text: part, .. } => text.push_str(part),
+            Node::Entity { children, .. } => text.push_str(&plain_text(children)),
+        }
+    }
+    text
+}
+
+fn render_node(node: &Node, rendered: &mut String) {
+    let (kind, children) = match node {
+        Node::Text { text, .. } => {
+            rendered.push_str(&Formatter::escape_markdown_text(text));
+            return;
+        }
+        Node::Entity { kind, children, .. } => (kind, children),
+    };
+
+    match kind {
+        EntityKind::Code | EntityKind::Pre { .. } => {
+            let code = Formatter::escape_markdown_code(&plain_text(children)).into_owned();
+            match kind {
+                EntityKind::Pre { language } => {
+                    rendered.push_str("```");
+                    rendered.push_str(language.as_deref().unwrap_or_default());
+                    rendered.push('\n');
+                    rendered.push_str(&code);
+                    rendered.push_str("```");
+                }
+                _ => {
+                    rendered.push('`');
+                    rendered.push_str(&code);
+                    rendered.push('`');
+                }
+            }
+        }
+        EntityKind::TextLink { url } | EntityKind::CustomEmoji { url } => {
+            let open = if matches!(kind, EntityKind::CustomEmoji { .. }) {
+                "!["
+            } else {
+                "["
+            };
+            rendered.push_str(open);
+            rendered.push_str(&render(children));
+            rendered.push_str("](");
+            rendered.push_str(&Formatter::escape_markdown_url(url));
+            rendered.push(')');
+        }
+        EntityKind::Blockquote => {
+            rendered.push('>');
+            rendered.push_str(&render(children).replace('\n', "\n>"));
+        }
+        _ => {
+            let marker = match kind {
+                EntityKind::Bold => Marker::Bold,
+                EntityKind::Italic => Marker::Italic,
+                EntityKind::Underline => Marker::Underline,
+                EntityKind::Strikethrough => Marker::Strikethrough,
+                _ => Marker::Spoiler,
+            }
+            .marker();
+            rendered.push_str(marker);
+            rendered.push_str(&render(children));
+            rendered.push_str(marker);
+        }
+    }
+}
+
+/// Entities that are opened by a marker and closed later
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Spoiler,
+    LinkText,
+    EmojiText,
+    Blockquote,
+}
+
+impl Marker {
+    fn name(self) -> &'static str {
+        match self {
+            Marker::Bold => "bold",
+            Marker::Italic => "italic",
+            Marker::Underline => "underline",
+            Marker::Strikethrough => "strikethrough",
+            Marker::Spoiler => "spoiler",
+            Marker::LinkText | Marker::EmojiText => "link text",
+            Marker::Blockquote => "blockquote",
+        }
+    }
+
+    /// Text opening the entity
+    fn marker(self) -> &'static str {
+        match self {
+            Marker::Bold => "*",
+            Marker::Italic => "_",
+            Marker::Underline => "__",
+            Marker::Strikethrough => "~",
+            Marker::Spoiler => "||",
+            Marker::LinkText => "[",
+            Marker::EmojiText => "![",
+            Marker::Blockquote => ">",
+        }
+    }
+
+    fn kind(self) -> EntityKind {
+        match self {
+            Marker::Bold => EntityKind::Bold,
+            Marker::Italic => EntityKind::Italic,
+            Marker::Underline => EntityKind::Underline,
+            Marker::Strikethrough => EntityKind::Strikethrough,
+            Marker::Spoiler => EntityKind::Spoiler,
+            Marker::Blockquote => EntityKind::Blockquote,
+            // Links get their kind once their URL is read
+            Marker::LinkText | Marker::EmojiText => unreachable!("links are closed by close_link"),
+        }
+    }
+}
+
+/// An entity being parsed
+struct Frame {
+    marker: Marker,
+    /// Byte offset of its opening marker
+    start: usize,
+    children: Vec<Node>,
+}
+
+/// Single-pass MarkdownV2 parser behind [`parse`] and [`parse_lenient`]
+struct Parser<'a> {
+    text: &'a str,
+    lenient: bool,
+    /// Byte offset of the next character to read
+    pos: usize,
+    /// Content of the text outside any entity
+    root: Vec<Node>,
+    /// Open entities, innermost last
+    open: Vec<Frame>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, lenient: bool) -> Self {
+        Parser {
+            text,
+            lenient,
+            pos: 0,
+            root: Vec::new(),
+            open: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Result<Vec<Node>, MarkdownV2Error> {
+        while let Some(c) = self.peek(0) {
+            let start = self.pos;
+            let rest = &self.text[start..];
+
+            match c {
+                '\\' => self.escape(start)?,
+                '`' if rest.starts_with("```") => self.code(start, "```")?,
+                '`' => self.code(start, "`")?,
+                '*' => self.toggle(Marker::Bold, start)?,
+                '_' if rest.starts_with("__") => self.toggle(Marker::Underline, start)?,
+                '_' => self.toggle(Marker::Italic, start)?,
+                '~' => self.toggle(Marker::Strikethrough, start)?,
+                '|' if rest.starts_with("||") => self.toggle(Marker::Spoiler, start)?,
+                '[' => self.open_frame(Marker::LinkText, start),
+                // Custom emoji: ![👍](tg://emoji?id=...)
+                '!' if rest.starts_with("![") => self.open_frame(Marker::EmojiText, start),
+                ']' => self.close_link(start)?,
+                // Block quotation at the start of a line
+                '>' if !self.lenient && (start == 0 || self.text[..start].ends_with('\n')) => {
+                    self.pos += 1;
+                    // Continued lines only repeat the marker
+                    if !self.is_open(Marker::Blockquote) {
+                        self.open_frame_at(Marker::Blockquote, start);
+                    }
+                }
+                '\n' => {
+                    if self.is_open(Marker::Blockquote) && self.peek(1) != Some('>') {
+                        self.close_blockquote(start)?;
+                    }
+                    self.pos += 1;
+                    self.push_text("\n", start..self.pos);
+                }
+                c if MARKDOWN_SPECIAL_CHARS.contains(&c) => {
+                    if !self.lenient {
+                        return Err(self.error(
+                            start,
+                            format!(
+                                "Character '{}' is reserved and must be escaped with '\\'",
+                                c
+                            ),
+                        ));
+                    }
+                    self.pos += c.len_utf8();
+                    self.push_text(&rest[..c.len_utf8()], start..self.pos);
+                }
+                _ => {
+                    // Copy a run of ordinary characters at once
+                    let len = rest
+                        .find(|c: char| {
+                            c == '\\' || c == '\n' || MARKDOWN_SPECIAL_CHARS.contains(&c)
+                        })
+                        .unwrap_or(rest.len());
+                    self.pos += len;
+                    self.push_text(&rest[..len], start..self.pos);
+                }
+            }
+        }
+
+        while let Some(frame) = self.open.pop() {
+            if frame.marker == Marker::Blockquote {
+                self.close_frame(frame, EntityKind::Blockquote);
+            } else if self.lenient {
+                self.unwind(frame);
+            } else {
+                return Err(self.error(
+                    frame.start,
+                    format!("{} entity is never closed", capitalize(frame.marker.name())),
+                ));
+            }
+        }
+        Ok(self.root)
+    }
+
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.text[self.pos..].chars().nth(ahead)
+    }
+
+    fn error(&self, byte: usize, message: String) -> MarkdownV2Error {
+        MarkdownV2Error {
+            byte_offset: byte,
+            char_offset: self.text[..byte].chars().count(),
+            message,
+        }
+    }
+
+    fn is_open(&self, marker: Marker) -> bool {
+        self.open.iter().any(|frame| frame.marker == marker)
+    }
+
+    /// Nodes of the innermost open entity, or of the text
+    fn children(&mut self) -> &mut Vec<Node> {
+        match self.open.last_mut() {
+            Some(frame) => &mut frame.children,
+            None => &mut self.root,
+        }
+    }
+
+    /// Add text, merging it with text right before it
+    fn push_text(&mut self, text: &str, range: Range<usize>) {
+        let children = self.children();
+        if let Some(Node::Text {
+            text: last,
+            range: last_range,
+        }) = children.last_mut()
+        {
+            if last_range.end == range.start {
+                last.push_str(text);
+                last_range.end = range.end;
+                return;
+            }
+        }
+        children.push(Node::Text {
+            text: text.to_string(),
+            range,
+        });
+    }
+
+    fn push_nodes(&mut self, nodes: Vec<Node>) {
+        for node in nodes {
+            match node {
+                Node::Text { text, range } => self.push_text(&text, range),
+                node => self.children().push(node),
+            }
+        }
+    }
+
+    fn open_frame(&mut self, marker: Marker, start: usize) {
+        self.pos += marker.marker().len();
+        self.open_frame_at(marker, start);
+    }
+
+    fn open_frame_at(&mut self, marker: Marker, start: usize) {
+        self.open.push(Frame {
+            marker,
+            start,
+            children: Vec::new(),
+        });
+    }
+
+    /// Add a closed entity ending at the current position to its parent
+    fn close_frame(&mut self, frame: Frame, kind: EntityKind) {
+        let range = frame.start..self.pos;
+        self.children().push(Node::Entity {
+            kind,
+            range,
+            children: frame.children,
+        });
+    }
+
+    /// Read an entity that never formed as text: its marker, then its content
+    fn unwind(&mut self, frame: Frame) {
+        let marker = frame.marker.marker();
+        self.push_text(marker, frame.start..frame.start + marker.len());
+        self.push_nodes(frame.children);
+    }
+
+    /// Unwind the entities opened inside the innermost `marker`, leniently
+    fn unwind_to(&mut self, marker: Marker) {
+        while self.open.last().is_some_and(|frame| frame.marker != marker) {
+            let frame = self.open.pop().expect("an entity is open");
+            self.unwind(frame);
+        }
+    }
+
+    /// Read the character after a backslash
+    fn escape(&mut self, start: usize) -> Result<(), MarkdownV2Error> {
+        match self.peek(1) {
+            Some(c) if self.lenient && c != '\\' && !MARKDOWN_SPECIAL_CHARS.contains(&c) => {
+                self.pos += 1;
+                self.push_text("\\", start..self.pos);
+            }
+            Some(c) if (1..=126).contains(&(c as u32)) => {
+                self.pos += 1 + c.len_utf8();
+                self.push_text(&self.text[start + 1..self.pos], start..self.pos);
+            }
+            Some(_) if self.lenient => {
+                self.pos += 1;
+                self.push_text("\\", start..self.pos);
+            }
+            Some(c) => return Err(self.error(start, format!("Character '{}' can't be escaped", c))),
+            None if self.lenient => {
+                self.pos += 1;
+                self.push_text("\\", start..self.pos);
+            }
+            None => return Err(self.error(start, "Text ends with a lone '\\'".to_string())),
+        }
+        Ok(())
+    }
+
+    /// Read an inline code span or pre block, where only '`' and '\' are special
+    fn code(&mut self, start: usize, fence: &str) -> Result<(), MarkdownV2Error> {
+        let mut pos = start + fence.len();
+
+        // A pre block may name its language on the first line
+        let mut language = None;
+        if fence == "```" {
+            let first_line = self.text[pos..].split('\n').next().unwrap_or_default();
+            let has_newline = pos + first_line.len() < self.text.len();
+            if has_newline && !first_line.contains(|c: char| c.is_whitespace() || c == '`') {
+                language = Some(first_line.to_string()).filter(|line| !line.is_empty());
+                pos += first_line.len() + 1;
+            }
+        }
+
+        let content_start = pos;
+        let mut content = String::new();
+        while let Some(c) = self.text[pos..].chars().next() {
+            let rest = &self.text[pos..];
+            if c == '\\' {
+                match rest[1..].chars().next() {
+                    Some(next) if next == '`' || next == '\\' || !self.lenient => {
+                        content.push(next);
+                        pos += 1 + next.len_utf8();
+                    }
+                    _ => {
+                        content.push(c);
+                        pos += 1;
+                    }
+                }
+            } else if rest.starts_with(fence) {
+                if content.is_empty() && self.lenient {
+                    break;
+                }
+                let kind = if fence == "`" {
+                    EntityKind::Code
+                } else {
+                    EntityKind::Pre { language }
+                };
+                let children = if content.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![Node::Text {
+                        text: content,
+                        range: content_start..pos,
+                    }]
+                };
+                self.pos = pos + fence.len();
+                let range = start..self.pos;
+                self.children().push(Node::Entity {
+                    kind,
+                    range,
+                    children,
+                });
+                return Ok(());
+            } else if c == '`' && !self.lenient {
+                return Err(
+                    self.error(pos, "Character '`' must be escaped inside code".to_string())
+                );
+            } else {
+                content.push(c);
+                pos += c.len_utf8();
+            }
+        }
+
+        if self.lenient {
+            // Not code after all, the fence is text
+            self.pos = start + fence.len();
+            self.push_text(fence, start..self.pos);
+            return Ok(());
+        }
+        let kind = if fence == "`" {
+            "Inline code"
+        } else {
+            "Code block"
+        };
+        Err(self.error(start, format!("{} is never closed", kind)))
+    }
+
+    /// Open an entity, or close it if it is the innermost open one
+    fn toggle(&mut self, marker: Marker, start: usize) -> Result<(), MarkdownV2Error> {
+        if self.is_open(marker) {
+            if self.lenient {
+                self.unwind_to(marker);
+            }
+            let innermost = self.open.last().expect("an entity is open");
+            if innermost.marker != marker {
+                return Err(self.error(
+                    start,
+                    format!(
+                        "{} entity is closed while the {} entity opened at byte {} is still open",
+                        capitalize(marker.name()),
+                        innermost.marker.name(),
+                        innermost.start
+                    ),
+                ));
+            }
+
+            let frame = self.open.pop().expect("an entity is open");
+            self.pos += marker.marker().len();
+            if frame.children.is_empty() && self.lenient {
+                // Nothing to format, e.g. `**`
+                self.unwind(frame);
+                let end = self.pos;
+                self.push_text(marker.marker(), start..end);
+            } else {
+                self.close_frame(frame, marker.kind());
+            }
+            return Ok(());
+        }
+
+        self.open_frame(marker, start);
+        Ok(())
+    }
+
+    /// Close link text with the `(url)` that must follow it
+    fn close_link(&mut self, start: usize) -> Result<(), MarkdownV2Error> {
+        let link = self
+            .open
+            .iter()
+            .rposition(|frame| matches!(frame.marker, Marker::LinkText | Marker::EmojiText));
+        let url = self.link_url(start + 1);
+
+        if self.lenient {
+            match (link, url) {
+                (Some(link), Some((url, end))) if !self.open[link].children.is_empty() => {
+                    let marker = self.open[link].marker;
+                    self.unwind_to(marker);
+                    let frame = self.open.pop().expect("a link is open");
+                    self.pos = end;
+                    self.close_frame(frame, link_kind(marker, url));
+                }
+                _ => {
+                    self.pos += 1;
+                    self.push_text("]", start..self.pos);
+                }
+            }
+            return Ok(());
+        }
+
+        let Some(link) = link else {
+            return Err(self.error(
+                start,
+                "Character ']' is reserved and must be escaped with '\\'".to_string(),
+            ));
+        };
+        if link != self.open.len() - 1 {
+            let innermost = self.open.last().expect("an entity is open");
+            return Err(self.error(
+                start,
+                format!(
+                    "Link text ends inside an unclosed {} entity",
+                    innermost.marker.name()
+                ),
+            ));
+        }
+
+        let url_start = start + 1;
+        if !self.text[url_start..].starts_with('(') {
+            return Err(self.error(
+                url_start,
+                "Link text must be followed by '(url)'".to_string(),
+            ));
+        }
+        match url {
+            Some((url, _)) if url.is_empty() => {
+                Err(self.error(url_start, "Link URL is empty".to_string()))
+            }
+            Some((url, end)) => {
+                let frame = self.open.pop().expect("a link is open");
+                let kind = link_kind(frame.marker, url);
+                self.pos = end;
+                self.close_frame(frame, kind);
+                Ok(())
+            }
+            None => Err(self.error(url_start, "Link URL is never closed with ')'".to_string())),
+        }
+    }
+
+    /// The unescaped `(url)` at `at` and the offset after it
+    fn link_url(&self, at: usize) -> Option<(String, usize)> {
+        let rest = self.text[at..].strip_prefix('(')?;
+        let mut url = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => url.extend(chars.next().map(|(_, escaped)| escaped)),
+                ')' if url.is_empty() && self.lenient => return None,
+                ')' => return Some((url, at + 1 + i + 1)),
+                _ => url.push(c),
+            }
+        }
+        None
+    }
+
+    /// Close the open block quotation at the end of its last line
+    fn close_blockquote(&mut self, end: usize) -> Result<(), MarkdownV2Error> {
+        let innermost = self.open.last().expect("a blockquote is open");
+        if innermost.marker != Marker::Blockquote {
+            return Err(self.error(
+                end,
+                format!(
+                    "Blockquote ends inside an unclosed {} entity",
+                    innermost.marker.name()
+                ),
+            ));
+        }
+        let frame = self.open.pop().expect("a blockquote is open");
+        self.close_frame(frame, EntityKind::Blockquote);
+        Ok(())
+    }
+}
+
+/// Entity of a link with its URL read
+fn link_kind(marker: Marker, url: String) -> EntityKind {
+    match marker {
+        Marker::EmojiText => EntityKind::CustomEmoji { url },
+        _ => EntityKind::TextLink { url },
+    }
+}
+
+/// Uppercase the first letter of an entity name for messages
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
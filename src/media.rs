@@ -0,0 +1,502 @@
+//! Media attached to messages.
+//!
+//! [`InputMedia`] describes a photo, video or document already known to
+//! Telegram (by `file_id`) or reachable over HTTP. Captions are raw text and go
+//! through the same formatting pipeline as messages when the request is built.
+//! [`InputFile`] is a file uploaded with the request.
+
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "reqwest")]
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::error::Error;
+use crate::transport::UPLOAD_CHUNK_SIZE;
+
+/// Kind of an [`InputMedia`], the Bot API `type` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    /// A photo
+    Photo,
+    /// A video
+    Video,
+    /// A GIF or soundless H.264/MPEG-4 video
+    Animation,
+    /// An audio file shown in the music player
+    Audio,
+    /// A general file
+    Document,
+}
+
+impl MediaKind {
+    /// Value of the Bot API `type` field
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Photo => "photo",
+            MediaKind::Video => "video",
+            MediaKind::Animation => "animation",
+            MediaKind::Audio => "audio",
+            MediaKind::Document => "document",
+        }
+    }
+}
+
+/// Largest thumbnail Telegram accepts, in bytes
+pub const MAX_THUMBNAIL_SIZE: u64 = 200 * 1024;
+
+/// Largest width and height of a thumbnail, in pixels
+pub const MAX_THUMBNAIL_DIMENSION: u32 = 320;
+
+/// Callback receiving the bytes of a file uploaded so far and its size
+pub type UploadProgress = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+/// Where the contents of an [`InputFile`] are read from
+#[derive(Clone)]
+enum FileSource {
+    /// In memory, shared by clones and retries
+    Memory(Arc<[u8]>),
+    /// On disk, opened again for each attempt
+    Path(PathBuf),
+    /// A reader, consumed by the first attempt
+    Reader(Arc<Mutex<Option<Box<dyn Read + Send>>>>),
+    /// An async reader, consumed by the first attempt
+    #[cfg(feature = "reqwest")]
+    AsyncReader(Arc<Mutex<Option<AsyncSource>>>),
+}
+
+/// Async reader of an [`InputFile::from_async_reader`] file
+#[cfg(feature = "reqwest")]
+pub(crate) type AsyncSource = Pin<Box<dyn tokio::io::AsyncRead + Send>>;
+
+/// Contents of an [`InputFile`] being read for an upload
+pub(crate) enum FileReader {
+    /// Contents held in memory, read without blocking
+    Memory(Cursor<Arc<[u8]>>),
+    /// A file or reader whose reads may block
+    Blocking(Box<dyn Read + Send>),
+    /// An async reader, polled by async transports
+    #[cfg(feature = "reqwest")]
+    Async(AsyncSource),
+}
+
+impl Read for FileReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            FileReader::Memory(contents) => contents.read(buf),
+            FileReader::Blocking(reader) => reader.read(buf),
+            // Blocking transports wait for the async reader
+            #[cfg(feature = "reqwest")]
+            FileReader::Async(reader) => crate::transport::block_on(std::future::poll_fn(|cx| {
+                let mut buf = tokio::io::ReadBuf::new(buf);
+                reader
+                    .as_mut()
+                    .poll_read(cx, &mut buf)
+                    .map_ok(|()| buf.filled().len())
+            })),
+        }
+    }
+}
+
+/// File uploaded as part of a request
+///
+/// Files read from disk or a reader are streamed into the request, so even
+/// uploads of hundreds of megabytes to a local Bot API server aren't loaded
+/// into memory.
+#[derive(Clone)]
+pub struct InputFile {
+    /// File name shown to Telegram
+    pub file_name: String,
+    source: FileSource,
+    size: u64,
+    chunk_size: usize,
+    progress: Option<UploadProgress>,
+    thumbnail: Option<Box<InputFile>>,
+    /// Whether a local Bot API server may open the file by path
+    local: bool,
+}
+
+impl InputFile {
+    /// Upload `contents` from memory as a file named `file_name`
+    ///
+    /// Generated reports never have to touch disk; `file_name` is the name
+    /// Telegram shows for the document:
+    ///
+    /// ```
+    /// use telegrama_rs::InputFile;
+    ///
+    /// let csv = "date,revenue\n2024-06-01,1200\n";
+    /// let report = InputFile::bytes("report-2024.csv", csv);
+    /// assert_eq!(report.size(), csv.len() as u64);
+    /// ```
+    pub fn bytes<N: Into<String>, C: Into<Vec<u8>>>(file_name: N, contents: C) -> Self {
+        Self::new(file_name, contents)
+    }
+
+    /// Create a file from in-memory contents, like [`InputFile::bytes`]
+    pub fn new<N: Into<String>, C: Into<Vec<u8>>>(file_name: N, contents: C) -> Self {
+        let contents: Arc<[u8]> = contents.into().into();
+        Self::with_source(
+            file_name.into(),
+            contents.len() as u64,
+            FileSource::Memory(contents),
+        )
+    }
+
+    /// Stream a file from disk, keeping its file name
+    ///
+    /// The file is read when the request is sent and must keep its size until then.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path).map_err(|e| {
+            Error::other(format!("Failed to read file '{}': {}", path.display(), e))
+        })?;
+        if !metadata.is_file() {
+            return Err(Error::other(format!(
+                "Failed to read file '{}': not a file",
+                path.display()
+            )));
+        }
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "file".to_string());
+
+        Ok(Self::with_source(
+            file_name,
+            metadata.len(),
+            FileSource::Path(path.to_path_buf()),
+        ))
+    }
+
+    /// A file co-located with a local Bot API server, passed to it by path
+    ///
+    /// Transports talking to a [local server](crate::Transport::is_local_server)
+    /// send a `file://` URI instead of uploading the file, e.g. for files just
+    /// received with a `getFile` path. Other transports upload it like
+    /// [`InputFile::from_path`].
+    pub fn local<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let path = path.canonicalize().map_err(|e| {
+            Error::other(format!("Failed to read file '{}': {}", path.display(), e))
+        })?;
+        let mut file = Self::from_path(path)?;
+        file.local = true;
+        Ok(file)
+    }
+
+    /// Stream `size` bytes of `reader`, e.g. a pipe or a decompressor
+    ///
+    /// A reader can only be read once, so a request uploading it fails instead
+    /// of being retried.
+    pub fn from_reader<N, R>(file_name: N, reader: R, size: u64) -> Self
+    where
+        N: Into<String>,
+        R: Read + Send + 'static,
+    {
+        let reader: Box<dyn Read + Send> = Box::new(reader);
+        Self::with_source(
+            file_name.into(),
+            size,
+            FileSource::Reader(Arc::new(Mutex::new(Some(reader)))),
+        )
+    }
+
+    /// Stream `size` bytes of an async `reader`, e.g. a `tokio::fs::File` or a socket
+    ///
+    /// [`AsyncReqwestTransport`](crate::AsyncReqwestTransport) polls the reader
+    /// without blocking; blocking transports wait for each read. Like
+    /// [`InputFile::from_reader`], it can only be read once.
+    #[cfg(feature = "reqwest")]
+    pub fn from_async_reader<N, R>(file_name: N, reader: R, size: u64) -> Self
+    where
+        N: Into<String>,
+        R: tokio::io::AsyncRead + Send + 'static,
+    {
+        let reader: AsyncSource = Box::pin(reader);
+        Self::with_source(
+            file_name.into(),
+            size,
+            FileSource::AsyncReader(Arc::new(Mutex::new(Some(reader)))),
+        )
+    }
+
+    fn with_source(file_name: String, size: u64, source: FileSource) -> Self {
+        InputFile {
+            file_name,
+            source,
+            size,
+            chunk_size: UPLOAD_CHUNK_SIZE,
+            progress: None,
+            thumbnail: None,
+            local: false,
+        }
+    }
+
+    /// Size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Read the file in chunks of at most `chunk_size` bytes instead of [`UPLOAD_CHUNK_SIZE`]
+    ///
+    /// Larger chunks mean fewer reads and progress reports.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Call `progress` with the bytes sent so far and the file size while uploading
+    ///
+    /// Each chunk of the file is reported as it is sent (see
+    /// [`InputFile::with_chunk_size`]). Retried requests report from zero again.
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Upload `thumbnail` as the preview of a document, video or audio file
+    ///
+    /// Telegram only accepts JPEGs of up to [`MAX_THUMBNAIL_SIZE`] bytes and
+    /// [`MAX_THUMBNAIL_DIMENSION`] pixels in width and height. Thumbnails are
+    /// checked before the upload starts, and an invalid one fails the send.
+    pub fn with_thumbnail(mut self, thumbnail: InputFile) -> Self {
+        self.thumbnail = Some(Box::new(thumbnail));
+        self
+    }
+
+    /// The thumbnail, read into memory once it passed Telegram's limits
+    pub(crate) fn validated_thumbnail(&self) -> Result<Option<InputFile>, Error> {
+        let Some(thumbnail) = &self.thumbnail else {
+            return Ok(None);
+        };
+        let invalid = |reason: String| {
+            Error::other(format!(
+                "Invalid thumbnail '{}': {}",
+                thumbnail.file_name, reason
+            ))
+        };
+        if thumbnail.size > MAX_THUMBNAIL_SIZE {
+            return Err(invalid(format!(
+                "{} bytes, at most {} are allowed",
+                thumbnail.size, MAX_THUMBNAIL_SIZE
+            )));
+        }
+
+        let contents = match thumbnail.contents() {
+            Some(contents) => contents.to_vec(),
+            None => {
+                let mut contents = Vec::new();
+                thumbnail
+                    .open()?
+                    .take(MAX_THUMBNAIL_SIZE)
+                    .read_to_end(&mut contents)
+                    .map_err(|e| invalid(e.to_string()))?;
+                contents
+            }
+        };
+        let (width, height) =
+            jpeg_dimensions(&contents).ok_or_else(|| invalid("not a JPEG image".to_string()))?;
+        if width > MAX_THUMBNAIL_DIMENSION || height > MAX_THUMBNAIL_DIMENSION {
+            return Err(invalid(format!(
+                "{}x{} pixels, at most {}x{} are allowed",
+                width, height, MAX_THUMBNAIL_DIMENSION, MAX_THUMBNAIL_DIMENSION
+            )));
+        }
+
+        Ok(Some(InputFile::new(thumbnail.file_name.clone(), contents)))
+    }
+
+    /// Most bytes read from the file at a time
+    pub(crate) fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// `file://` URI of a local file
+    pub(crate) fn local_uri(&self) -> Option<String> {
+        match &self.source {
+            FileSource::Path(path) if self.local => Some(format!("file://{}", path.display())),
+            _ => None,
+        }
+    }
+
+    /// Contents of a file held in memory
+    pub(crate) fn contents(&self) -> Option<&[u8]> {
+        match &self.source {
+            FileSource::Memory(contents) => Some(contents),
+            _ => None,
+        }
+    }
+
+    /// Start reading the file for an upload
+    pub(crate) fn open(&self) -> Result<FileReader, Error> {
+        let consumed = || {
+            Error::other(format!(
+                "Reader of '{}' was already consumed by a previous upload",
+                self.file_name
+            ))
+        };
+        match &self.source {
+            FileSource::Memory(contents) => {
+                Ok(FileReader::Memory(Cursor::new(Arc::clone(contents))))
+            }
+            FileSource::Path(path) => {
+                let file = std::fs::File::open(path).map_err(|e| {
+                    Error::other(format!("Failed to open file '{}': {}", path.display(), e))
+                })?;
+                Ok(FileReader::Blocking(Box::new(file)))
+            }
+            FileSource::Reader(reader) => reader
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+                .map(FileReader::Blocking)
+                .ok_or_else(consumed),
+            #[cfg(feature = "reqwest")]
+            FileSource::AsyncReader(reader) => reader
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+                .map(FileReader::Async)
+                .ok_or_else(consumed),
+        }
+    }
+
+    /// Report that `sent` bytes of the file were uploaded
+    pub(crate) fn report_progress(&self, sent: u64) {
+        if let Some(progress) = &self.progress {
+            progress(sent, self.size);
+        }
+    }
+}
+
+impl PartialEq for InputFile {
+    fn eq(&self, other: &Self) -> bool {
+        let same_source = match (&self.source, &other.source) {
+            (FileSource::Memory(a), FileSource::Memory(b)) => a == b,
+            (FileSource::Path(a), FileSource::Path(b)) => a == b,
+            (FileSource::Reader(a), FileSource::Reader(b)) => Arc::ptr_eq(a, b),
+            #[cfg(feature = "reqwest")]
+            (FileSource::AsyncReader(a), FileSource::AsyncReader(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        };
+        self.file_name == other.file_name
+            && same_source
+            && self.thumbnail == other.thumbnail
+            && self.local == other.local
+    }
+}
+
+impl Eq for InputFile {}
+
+impl std::fmt::Debug for InputFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Contents can be large, only show their size
+        let source = match &self.source {
+            FileSource::Memory(_) => "memory",
+            FileSource::Path(_) => "path",
+            FileSource::Reader(_) => "reader",
+            #[cfg(feature = "reqwest")]
+            FileSource::AsyncReader(_) => "async reader",
+        };
+        f.debug_struct("InputFile")
+            .field("file_name", &self.file_name)
+            .field("source", &source)
+            .field("size", &self.size)
+            .field("chunk_size", &self.chunk_size)
+            .field("progress", &self.progress.is_some())
+            .field("thumbnail", &self.thumbnail)
+            .field("local", &self.local)
+            .finish()
+    }
+}
+
+/// Width and height of a JPEG image, from its first frame header
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let read_u16 = |at: usize| -> Option<u32> {
+        Some(u32::from(u16::from_be_bytes([
+            *data.get(at)?,
+            *data.get(at + 1)?,
+        ])))
+    };
+
+    let mut at = 2;
+    loop {
+        if *data.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(at + 1)?;
+        match marker {
+            // Fill bytes before a marker
+            0xFF => at += 1,
+            // Markers without a segment
+            0x01 | 0xD0..=0xD7 => at += 2,
+            // Start of frame, except the DHT, JPG and DAC markers in that range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = read_u16(at + 5)?;
+                let width = read_u16(at + 7)?;
+                return Some((width, height));
+            }
+            _ => at += 2 + read_u16(at + 2)? as usize,
+        }
+    }
+}
+
+/// Media referenced by `file_id` or HTTP URL, with an optional caption
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMedia {
+    /// Kind of media
+    pub kind: MediaKind,
+    /// `file_id` of a file on Telegram's servers or an HTTP URL to fetch it from
+    pub media: String,
+    /// Caption before formatting
+    pub caption: Option<String>,
+}
+
+impl InputMedia {
+    /// Create media of the given kind without a caption
+    pub fn new<S: Into<String>>(kind: MediaKind, media: S) -> Self {
+        InputMedia {
+            kind,
+            media: media.into(),
+            caption: None,
+        }
+    }
+
+    /// A photo
+    pub fn photo<S: Into<String>>(media: S) -> Self {
+        Self::new(MediaKind::Photo, media)
+    }
+
+    /// A video
+    pub fn video<S: Into<String>>(media: S) -> Self {
+        Self::new(MediaKind::Video, media)
+    }
+
+    /// An animation
+    pub fn animation<S: Into<String>>(media: S) -> Self {
+        Self::new(MediaKind::Animation, media)
+    }
+
+    /// An audio file
+    pub fn audio<S: Into<String>>(media: S) -> Self {
+        Self::new(MediaKind::Audio, media)
+    }
+
+    /// A document
+    pub fn document<S: Into<String>>(media: S) -> Self {
+        Self::new(MediaKind::Document, media)
+    }
+
+    /// Set the caption
+    pub fn with_caption<S: Into<String>>(mut self, caption: S) -> Self {
+        self.caption = Some(caption.into());
+        self
+    }
+}
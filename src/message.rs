@@ -0,0 +1,241 @@
+//! Messages built with typed methods instead of an options array.
+//!
+//! [`Telegrama::message`](crate::Telegrama::message) starts a message; every
+//! option of [`Client::send_message`](crate::Client::send_message) has a method:
+//!
+//! ```no_run
+//! use telegrama_rs::Telegrama;
+//!
+//! Telegrama::message("*Deploy* done")
+//!     .to("ops")
+//!     .silent()
+//!     .markdown()
+//!     .send()?;
+//! # Ok::<(), telegrama_rs::Error>(())
+//! ```
+
+use std::time::Duration;
+
+use crate::client::{Client, Response};
+use crate::configuration::{FallbackPolicy, ParseMode, TruncateMode};
+use crate::error::Error;
+
+/// A message and its send options
+///
+/// Sent on its own with [`OutgoingMessage::send`], or with many others through
+/// [`Client::send_messages`] and [`DeliveryQueue`](crate::DeliveryQueue).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutgoingMessage {
+    /// Message text, formatted like in [`Client::send_message`]
+    pub text: String,
+    /// Options of [`Client::send_message`]
+    pub options: Vec<(String, String)>,
+}
+
+impl OutgoingMessage {
+    /// Message to the configured chat
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        OutgoingMessage {
+            text: text.into(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Send the message to `chat_id` (an ID, `@username` or alias) instead
+    pub fn to<S: Into<String>>(self, chat_id: S) -> Self {
+        self.with_option("chat_id", chat_id)
+    }
+
+    /// Send with the named bot profile of the configuration
+    pub fn bot<S: Into<String>>(self, name: S) -> Self {
+        self.with_option("bot", name)
+    }
+
+    /// Send to a named [route](crate::Route) of the configuration
+    pub fn route<S: Into<String>>(self, name: S) -> Self {
+        self.with_option("route", name)
+    }
+
+    /// Post in a forum topic of the chat
+    pub fn thread(self, message_thread_id: i64) -> Self {
+        self.with_option("message_thread_id", message_thread_id.to_string())
+    }
+
+    /// Reply to a message of the chat
+    pub fn reply_to(self, message_id: i64) -> Self {
+        self.with_option("reply_to_message_id", message_id.to_string())
+    }
+
+    /// Send the reply even if the message it replies to was deleted
+    pub fn allow_sending_without_reply(self) -> Self {
+        self.with_option("allow_sending_without_reply", "true")
+    }
+
+    /// Deliver without a notification sound
+    pub fn silent(self) -> Self {
+        self.with_option("disable_notification", "true")
+    }
+
+    /// Format the text in `parse_mode` instead of the configured default
+    pub fn parse_mode(self, parse_mode: ParseMode) -> Self {
+        self.with_option("parse_mode", parse_mode.to_string())
+    }
+
+    /// Format the text as MarkdownV2
+    pub fn markdown(self) -> Self {
+        self.parse_mode(ParseMode::MarkdownV2)
+    }
+
+    /// Format the text as HTML
+    pub fn html(self) -> Self {
+        self.parse_mode(ParseMode::Html)
+    }
+
+    /// Send the text without formatting
+    pub fn plain(self) -> Self {
+        self.parse_mode(ParseMode::Plain)
+    }
+
+    /// What to do if Telegram rejects the message in its parse mode
+    pub fn fallback_policy(self, policy: FallbackPolicy) -> Self {
+        let policy = match policy {
+            FallbackPolicy::Auto => "auto",
+            FallbackPolicy::Strict => "strict",
+            FallbackPolicy::PlainOnly => "plain_only",
+        };
+        self.with_option("fallback_policy", policy)
+    }
+
+    /// Parse modes to retry in, in order, if Telegram rejects the message
+    pub fn fallback_chain(self, modes: &[ParseMode]) -> Self {
+        let chain: Vec<String> = modes.iter().map(ParseMode::to_string).collect();
+        self.with_option("fallback_chain", chain.join(","))
+    }
+
+    /// Total time the request may take, replacing `ClientOptions::request_timeout`
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.with_option("timeout", timeout.as_secs().to_string())
+    }
+
+    /// Suppress other sends with the same key, see `ClientOptions::idempotency_window`
+    pub fn idempotency_key<S: Into<String>>(self, key: S) -> Self {
+        self.with_option("idempotency_key", key)
+    }
+
+    /// Merge the message with others of the same key, see [`crate::aggregation`]
+    pub fn aggregation_key<S: Into<String>>(self, key: S) -> Self {
+        self.with_option("aggregation_key", key)
+    }
+
+    /// Don't show a preview of the links in the message
+    pub fn disable_link_preview(self) -> Self {
+        self.with_option("link_preview_disabled", "true")
+    }
+
+    /// Show the preview of `url` instead of the first link of the message
+    pub fn link_preview_url<S: Into<String>>(self, url: S) -> Self {
+        self.with_option("link_preview_url", url)
+    }
+
+    /// Show the link preview with small media
+    pub fn prefer_small_media(self) -> Self {
+        self.with_option("link_preview_prefer_small_media", "true")
+    }
+
+    /// Show the link preview with large media
+    pub fn prefer_large_media(self) -> Self {
+        self.with_option("link_preview_prefer_large_media", "true")
+    }
+
+    /// Show the link preview above the text
+    pub fn preview_above_text(self) -> Self {
+        self.with_option("link_preview_show_above_text", "true")
+    }
+
+    /// Whether to escape Markdown special characters
+    pub fn escape_markdown(self, enabled: bool) -> Self {
+        self.with_option("escape_markdown", enabled.to_string())
+    }
+
+    /// Whether to escape HTML special characters
+    pub fn escape_html(self, enabled: bool) -> Self {
+        self.with_option("escape_html", enabled.to_string())
+    }
+
+    /// Whether to sanitize HTML to the tags Telegram supports instead of escaping it
+    pub fn sanitize_html(self, enabled: bool) -> Self {
+        self.with_option("sanitize_html", enabled.to_string())
+    }
+
+    /// Whether to obfuscate email addresses
+    pub fn obfuscate_emails(self, enabled: bool) -> Self {
+        self.with_option("obfuscate_emails", enabled.to_string())
+    }
+
+    /// Whether to mask credentials like API keys and tokens
+    pub fn scrub_secrets(self, enabled: bool) -> Self {
+        self.with_option("scrub_secrets", enabled.to_string())
+    }
+
+    /// Truncate the formatted text to `max_length` bytes
+    pub fn truncate(self, max_length: usize) -> Self {
+        self.with_option("truncate", max_length.to_string())
+    }
+
+    /// Mark where truncated text was cut with `suffix` instead of `...`
+    pub fn truncation_suffix<S: Into<String>>(self, suffix: S) -> Self {
+        self.with_option("truncation_suffix", suffix)
+    }
+
+    /// Keep the head, the tail or both ends of truncated text
+    pub fn truncate_mode(self, mode: TruncateMode) -> Self {
+        let mode = match mode {
+            TruncateMode::KeepHead => "keep_head",
+            TruncateMode::KeepTail => "keep_tail",
+            TruncateMode::KeepBothEnds => "keep_both_ends",
+        };
+        self.with_option("truncate_mode", mode)
+    }
+
+    /// Keep only the last `lines` lines of the text, before `truncate` applies
+    pub fn truncate_lines(self, lines: usize) -> Self {
+        self.with_option("truncate_lines", lines.to_string())
+    }
+
+    /// Set an option of [`Client::send_message`], replacing an earlier value
+    pub fn with_option<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        let key = key.into();
+        self.options.retain(|(k, _)| *k != key);
+        self.options.push((key, value.into()));
+        self
+    }
+
+    /// Send the message with a new [`Client`]
+    pub fn send(&self) -> Result<Response, Error> {
+        self.send_with(&Client::new())
+    }
+
+    /// Send the message with `client`
+    pub fn send_with(&self, client: &Client) -> Result<Response, Error> {
+        client.send_message(&self.text, &self.option_refs())
+    }
+
+    /// Options in the form [`Client::send_message`] takes them
+    pub(crate) fn option_refs(&self) -> Vec<(&str, &str)> {
+        self.options
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    /// Target chat, the route name without an explicit chat, empty for the configured one
+    pub(crate) fn chat(&self) -> &str {
+        let option = |key: &str| {
+            self.options
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        option("chat_id").or_else(|| option("route")).unwrap_or("")
+    }
+}
@@ -0,0 +1,58 @@
+//! Delivery metrics exported through the [`metrics`](https://docs.rs/metrics) facade.
+//!
+//! Enabled with the `metrics` feature. Install any `metrics` recorder (e.g.
+//! `metrics-exporter-prometheus`) and call [`describe`] once to register help
+//! texts. The following metrics are emitted:
+//!
+//! | Name | Type | Labels |
+//! |------|------|--------|
+//! | `telegrama_messages_sent_total` | counter | `chat_id` |
+//! | `telegrama_messages_failed_total` | counter | `chat_id`, `kind` |
+//! | `telegrama_api_request_duration_seconds` | histogram | `method` |
+
+use std::time::Duration;
+
+use ::metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+
+use crate::error::Error;
+
+/// Counter of messages delivered to Telegram
+pub const MESSAGES_SENT: &str = "telegrama_messages_sent_total";
+
+/// Counter of messages that could not be delivered, by error kind
+pub const MESSAGES_FAILED: &str = "telegrama_messages_failed_total";
+
+/// Histogram of Bot API request latency, one sample per HTTP attempt
+pub const API_LATENCY: &str = "telegrama_api_request_duration_seconds";
+
+/// Register descriptions and units of all metrics with the installed recorder
+pub fn describe() {
+    describe_counter!(MESSAGES_SENT, "Messages delivered to Telegram");
+    describe_counter!(
+        MESSAGES_FAILED,
+        "Messages that could not be delivered to Telegram"
+    );
+    describe_histogram!(
+        API_LATENCY,
+        Unit::Seconds,
+        "Latency of Telegram Bot API requests"
+    );
+}
+
+/// Count the outcome of a message send to `chat_id`
+pub(crate) fn record_delivery<T>(chat_id: &str, outcome: &Result<T, Error>) {
+    match outcome {
+        Ok(_) => counter!(MESSAGES_SENT, "chat_id" => chat_id.to_string()).increment(1),
+        Err(e) => counter!(
+            MESSAGES_FAILED,
+            "chat_id" => chat_id.to_string(),
+            "kind" => e.kind()
+        )
+        .increment(1),
+    }
+}
+
+/// Record the latency of one Bot API request attempt
+pub(crate) fn record_latency(method: &str, elapsed: Duration) {
+    histogram!(API_LATENCY, "method" => method.to_string()).record(elapsed.as_secs_f64());
+}
@@ -0,0 +1,51 @@
+//! Hooks that run around every Bot API call made by a [`Client`](crate::Client).
+//!
+//! Register a [`Middleware`] with [`Client::with_middleware`](crate::Client::with_middleware)
+//! to scrub or enrich outgoing parameters, veto requests, or observe results.
+//! Middleware runs in registration order, once per API call (retries of the same
+//! call don't run it again).
+//!
+//! ```
+//! use telegrama_rs::{Error, Middleware};
+//!
+//! /// Refuse to send anything that looks like a card number
+//! struct CardNumberGuard;
+//!
+//! impl Middleware for CardNumberGuard {
+//!     fn on_before_send(&self, _method: &str, params: &mut serde_json::Value) -> Result<(), Error> {
+//!         let text = params.get("text").and_then(|t| t.as_str()).unwrap_or("");
+//!         if text.chars().filter(char::is_ascii_digit).count() >= 16 {
+//!             return Err(Error::other("message vetoed: possible card number"));
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//! ```
+
+use crate::client::Response;
+use crate::error::Error;
+
+/// Callbacks invoked around each Bot API call
+///
+/// All methods have no-op defaults, so implementations only override the
+/// hooks they need.
+pub trait Middleware: Send + Sync {
+    /// Inspect or modify the JSON parameters of `method` before they are sent
+    ///
+    /// Returning an error vetoes the request: nothing is sent, later middleware
+    /// is skipped and the error is returned to the caller.
+    fn on_before_send(&self, method: &str, params: &mut serde_json::Value) -> Result<(), Error> {
+        let _ = (method, params);
+        Ok(())
+    }
+
+    /// Observe a successful response
+    fn on_after_send(&self, method: &str, response: &Response) {
+        let _ = (method, response);
+    }
+
+    /// Observe a failed call, including vetoes and exhausted retries
+    fn on_error(&self, method: &str, error: &Error) {
+        let _ = (method, error);
+    }
+}
@@ -0,0 +1,235 @@
+//! Permissions, administrator rights and invite links for administering groups.
+//!
+//! [`Client::restrict_chat_member`](crate::Client::restrict_chat_member) and
+//! [`Client::set_chat_permissions`](crate::Client::set_chat_permissions) take
+//! [`ChatPermissions`], [`Client::promote_chat_member`](crate::Client::promote_chat_member)
+//! takes [`ChatAdministratorRights`]. Invite links are created and edited with
+//! [`InviteLinkOptions`].
+//!
+//! ```
+//! use telegrama_rs::moderation::ChatPermissions;
+//!
+//! // Read-only: members can't post anything
+//! let read_only = ChatPermissions::none();
+//! assert_eq!(read_only.can_send_messages, Some(false));
+//!
+//! // Text only, everything else unchanged
+//! let text_only = ChatPermissions {
+//!     can_send_messages: Some(true),
+//!     can_send_photos: Some(false),
+//!     can_send_videos: Some(false),
+//!     ..Default::default()
+//! };
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::User;
+
+/// Actions members of a chat are allowed to take
+///
+/// `None` leaves a permission out of the request, so Telegram applies its
+/// default (the chat's permissions for restrictions, `false` otherwise).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatPermissions {
+    /// Send text messages, contacts, locations and venues
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_messages: Option<bool>,
+    /// Send audios
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_audios: Option<bool>,
+    /// Send documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_documents: Option<bool>,
+    /// Send photos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_photos: Option<bool>,
+    /// Send videos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_videos: Option<bool>,
+    /// Send video notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_video_notes: Option<bool>,
+    /// Send voice notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_voice_notes: Option<bool>,
+    /// Send polls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_polls: Option<bool>,
+    /// Send animations, games, stickers and inline bot results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_send_other_messages: Option<bool>,
+    /// Add web page previews to messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_add_web_page_previews: Option<bool>,
+    /// Change the chat title, photo and other settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_change_info: Option<bool>,
+    /// Invite new users
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_invite_users: Option<bool>,
+    /// Pin messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_pin_messages: Option<bool>,
+    /// Create forum topics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_manage_topics: Option<bool>,
+}
+
+impl ChatPermissions {
+    /// Every permission granted
+    pub fn all() -> Self {
+        Self::uniform(true)
+    }
+
+    /// Every permission revoked, e.g. to mute a member or make a chat read-only
+    pub fn none() -> Self {
+        Self::uniform(false)
+    }
+
+    /// Permissions all set to `allowed`
+    fn uniform(allowed: bool) -> Self {
+        let allowed = Some(allowed);
+        ChatPermissions {
+            can_send_messages: allowed,
+            can_send_audios: allowed,
+            can_send_documents: allowed,
+            can_send_photos: allowed,
+            can_send_videos: allowed,
+            can_send_video_notes: allowed,
+            can_send_voice_notes: allowed,
+            can_send_polls: allowed,
+            can_send_other_messages: allowed,
+            can_add_web_page_previews: allowed,
+            can_change_info: allowed,
+            can_invite_users: allowed,
+            can_pin_messages: allowed,
+            can_manage_topics: allowed,
+        }
+    }
+}
+
+/// Rights of a chat administrator
+///
+/// Rights left `false` are not granted; promoting a member with
+/// `ChatAdministratorRights::default()` demotes them to a regular member.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatAdministratorRights {
+    /// Keep the administrator's presence in the chat hidden
+    #[serde(default)]
+    pub is_anonymous: bool,
+    /// Access the event log, boost list, member list and statistics
+    #[serde(default)]
+    pub can_manage_chat: bool,
+    /// Delete messages of other users
+    #[serde(default)]
+    pub can_delete_messages: bool,
+    /// Manage video chats
+    #[serde(default)]
+    pub can_manage_video_chats: bool,
+    /// Restrict, ban or unban members
+    #[serde(default)]
+    pub can_restrict_members: bool,
+    /// Add administrators with a subset of their own rights
+    #[serde(default)]
+    pub can_promote_members: bool,
+    /// Change the chat title, photo and other settings
+    #[serde(default)]
+    pub can_change_info: bool,
+    /// Invite new users
+    #[serde(default)]
+    pub can_invite_users: bool,
+    /// Post stories to the chat
+    #[serde(default)]
+    pub can_post_stories: bool,
+    /// Edit stories posted by other users
+    #[serde(default)]
+    pub can_edit_stories: bool,
+    /// Delete stories posted by other users
+    #[serde(default)]
+    pub can_delete_stories: bool,
+    /// Post messages in a channel
+    #[serde(default)]
+    pub can_post_messages: bool,
+    /// Edit messages of other users in a channel
+    #[serde(default)]
+    pub can_edit_messages: bool,
+    /// Pin messages (groups and supergroups)
+    #[serde(default)]
+    pub can_pin_messages: bool,
+    /// Create, rename, close and reopen forum topics
+    #[serde(default)]
+    pub can_manage_topics: bool,
+}
+
+/// Settings of an invite link created or edited with
+/// [`Client::create_chat_invite_link`](crate::Client::create_chat_invite_link)
+///
+/// `member_limit` and `creates_join_request` can't be combined.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InviteLinkOptions {
+    /// Name of the link shown to administrators (0-32 characters)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Unix time the link expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expire_date: Option<i64>,
+    /// Number of users that can join through the link (1-99999), e.g. 1 for a single-use link
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_limit: Option<u32>,
+    /// Users joining through the link must be approved by an administrator
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub creates_join_request: bool,
+}
+
+impl InviteLinkOptions {
+    /// A link that lets a single user join
+    pub fn single_use() -> Self {
+        InviteLinkOptions {
+            member_limit: Some(1),
+            ..Default::default()
+        }
+    }
+
+    /// Give the link a name shown to administrators
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Expire the link at the given Unix time
+    pub fn with_expire_date(mut self, expire_date: i64) -> Self {
+        self.expire_date = Some(expire_date);
+        self
+    }
+}
+
+/// An invite link of a chat
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatInviteLink {
+    /// The link, with its end replaced by `…` if created by another administrator
+    pub invite_link: String,
+    /// Creator of the link
+    pub creator: User,
+    /// Users joining through the link must be approved by an administrator
+    #[serde(default)]
+    pub creates_join_request: bool,
+    /// Whether the link is the chat's primary link
+    #[serde(default)]
+    pub is_primary: bool,
+    /// Whether the link was revoked
+    #[serde(default)]
+    pub is_revoked: bool,
+    /// Name of the link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Unix time the link expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire_date: Option<i64>,
+    /// Number of users that can join through the link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_limit: Option<u32>,
+    /// Number of pending join requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_join_request_count: Option<u32>,
+}
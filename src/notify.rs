@@ -0,0 +1,122 @@
+//! Notifying the configured chat of errors while passing them on.
+//!
+//! [`NotifyErr`] adds `notify_err` to every `Result` whose error implements
+//! [`std::error::Error`]. On `Err` the error and its sources are sent to the
+//! configured chat; the `Result` is returned unchanged either way:
+//!
+//! ```no_run
+//! use telegrama_rs::NotifyErr;
+//!
+//! fn charge() -> Result<(), std::io::Error> {
+//!     Err(std::io::Error::other("card declined"))
+//! }
+//!
+//! fn run() -> Result<(), std::io::Error> {
+//!     charge().notify_err("payment job")?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Notifications are rate-limited per context to one per [`NOTIFY_INTERVAL`];
+//! the next one mentions how many errors were left out meanwhile.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::client::Client;
+
+/// Minimum time between two notifications of the same context
+pub const NOTIFY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Last notification of a context and the errors left out since
+struct Notified {
+    at: Instant,
+    suppressed: u64,
+}
+
+static NOTIFIED: Mutex<Option<HashMap<String, Notified>>> = Mutex::new(None);
+
+/// Extension of `Result` sending errors to Telegram
+pub trait NotifyErr {
+    /// On `Err`, send the error chain to the configured chat with a new [`Client`]
+    fn notify_err(self, context: &str) -> Self;
+
+    /// On `Err`, send the error chain to the configured chat with `client`
+    fn notify_err_with(self, client: &Client, context: &str) -> Self;
+}
+
+impl<T, E: StdError> NotifyErr for Result<T, E> {
+    fn notify_err(self, context: &str) -> Self {
+        if let Err(e) = &self {
+            notify(&Client::new(), context, e);
+        }
+        self
+    }
+
+    fn notify_err_with(self, client: &Client, context: &str) -> Self {
+        if let Err(e) = &self {
+            notify(client, context, e);
+        }
+        self
+    }
+}
+
+/// Send `error` unless `context` was notified within [`NOTIFY_INTERVAL`]
+fn notify(client: &Client, context: &str, error: &dyn StdError) {
+    let Some(suppressed) = admit(context) else {
+        debug!(context, "Error notification suppressed by rate limit");
+        return;
+    };
+
+    let mut text = format!("❌ {} failed: {}", context, error);
+    let mut source = error.source();
+    while let Some(cause) = source {
+        text.push_str(&format!("\nCaused by: {}", cause));
+        source = cause.source();
+    }
+    if suppressed > 0 {
+        text.push_str(&format!(
+            "\n\n{} more error(s) since the last notification",
+            suppressed
+        ));
+    }
+
+    if let Err(e) = client.send_message(&text, &[]) {
+        warn!(context, error = %e, "Failed to send error notification");
+    }
+}
+
+/// Claim a notification for `context`, returning the errors suppressed before it
+fn admit(context: &str) -> Option<u64> {
+    let now = Instant::now();
+    let mut notified = NOTIFIED.lock().unwrap_or_else(PoisonError::into_inner);
+    let notified = notified.get_or_insert_with(HashMap::new);
+
+    match notified.get_mut(context) {
+        Some(last) if now.duration_since(last.at) < NOTIFY_INTERVAL => {
+            last.suppressed += 1;
+            None
+        }
+        Some(last) => {
+            let suppressed = last.suppressed;
+            *last = Notified {
+                at: now,
+                suppressed: 0,
+            };
+            Some(suppressed)
+        }
+        None => {
+            notified.insert(
+                context.to_string(),
+                Notified {
+                    at: now,
+                    suppressed: 0,
+                },
+            );
+            Some(0)
+        }
+    }
+}
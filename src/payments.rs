@@ -0,0 +1,228 @@
+//! Invoices and the queries Telegram sends while a user pays them.
+//!
+//! [`Client::send_invoice`](crate::Client::send_invoice) sends an [`Invoice`].
+//! Telegram then sends a [`ShippingQuery`] for flexible invoices that need a
+//! shipping address, and a [`PreCheckoutQuery`] before charging the user; both
+//! must be answered within 10 seconds with
+//! [`Client::answer_shipping_query`](crate::Client::answer_shipping_query) and
+//! [`Client::answer_pre_checkout_query`](crate::Client::answer_pre_checkout_query).
+//!
+//! ```
+//! use telegrama_rs::payments::{Invoice, LabeledPrice};
+//!
+//! // Amounts are in the smallest units of the currency, e.g. cents
+//! let invoice = Invoice::new(
+//!     "Pro plan",
+//!     "One month of unlimited alerts",
+//!     "pro-monthly:user-42",
+//!     "USD",
+//!     vec![LabeledPrice::new("Pro plan", 999), LabeledPrice::new("VAT", 190)],
+//! )
+//! .with_provider_token("PAYMENT_PROVIDER_TOKEN");
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::User;
+
+/// A portion of the price, e.g. the product, a tax or a discount
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabeledPrice {
+    /// Label of the portion
+    pub label: String,
+    /// Amount in the smallest units of the currency (negative for discounts)
+    pub amount: i64,
+}
+
+impl LabeledPrice {
+    /// Create a price portion
+    pub fn new<S: Into<String>>(label: S, amount: i64) -> Self {
+        LabeledPrice {
+            label: label.into(),
+            amount,
+        }
+    }
+}
+
+/// An invoice sent with [`Client::send_invoice`](crate::Client::send_invoice)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Invoice {
+    /// Product name (1-32 characters)
+    pub title: String,
+    /// Product description (1-255 characters)
+    pub description: String,
+    /// Bot-defined payload returned in the payment queries, not shown to the user
+    pub payload: String,
+    /// Token of the payment provider, unset for payments in Telegram Stars
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_token: Option<String>,
+    /// Three-letter ISO 4217 currency code, or `XTR` for Telegram Stars
+    pub currency: String,
+    /// Price breakdown
+    pub prices: Vec<LabeledPrice>,
+    /// Maximum tip in the smallest units of the currency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tip_amount: Option<i64>,
+    /// Suggested tips, at most 4, in increasing order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggested_tip_amounts: Vec<i64>,
+    /// Deep-linking parameter; forwarded copies of the invoice become payable when set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_parameter: Option<String>,
+    /// JSON data shared with the payment provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_data: Option<String>,
+    /// URL of a product photo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub photo_url: Option<String>,
+    /// Ask for the user's full name
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub need_name: bool,
+    /// Ask for the user's phone number
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub need_phone_number: bool,
+    /// Ask for the user's email address
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub need_email: bool,
+    /// Ask for the user's shipping address
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub need_shipping_address: bool,
+    /// The final price depends on the shipping method, see [`ShippingQuery`]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_flexible: bool,
+}
+
+impl Invoice {
+    /// Create an invoice payable in Telegram Stars, or with a provider token
+    /// set with [`Invoice::with_provider_token`]
+    pub fn new<S: Into<String>>(
+        title: S,
+        description: S,
+        payload: S,
+        currency: S,
+        prices: Vec<LabeledPrice>,
+    ) -> Self {
+        Invoice {
+            title: title.into(),
+            description: description.into(),
+            payload: payload.into(),
+            provider_token: None,
+            currency: currency.into(),
+            prices,
+            max_tip_amount: None,
+            suggested_tip_amounts: Vec::new(),
+            start_parameter: None,
+            provider_data: None,
+            photo_url: None,
+            need_name: false,
+            need_phone_number: false,
+            need_email: false,
+            need_shipping_address: false,
+            is_flexible: false,
+        }
+    }
+
+    /// Charge through the payment provider of `token`
+    pub fn with_provider_token<S: Into<String>>(mut self, token: S) -> Self {
+        self.provider_token = Some(token.into());
+        self
+    }
+
+    /// Show a product photo
+    pub fn with_photo_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.photo_url = Some(url.into());
+        self
+    }
+}
+
+/// A shipping method offered in answer to a [`ShippingQuery`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShippingOption {
+    /// Identifier, returned in [`PreCheckoutQuery::shipping_option_id`]
+    pub id: String,
+    /// Name shown to the user
+    pub title: String,
+    /// Price breakdown of the method
+    pub prices: Vec<LabeledPrice>,
+}
+
+impl ShippingOption {
+    /// Create a shipping method
+    pub fn new<S: Into<String>>(id: S, title: S, prices: Vec<LabeledPrice>) -> Self {
+        ShippingOption {
+            id: id.into(),
+            title: title.into(),
+            prices,
+        }
+    }
+}
+
+/// A shipping address entered by the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShippingAddress {
+    /// Two-letter ISO 3166-1 alpha-2 country code
+    pub country_code: String,
+    /// State, if applicable
+    #[serde(default)]
+    pub state: String,
+    /// City
+    pub city: String,
+    /// First line of the address
+    pub street_line1: String,
+    /// Second line of the address
+    #[serde(default)]
+    pub street_line2: String,
+    /// Post code
+    pub post_code: String,
+}
+
+/// Information the user entered for an order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderInfo {
+    /// Full name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Phone number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    /// Email address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Shipping address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shipping_address: Option<ShippingAddress>,
+}
+
+/// Request for shipping methods of a flexible invoice
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShippingQuery {
+    /// Identifier to pass to [`Client::answer_shipping_query`](crate::Client::answer_shipping_query)
+    pub id: String,
+    /// User who is paying
+    pub from: User,
+    /// Payload of the invoice
+    pub invoice_payload: String,
+    /// Address to ship to
+    pub shipping_address: ShippingAddress,
+}
+
+/// Final confirmation request before the user is charged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreCheckoutQuery {
+    /// Identifier to pass to [`Client::answer_pre_checkout_query`](crate::Client::answer_pre_checkout_query)
+    pub id: String,
+    /// User who is paying
+    pub from: User,
+    /// Three-letter ISO 4217 currency code, or `XTR` for Telegram Stars
+    pub currency: String,
+    /// Total price in the smallest units of the currency
+    pub total_amount: i64,
+    /// Payload of the invoice
+    pub invoice_payload: String,
+    /// Shipping method chosen by the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shipping_option_id: Option<String>,
+    /// Information the user entered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_info: Option<OrderInfo>,
+}
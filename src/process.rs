@@ -0,0 +1,191 @@
+//! Reports on commands that ran to completion, e.g. deploys and cron jobs.
+//!
+//! [`notify_on_exit`] runs a command, passes its output through, and sends a
+//! success or failure report with its duration, exit code and last lines of
+//! output to the configured chat.
+//!
+//! ```no_run
+//! use std::process::Command;
+//! use telegrama_rs::process::notify_on_exit;
+//!
+//! let mut deploy = Command::new("make");
+//! deploy.arg("deploy");
+//! let report = notify_on_exit(deploy)?;
+//! std::process::exit(report.exit_code());
+//! # Ok::<(), telegrama_rs::Error>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::client::{Client, Response};
+use crate::error::Error;
+use crate::formatter::{format_duration, Formatter};
+
+/// Lines of output included in a report
+pub const TAIL_LINES: usize = 20;
+
+/// Bytes of output included in a report, to stay within the message limit
+const TAIL_BYTES: usize = 3000;
+
+/// Outcome of a command run with [`RunReport::run`]
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    /// The command line, as shown in the report
+    pub command: String,
+    /// Exit status of the command
+    pub status: ExitStatus,
+    /// Wall-clock time the command took
+    pub duration: Duration,
+    /// Last [`TAIL_LINES`] lines of stdout and stderr, interleaved
+    pub output_tail: Vec<String>,
+}
+
+impl RunReport {
+    /// Run `command` to completion, capturing the tail of its output
+    ///
+    /// stdout and stderr are still passed through to the current process;
+    /// stdin is inherited.
+    pub fn run(mut command: Command) -> Result<Self, Error> {
+        let command_line = command_line(&command);
+        let tail = Arc::new(Mutex::new(VecDeque::with_capacity(TAIL_LINES)));
+
+        let started = Instant::now();
+        let mut child = command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::other(format!("Failed to run '{}': {}", command_line, e)))?;
+
+        let readers = [
+            child.stdout.take().map(|stdout| {
+                let tail = Arc::clone(&tail);
+                std::thread::spawn(move || tee(stdout, std::io::stdout(), &tail))
+            }),
+            child.stderr.take().map(|stderr| {
+                let tail = Arc::clone(&tail);
+                std::thread::spawn(move || tee(stderr, std::io::stderr(), &tail))
+            }),
+        ];
+
+        let status = child
+            .wait()
+            .map_err(|e| Error::other(format!("Failed to wait for '{}': {}", command_line, e)))?;
+        let duration = started.elapsed();
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+
+        let output_tail = tail
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+            .collect();
+
+        Ok(RunReport {
+            command: command_line,
+            status,
+            duration,
+            output_tail,
+        })
+    }
+
+    /// Whether the command exited successfully
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    /// Exit code to exit with, mirroring the command (1 if it was killed by a signal)
+    pub fn exit_code(&self) -> i32 {
+        self.status.code().unwrap_or(1)
+    }
+
+    /// Report message, in MarkdownV2
+    pub fn message(&self) -> String {
+        let outcome = match (self.success(), self.status.code()) {
+            (true, _) => "✅ Command succeeded".to_string(),
+            (false, Some(code)) => format!("❌ Command failed with exit code {}", code),
+            (false, None) => "❌ Command was terminated by a signal".to_string(),
+        };
+
+        let mut output = format!("$ {}", self.command);
+        let tail = self.output_tail.join("\n");
+        let tail = &tail[floor_char_boundary(&tail, tail.len().saturating_sub(TAIL_BYTES))..];
+        if !tail.is_empty() {
+            output.push('\n');
+            output.push_str(tail);
+        }
+
+        format!(
+            "{} after {}\n{}",
+            outcome,
+            format_duration(self.duration),
+            Formatter::code_block(&output)
+        )
+    }
+
+    /// Send the report through `client`
+    ///
+    /// Accepts the options of [`Client::send_message`]; the parse mode is
+    /// always MarkdownV2.
+    pub fn send(&self, client: &Client, options: &[(&str, &str)]) -> Result<Response, Error> {
+        let mut options = options.to_vec();
+        options.retain(|(k, _)| *k != "parse_mode");
+        options.push(("parse_mode", "MarkdownV2"));
+        client.send_message(&self.message(), &options)
+    }
+}
+
+/// Run `command` and send a success or failure report to the configured chat
+///
+/// Fails if the command can't be started or the report can't be sent; use
+/// [`RunReport::run`] and [`RunReport::send`] to handle these separately.
+pub fn notify_on_exit(command: Command) -> Result<RunReport, Error> {
+    let report = RunReport::run(command)?;
+    report.send(&Client::new(), &[])?;
+    Ok(report)
+}
+
+/// Copy `source` to `sink` line by line, keeping the last lines in `tail`
+fn tee<R: Read, W: Write>(source: R, mut sink: W, tail: &Mutex<VecDeque<String>>) {
+    let mut reader = BufReader::new(source);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let _ = sink.write_all(&line);
+                let text = String::from_utf8_lossy(&line);
+                let mut tail = tail.lock().unwrap_or_else(PoisonError::into_inner);
+                if tail.len() == TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(text.trim_end_matches(['\r', '\n']).to_string());
+            }
+        }
+    }
+    let _ = sink.flush();
+}
+
+/// Program and arguments of `command`, separated by spaces
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The largest character boundary of `text` at or below `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
@@ -0,0 +1,136 @@
+//! Background sending, so callers don't wait for Telegram.
+//!
+//! A [`DeliveryQueue`] sends messages one after another on its own thread.
+//! [`DeliveryQueue::enqueue`] returns right away with a [`DeliveryHandle`]:
+//! drop it for fire-and-forget, or wait on it for critical messages.
+//!
+//! ```no_run
+//! use telegrama_rs::{Client, DeliveryQueue, OutgoingMessage};
+//!
+//! let queue = DeliveryQueue::start(Client::new())?;
+//!
+//! // Fire and forget
+//! queue.enqueue(OutgoingMessage::new("Cache warmed up"));
+//!
+//! // Wait for confirmation
+//! queue.enqueue(OutgoingMessage::new("Payout failed!")).wait()?;
+//! # Ok::<(), telegrama_rs::Error>(())
+//! ```
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::client::{Client, Response};
+use crate::configuration::Configuration;
+use crate::error::Error;
+use crate::message::OutgoingMessage;
+
+/// A message waiting to be sent, with the configuration of the caller
+struct Job {
+    message: OutgoingMessage,
+    config: Arc<Configuration>,
+    result: Sender<Result<Response, Error>>,
+}
+
+/// Background thread sending queued messages in order
+///
+/// Each message goes through [`Client::send_message`] with the configuration
+/// in effect for the thread that enqueued it. Dropping the queue sends the
+/// remaining messages before returning.
+pub struct DeliveryQueue {
+    jobs: Option<Sender<Job>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DeliveryQueue {
+    /// Start sending queued messages with `client`
+    pub fn start(client: Client) -> Result<Self, Error> {
+        let (jobs, jobs_rx) = mpsc::channel::<Job>();
+        let handle = std::thread::Builder::new()
+            .name("telegrama-queue".to_string())
+            .spawn(move || {
+                // Runs until the queue is dropped and drained
+                for job in jobs_rx {
+                    let _guard = Configuration::push_snapshot(job.config);
+                    let result = job.message.send_with(&client);
+                    if job.result.send(result).is_err() {
+                        debug!("Queued message sent without anyone waiting for it");
+                    }
+                }
+            })
+            .map_err(|e| Error::other(format!("Failed to start delivery queue: {}", e)))?;
+
+        Ok(DeliveryQueue {
+            jobs: Some(jobs),
+            handle: Some(handle),
+        })
+    }
+
+    /// Queue `message` for sending, returning a handle to its result
+    pub fn enqueue(&self, message: OutgoingMessage) -> DeliveryHandle {
+        let (result, result_rx) = mpsc::channel();
+        let job = Job {
+            message,
+            config: Configuration::snapshot(),
+            result,
+        };
+        if let Some(Err(mpsc::SendError(job))) = self.jobs.as_ref().map(|jobs| jobs.send(job)) {
+            // The worker died (e.g. a panicking callback); fail instead of hanging waiters
+            let _ = job
+                .result
+                .send(Err(Error::other("Delivery queue is not running")));
+        }
+        DeliveryHandle { result: result_rx }
+    }
+}
+
+impl Drop for DeliveryQueue {
+    fn drop(&mut self) {
+        // Closing the channel lets the thread finish the queued messages and exit
+        self.jobs.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Final result of a queued message
+///
+/// Dropping the handle doesn't cancel the send.
+#[derive(Debug)]
+pub struct DeliveryHandle {
+    result: Receiver<Result<Response, Error>>,
+}
+
+impl DeliveryHandle {
+    /// Block until the message was sent or finally failed
+    pub fn wait(self) -> Result<Response, Error> {
+        self.result.recv().unwrap_or_else(|_| Err(Self::lost()))
+    }
+
+    /// Block at most `timeout` for the result, `None` if it isn't there yet
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Result<Response, Error>> {
+        match self.result.recv_timeout(timeout) {
+            Ok(result) => Some(result),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Err(Self::lost())),
+        }
+    }
+
+    /// The result if the message was already handled, without blocking
+    pub fn try_result(&self) -> Option<Result<Response, Error>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(Self::lost())),
+        }
+    }
+
+    /// Error for a message whose worker went away without a result
+    fn lost() -> Error {
+        Error::other("Delivery queue stopped before the message was sent")
+    }
+}
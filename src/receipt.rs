@@ -0,0 +1,73 @@
+//! Delivery receipts handed to application callbacks, e.g. for audit trails.
+//!
+//! Register callbacks with
+//! [`Configuration::on_delivered`](crate::Configuration::on_delivered) and
+//! [`Configuration::on_failed`](crate::Configuration::on_failed):
+//!
+//! ```
+//! use telegrama_rs::Telegrama;
+//!
+//! Telegrama::configure(|config| {
+//!     config.on_delivered(|receipt| {
+//!         println!(
+//!             "delivered message {:?} to {} in {:?} ({} attempts)",
+//!             receipt.message_id, receipt.chat_id, receipt.latency, receipt.attempts
+//!         );
+//!     });
+//!     config.on_failed(|receipt, error| {
+//!         eprintln!("could not deliver to {}: {}", receipt.chat_id, error);
+//!     });
+//! });
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// Outcome details of a send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryReceipt {
+    /// Chat the message was sent to
+    pub chat_id: String,
+    /// ID of the delivered message (`None` for failed sends)
+    pub message_id: Option<i64>,
+    /// Bot API requests made, including retries and parse mode fallbacks
+    pub attempts: u32,
+    /// Time from the start of the send to its outcome
+    pub latency: Duration,
+}
+
+/// Callback invoked after a successful send
+pub(crate) type DeliveredFn = Arc<dyn Fn(&DeliveryReceipt) + Send + Sync>;
+
+/// Callback invoked after a failed send
+pub(crate) type FailedFn = Arc<dyn Fn(&DeliveryReceipt, &Error) + Send + Sync>;
+
+/// Receipt callbacks registered on a configuration
+#[derive(Clone, Default)]
+pub(crate) struct ReceiptCallbacks {
+    pub(crate) delivered: Vec<DeliveredFn>,
+    pub(crate) failed: Vec<FailedFn>,
+}
+
+impl ReceiptCallbacks {
+    /// Hand `receipt` to the callbacks matching `outcome`
+    pub(crate) fn notify<T>(&self, receipt: &DeliveryReceipt, outcome: &Result<T, Error>) {
+        match outcome {
+            Ok(_) => self.delivered.iter().for_each(|callback| callback(receipt)),
+            Err(e) => self.failed.iter().for_each(|callback| callback(receipt, e)),
+        }
+    }
+}
+
+impl std::fmt::Debug for ReceiptCallbacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{} delivered and {} failed callback(s)]",
+            self.delivered.len(),
+            self.failed.len()
+        )
+    }
+}
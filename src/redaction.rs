@@ -0,0 +1,166 @@
+//! User-defined redaction of sensitive content before it reaches Telegram.
+//!
+//! Register [`RedactionRule`]s with
+//! [`Configuration::add_redaction`](crate::Configuration::add_redaction); the
+//! formatter applies them to every message and caption, before escaping, so
+//! API keys, JWTs or IP addresses never end up in chat history:
+//!
+//! ```
+//! use telegrama_rs::{Redaction, Telegrama};
+//!
+//! Telegrama::configure(|config| {
+//!     config
+//!         .add_redaction("jwt", r"eyJ[\w-]+\.[\w-]+\.[\w-]+", Redaction::Mask)
+//!         .unwrap();
+//!     config
+//!         .add_redaction("ip", r"\b\d{1,3}(\.\d{1,3}){3}\b", Redaction::Hash)
+//!         .unwrap();
+//! });
+//! ```
+
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::secret::REDACTED;
+
+/// What replaces a match of a [`RedactionRule`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Redaction {
+    /// Replace the match with `[REDACTED]`
+    Mask,
+    /// Replace the match with a short hash, so repeats of a value can still be
+    /// told apart, e.g. `[hash:3f9a0c2e]`
+    Hash,
+    /// Remove the match
+    Drop,
+}
+
+impl FromStr for Redaction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mask" => Ok(Redaction::Mask),
+            "hash" => Ok(Redaction::Hash),
+            "drop" => Ok(Redaction::Drop),
+            _ => Err(Error::configuration(format!(
+                "Unknown redaction '{}', expected mask, hash or drop",
+                s
+            ))),
+        }
+    }
+}
+
+/// A named pattern and how to redact its matches
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RuleFile", into = "RuleFile")]
+pub struct RedactionRule {
+    name: String,
+    pattern: Regex,
+    redaction: Redaction,
+}
+
+/// Serialized form of a [`RedactionRule`], with the pattern as a string
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    name: String,
+    pattern: String,
+    redaction: Redaction,
+}
+
+impl RedactionRule {
+    /// Rule redacting matches of the regular expression `pattern`
+    pub fn new<N: Into<String>>(
+        name: N,
+        pattern: &str,
+        redaction: Redaction,
+    ) -> Result<Self, Error> {
+        let name = name.into();
+        let pattern = Regex::new(pattern).map_err(|e| {
+            Error::configuration(format!("Invalid redaction pattern '{}': {}", name, e))
+        })?;
+        Ok(RedactionRule {
+            name,
+            pattern,
+            redaction,
+        })
+    }
+
+    /// Name of the rule, e.g. `"jwt"`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Regular expression matching the content to redact
+    pub fn pattern(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    /// How matches are redacted
+    pub fn redaction(&self) -> Redaction {
+        self.redaction
+    }
+
+    /// Redact every match in `text`, borrowing it if nothing matches
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.redaction {
+            Redaction::Mask => self.pattern.replace_all(text, REDACTED),
+            Redaction::Hash => self
+                .pattern
+                .replace_all(text, |caps: &regex::Captures| hashed(&caps[0])),
+            Redaction::Drop => self.pattern.replace_all(text, ""),
+        }
+    }
+}
+
+impl TryFrom<RuleFile> for RedactionRule {
+    type Error = Error;
+
+    fn try_from(rule: RuleFile) -> Result<Self, Self::Error> {
+        RedactionRule::new(rule.name, &rule.pattern, rule.redaction)
+    }
+}
+
+impl From<RedactionRule> for RuleFile {
+    fn from(rule: RedactionRule) -> Self {
+        RuleFile {
+            pattern: rule.pattern.as_str().to_string(),
+            name: rule.name,
+            redaction: rule.redaction,
+        }
+    }
+}
+
+impl fmt::Debug for RedactionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedactionRule")
+            .field("name", &self.name)
+            .field("pattern", &self.pattern.as_str())
+            .field("redaction", &self.redaction)
+            .finish()
+    }
+}
+
+impl PartialEq for RedactionRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.pattern.as_str() == other.pattern.as_str()
+            && self.redaction == other.redaction
+    }
+}
+
+/// Short, stable-within-the-process hash of a redacted value
+fn hashed(value: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    format!("[hash:{:08x}]", hasher.finish() as u32)
+}
@@ -0,0 +1,462 @@
+//! Alerts for failed requests of web services.
+//!
+//! An [`HttpErrorReporter`] sends a Telegram alert with the method, path,
+//! status and latency of requests that failed. Alerts for the same method,
+//! path and status are throttled to one per interval, the next one mentions
+//! how many were left out meanwhile.
+//!
+//! Web frameworks are hooked up with the `tower` feature, for axum, hyper and
+//! other tower services ([`ErrorReportingLayer`]), and the `actix-web` feature
+//! ([`ActixErrorReporting`]).
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::client::Client;
+use crate::configuration::Configuration;
+use crate::formatter::format_duration;
+
+/// Minimum time between two alerts of the same method, path and status
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Last alert of a request kind and the alerts left out since
+struct Reported {
+    at: Instant,
+    suppressed: u64,
+}
+
+/// Sends throttled alerts for failed HTTP requests
+#[derive(Clone)]
+pub struct HttpErrorReporter {
+    client: Arc<Client>,
+    statuses: Vec<RangeInclusive<u16>>,
+    interval: Duration,
+    reported: Arc<Mutex<HashMap<String, Reported>>>,
+}
+
+impl HttpErrorReporter {
+    /// Reporter of 5xx responses, sending with a new [`Client`]
+    pub fn new() -> Self {
+        HttpErrorReporter {
+            client: Arc::new(Client::new()),
+            statuses: vec![500..=599],
+            interval: DEFAULT_INTERVAL,
+            reported: Arc::default(),
+        }
+    }
+
+    /// Send alerts with `client`
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Report responses with a status in one of `statuses` instead of 5xx ones
+    pub fn with_statuses<I: IntoIterator<Item = RangeInclusive<u16>>>(
+        mut self,
+        statuses: I,
+    ) -> Self {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Send at most one alert per method, path and status within `interval`
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Whether responses with `status` are reported
+    pub fn reports(&self, status: u16) -> bool {
+        self.statuses.iter().any(|range| range.contains(&status))
+    }
+
+    /// Report a response with `status`, if it is one of the reported statuses
+    pub fn report_status(&self, method: &str, path: &str, status: u16, latency: Duration) {
+        self.report(method, path, status, None, latency);
+    }
+
+    /// Report a response with `status` caused by `error`, if it is one of the reported statuses
+    pub fn report_error(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        error: &str,
+        latency: Duration,
+    ) {
+        self.report(method, path, status, Some(error), latency);
+    }
+
+    fn report(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        error: Option<&str>,
+        latency: Duration,
+    ) {
+        if !self.reports(status) {
+            return;
+        }
+        let mut text = format!("🔥 {} {} → {}", method, path, status);
+        if let Some(reason) = http::StatusCode::from_u16(status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+        {
+            text.push(' ');
+            text.push_str(reason);
+        }
+        text.push_str(&format!(" ({})", format_duration(latency)));
+        if let Some(error) = error {
+            text.push_str(&format!(": {}", error));
+        }
+        self.send(format!("{} {} {}", method, path, status), text);
+    }
+
+    /// Report a request whose handler panicked with `message`
+    pub fn report_panic(&self, method: &str, path: &str, message: &str, latency: Duration) {
+        let text = format!(
+            "🔥 {} {} panicked after {}: {}",
+            method,
+            path,
+            format_duration(latency),
+            message
+        );
+        self.send(format!("{} {} panic", method, path), text);
+    }
+
+    /// Send `text` unless `key` was reported within the interval
+    ///
+    /// Inside a tokio runtime, the alert is sent on a blocking thread so the
+    /// response isn't delayed.
+    fn send(&self, key: String, mut text: String) {
+        let Some(suppressed) = self.admit(key) else {
+            debug!("HTTP error alert suppressed by rate limit");
+            return;
+        };
+        if suppressed > 0 {
+            text.push_str(&format!(
+                "\n\n{} more alert(s) since the last one",
+                suppressed
+            ));
+        }
+
+        let client = Arc::clone(&self.client);
+        let config = Configuration::snapshot();
+        let send = move || {
+            let _guard = Configuration::push_snapshot(config);
+            if let Err(e) = client.send_message(&text, &[]) {
+                warn!(error = %e, "Failed to send HTTP error alert");
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(send)),
+            Err(_) => send(),
+        }
+    }
+
+    /// Claim an alert for `key`, returning the alerts suppressed before it
+    fn admit(&self, key: String) -> Option<u64> {
+        let now = Instant::now();
+        let mut reported = self.reported.lock().unwrap_or_else(PoisonError::into_inner);
+        // Paths with IDs would grow the map forever
+        reported
+            .retain(|_, last| last.suppressed > 0 || now.duration_since(last.at) < self.interval);
+
+        match reported.get_mut(&key) {
+            Some(last) if now.duration_since(last.at) < self.interval => {
+                last.suppressed += 1;
+                None
+            }
+            last => {
+                let suppressed = last.map_or(0, |last| last.suppressed);
+                reported.insert(
+                    key,
+                    Reported {
+                        at: now,
+                        suppressed: 0,
+                    },
+                );
+                Some(suppressed)
+            }
+        }
+    }
+}
+
+impl Default for HttpErrorReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for HttpErrorReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpErrorReporter")
+            .field("statuses", &self.statuses)
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Text of a panic payload
+#[cfg(feature = "tower")]
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// [`tower_layer::Layer`] reporting failed requests with an [`HttpErrorReporter`]
+///
+/// Enabled with the `tower` feature, e.g. for an axum router:
+///
+/// ```no_run
+/// use telegrama_rs::reporting::{ErrorReportingLayer, HttpErrorReporter};
+///
+/// // Also report rate limiting, at most once per method, path and status every 5 minutes
+/// let reporter = HttpErrorReporter::new()
+///     .with_statuses([429..=429, 500..=599])
+///     .with_interval(std::time::Duration::from_secs(300));
+/// let layer = ErrorReportingLayer::with_reporter(reporter);
+/// // let app = axum::Router::new().route("/orders", post(create_order)).layer(layer);
+/// ```
+#[cfg(feature = "tower")]
+#[derive(Debug, Clone, Default)]
+pub struct ErrorReportingLayer {
+    reporter: HttpErrorReporter,
+}
+
+#[cfg(feature = "tower")]
+impl ErrorReportingLayer {
+    /// Layer reporting 5xx responses and panics with a default [`HttpErrorReporter`]
+    pub fn new() -> Self {
+        Self::with_reporter(HttpErrorReporter::new())
+    }
+
+    /// Layer reporting with `reporter`
+    pub fn with_reporter(reporter: HttpErrorReporter) -> Self {
+        ErrorReportingLayer { reporter }
+    }
+}
+
+#[cfg(feature = "tower")]
+impl<S> tower_layer::Layer<S> for ErrorReportingLayer {
+    type Service = ErrorReporting<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ErrorReporting {
+            inner,
+            reporter: self.reporter.clone(),
+        }
+    }
+}
+
+/// Service wrapped by an [`ErrorReportingLayer`]
+///
+/// Panics are reported and then resumed, so the service behaves as before.
+#[cfg(feature = "tower")]
+#[derive(Debug, Clone)]
+pub struct ErrorReporting<S> {
+    inner: S,
+    reporter: HttpErrorReporter,
+}
+
+#[cfg(feature = "tower")]
+impl<S, ReqBody, ResBody> tower_service::Service<http::Request<ReqBody>> for ErrorReporting<S>
+where
+    S: tower_service::Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        use std::future::Future;
+        use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+        use std::task::Poll;
+
+        let started = Instant::now();
+        let method = request.method().to_string();
+        let path = request.uri().path().to_string();
+        let reporter = self.reporter.clone();
+
+        let mut future = match catch_unwind(AssertUnwindSafe(|| self.inner.call(request))) {
+            Ok(future) => Box::pin(future),
+            Err(panic) => {
+                reporter.report_panic(&method, &path, panic_message(&*panic), started.elapsed());
+                resume_unwind(panic);
+            }
+        };
+
+        Box::pin(async move {
+            let outcome = std::future::poll_fn(|cx| {
+                match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                    Ok(Poll::Ready(result)) => Poll::Ready(Ok(result)),
+                    Ok(Poll::Pending) => Poll::Pending,
+                    Err(panic) => Poll::Ready(Err(panic)),
+                }
+            })
+            .await;
+
+            match outcome {
+                Ok(result) => {
+                    if let Ok(response) = &result {
+                        reporter.report_status(
+                            &method,
+                            &path,
+                            response.status().as_u16(),
+                            started.elapsed(),
+                        );
+                    }
+                    result
+                }
+                Err(panic) => {
+                    reporter.report_panic(
+                        &method,
+                        &path,
+                        panic_message(&*panic),
+                        started.elapsed(),
+                    );
+                    resume_unwind(panic)
+                }
+            }
+        })
+    }
+}
+
+/// actix-web middleware reporting failed requests with an [`HttpErrorReporter`]
+///
+/// Enabled with the `actix-web` feature. Responses with a reported status are
+/// sent with the error that caused them, if any, including errors no handler
+/// turned into a response:
+///
+/// ```no_run
+/// use actix_web::App;
+/// use telegrama_rs::reporting::{ActixErrorReporting, HttpErrorReporter};
+///
+/// // Report client errors too
+/// let app = App::new().wrap(ActixErrorReporting::with_reporter(
+///     HttpErrorReporter::new().with_statuses([400..=599]),
+/// ));
+/// ```
+#[cfg(feature = "actix-web")]
+#[derive(Debug, Clone, Default)]
+pub struct ActixErrorReporting {
+    reporter: HttpErrorReporter,
+}
+
+#[cfg(feature = "actix-web")]
+impl ActixErrorReporting {
+    /// Middleware reporting 5xx responses with a default [`HttpErrorReporter`]
+    pub fn new() -> Self {
+        Self::with_reporter(HttpErrorReporter::new())
+    }
+
+    /// Middleware reporting with `reporter`
+    pub fn with_reporter(reporter: HttpErrorReporter) -> Self {
+        ActixErrorReporting { reporter }
+    }
+}
+
+#[cfg(feature = "actix-web")]
+impl<S, B> actix_web::dev::Transform<S, actix_web::dev::ServiceRequest> for ActixErrorReporting
+where
+    S: actix_web::dev::Service<
+            actix_web::dev::ServiceRequest,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = ActixErrorReportingService<S>;
+    type InitError = ();
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(ActixErrorReportingService {
+            service,
+            reporter: self.reporter.clone(),
+        }))
+    }
+}
+
+/// Service wrapped by [`ActixErrorReporting`]
+#[cfg(feature = "actix-web")]
+#[derive(Debug)]
+pub struct ActixErrorReportingService<S> {
+    service: S,
+    reporter: HttpErrorReporter,
+}
+
+#[cfg(feature = "actix-web")]
+impl<S, B> actix_web::dev::Service<actix_web::dev::ServiceRequest> for ActixErrorReportingService<S>
+where
+    S: actix_web::dev::Service<
+            actix_web::dev::ServiceRequest,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future =
+        std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, request: actix_web::dev::ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = request.method().to_string();
+        let path = request.path().to_string();
+        let reporter = self.reporter.clone();
+        let response = self.service.call(request);
+
+        Box::pin(async move {
+            match response.await {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    match response.response().error() {
+                        Some(error) => reporter.report_error(
+                            &method,
+                            &path,
+                            status,
+                            &error.to_string(),
+                            started.elapsed(),
+                        ),
+                        None => reporter.report_status(&method, &path, status, started.elapsed()),
+                    }
+                    Ok(response)
+                }
+                Err(error) => {
+                    // actix-web turns the error into a response with this status
+                    let status = error.as_response_error().status_code().as_u16();
+                    reporter.report_error(
+                        &method,
+                        &path,
+                        status,
+                        &error.to_string(),
+                        started.elapsed(),
+                    );
+                    Err(error)
+                }
+            }
+        })
+    }
+}
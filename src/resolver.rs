@@ -0,0 +1,52 @@
+//! Cache of `@username` chat targets resolved to numeric chat IDs.
+//!
+//! Sending to `@channelusername` makes the client look the chat up with `getChat`
+//! once and reuse the numeric ID for an hour, so channel targets work like any
+//! other chat ID without a lookup per message.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::time::Instant;
+
+/// How long a resolved chat ID is reused before looking it up again
+pub const CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Resolved chat IDs and their expiry, keyed by bot (token hash) and lowercase username
+type Cache = HashMap<(u64, String), (String, Instant)>;
+
+static RESOLVED: Mutex<Option<Cache>> = Mutex::new(None);
+
+/// Whether a chat target is a public username like `@mychannel`
+pub(crate) fn is_username(chat_id: &str) -> bool {
+    chat_id.len() > 1 && chat_id.starts_with('@')
+}
+
+/// Cached numeric ID of `username` for the bot owning `bot_token`
+pub(crate) fn cached(bot_token: &str, username: &str) -> Option<String> {
+    let now = Instant::now();
+    let resolved = RESOLVED.lock().unwrap_or_else(PoisonError::into_inner);
+    resolved
+        .as_ref()?
+        .get(&key(bot_token, username))
+        .filter(|(_, expires_at)| *expires_at > now)
+        .map(|(chat_id, _)| chat_id.clone())
+}
+
+/// Remember the numeric ID of `username`
+pub(crate) fn store(bot_token: &str, username: &str, chat_id: String) {
+    let now = Instant::now();
+    let mut resolved = RESOLVED.lock().unwrap_or_else(PoisonError::into_inner);
+    let resolved = resolved.get_or_insert_with(HashMap::new);
+    resolved.retain(|_, (_, expires_at)| *expires_at > now);
+    resolved.insert(key(bot_token, username), (chat_id, now + CACHE_TTL));
+}
+
+fn key(bot_token: &str, username: &str) -> (u64, String) {
+    let mut hasher = DefaultHasher::new();
+    bot_token.hash(&mut hasher);
+    (hasher.finish(), username.to_lowercase())
+}
@@ -0,0 +1,76 @@
+//! Secret values that must never show up in logs, dumps or error messages.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Placeholder printed instead of a secret
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A string that is redacted when printed with `Debug` or serialized
+///
+/// Used for bot tokens, so `{:?}`-printing or dumping a [`Configuration`](crate::Configuration)
+/// doesn't leak them. Call [`SecretString::expose_secret`] to read the value.
+///
+/// Serializing is one-way: the value is written as [`REDACTED`], and deserializing
+/// [`REDACTED`] fails instead of turning the placeholder into a token. A serialized
+/// configuration therefore can't be loaded back as is; set its tokens again, e.g. from
+/// `TELEGRAMA_BOT_TOKEN`, before using it.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wrap a secret value
+    pub fn new<S: Into<String>>(secret: S) -> Self {
+        SecretString(secret.into())
+    }
+
+    /// Access the secret value
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        SecretString(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        SecretString(secret.to_string())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let secret = String::deserialize(deserializer)?;
+        if secret == REDACTED {
+            return Err(D::Error::custom(
+                "secret is the redacted placeholder of a serialized value, not the secret itself",
+            ));
+        }
+        Ok(SecretString(secret))
+    }
+}
+
+/// Replace every occurrence of `secret` in `text` with [`REDACTED`]
+pub(crate) fn redact(text: &str, secret: &str) -> String {
+    if secret.is_empty() {
+        return text.to_string();
+    }
+    text.replace(secret, REDACTED)
+}
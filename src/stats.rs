@@ -0,0 +1,110 @@
+//! Process-wide delivery counters, cheap enough to keep without the `metrics` feature.
+//!
+//! [`Telegrama::stats`](crate::Telegrama::stats) returns the counters since startup,
+//! e.g. to answer a `/status` bot command:
+//!
+//! ```
+//! use telegrama_rs::Telegrama;
+//!
+//! let stats = Telegrama::stats();
+//! let reply = format!("Delivery since startup:\n{}", stats);
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::error::Error;
+
+static SENT: AtomicU64 = AtomicU64::new(0);
+static RETRIED: AtomicU64 = AtomicU64::new(0);
+static DEDUPLICATED: AtomicU64 = AtomicU64::new(0);
+static RATE_LIMITED: AtomicU64 = AtomicU64::new(0);
+static FAILED: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+
+/// Delivery counters since the process started
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeliveryStats {
+    /// Messages delivered to Telegram
+    pub sent: u64,
+    /// Messages that could not be delivered, by error kind (see [`Error::kind`])
+    pub failed: BTreeMap<&'static str, u64>,
+    /// Bot API requests retried after a network error, 5xx or flood control
+    pub retried: u64,
+    /// Messages suppressed as duplicates within the idempotency window
+    pub deduplicated: u64,
+    /// Bot API requests answered with flood control (HTTP 429)
+    pub rate_limited: u64,
+}
+
+impl DeliveryStats {
+    /// Messages that could not be delivered, whatever the error
+    pub fn failed_total(&self) -> u64 {
+        self.failed.values().sum()
+    }
+}
+
+impl fmt::Display for DeliveryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sent: {}\nFailed: {}", self.sent, self.failed_total())?;
+        if !self.failed.is_empty() {
+            let kinds: Vec<String> = self
+                .failed
+                .iter()
+                .map(|(kind, count)| format!("{}: {}", kind, count))
+                .collect();
+            write!(f, " ({})", kinds.join(", "))?;
+        }
+        write!(
+            f,
+            "\nRetried: {}\nDeduplicated: {}\nRate limited: {}",
+            self.retried, self.deduplicated, self.rate_limited
+        )
+    }
+}
+
+/// Current value of all counters
+pub(crate) fn snapshot() -> DeliveryStats {
+    DeliveryStats {
+        sent: SENT.load(Ordering::Relaxed),
+        failed: FAILED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone(),
+        retried: RETRIED.load(Ordering::Relaxed),
+        deduplicated: DEDUPLICATED.load(Ordering::Relaxed),
+        rate_limited: RATE_LIMITED.load(Ordering::Relaxed),
+    }
+}
+
+/// Count the outcome of a message send
+pub(crate) fn record_delivery<T>(outcome: &Result<T, Error>) {
+    match outcome {
+        Ok(_) => {
+            SENT.fetch_add(1, Ordering::Relaxed);
+        }
+        Err(e) => {
+            *FAILED
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(e.kind())
+                .or_default() += 1;
+        }
+    }
+}
+
+/// Count a Bot API request about to be retried
+pub(crate) fn record_retry() {
+    RETRIED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a message suppressed as a duplicate
+pub(crate) fn record_duplicate() {
+    DEDUPLICATED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a flood control response
+pub(crate) fn record_rate_limit() {
+    RATE_LIMITED.fetch_add(1, Ordering::Relaxed);
+}
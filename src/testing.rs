@@ -0,0 +1,276 @@
+//! Test helpers for applications that send Telegram messages.
+//!
+//! [`MockTransport`] is a [`Transport`] that records every request a [`Client`] sends and answers
+//! with canned Telegram responses, so alerting logic can be tested without
+//! network access.
+//!
+//! ```
+//! use telegrama_rs::testing::MockTransport;
+//! use telegrama_rs::Telegrama;
+//!
+//! let transport = MockTransport::new();
+//! let client = transport.client();
+//!
+//! Telegrama::with_config(
+//!     |config| {
+//!         config.set_bot_token("TEST_TOKEN");
+//!         config.set_chat_id("42");
+//!     },
+//!     || client.send_message("Disk almost full", &[]).unwrap(),
+//! );
+//!
+//! let request = transport.last_request().unwrap();
+//! assert_eq!(request.chat_id, "42");
+//! assert_eq!(request.text, "Disk almost full");
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::transport::{self, RawResponse, RequestBody, Transport};
+
+/// A request captured by [`MockTransport`]
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    /// Bot API method (e.g. `sendMessage`)
+    pub method: String,
+    /// Target chat ID
+    pub chat_id: String,
+    /// Final message text, after formatting
+    pub text: String,
+    /// Parse mode sent to Telegram (`None` for plain text)
+    pub parse_mode: Option<String>,
+    /// Full JSON body of the request
+    pub params: serde_json::Value,
+    /// Per-request timeout passed to the transport, if any
+    pub timeout: Option<Duration>,
+}
+
+/// Canned reply returned by [`MockTransport`]
+#[derive(Debug, Clone)]
+enum MockReply {
+    /// Successful response with the given result
+    Ok(serde_json::Value),
+    /// Telegram API error with the given description
+    ApiError(String),
+    /// Raw HTTP response
+    Raw(RawResponse),
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    requests: Vec<RecordedRequest>,
+    replies: VecDeque<MockReply>,
+    files: HashMap<String, Vec<u8>>,
+    local_server: bool,
+    next_message_id: i64,
+}
+
+/// In-memory transport that records requests instead of sending them
+///
+/// Clones share the same recorded requests and queued replies. When no reply
+/// is queued, every request succeeds with a message result echoing the text.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTransport {
+    /// Create an empty mock transport
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a [`Client`] that sends through this transport
+    pub fn client(&self) -> Client {
+        Client::with_transport(self.clone())
+    }
+
+    /// Queue a successful reply with a custom `result` payload
+    pub fn push_ok(&self, result: serde_json::Value) {
+        self.lock().replies.push_back(MockReply::Ok(result));
+    }
+
+    /// Queue a Telegram API error, e.g. `"Bad Request: can't parse entities"`
+    pub fn push_error<S: AsRef<str>>(&self, description: S) {
+        self.lock()
+            .replies
+            .push_back(MockReply::ApiError(description.as_ref().to_string()));
+    }
+
+    /// Queue a raw HTTP response, e.g. a 429 or a 502 from a proxy
+    pub fn push_raw(&self, status: u16, body: impl Into<String>) {
+        self.lock().replies.push_back(MockReply::Raw(RawResponse {
+            status,
+            body: body.into(),
+        }));
+    }
+
+    /// Serve `contents` for downloads of `file_path`
+    ///
+    /// Downloads of other paths fail with a 404 error. Downloads are recorded
+    /// as `download` requests with the `file_path` in their params.
+    pub fn push_file(&self, file_path: impl Into<String>, contents: impl Into<Vec<u8>>) {
+        self.lock().files.insert(file_path.into(), contents.into());
+    }
+
+    /// Act as a local Bot API server, receiving [local files](crate::InputFile::local) by path
+    pub fn set_local_server(&self, local_server: bool) {
+        self.lock().local_server = local_server;
+    }
+
+    /// All requests recorded so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// The most recent request, if any
+    pub fn last_request(&self) -> Option<RecordedRequest> {
+        self.lock().requests.last().cloned()
+    }
+
+    /// Number of requests recorded so far
+    pub fn request_count(&self) -> usize {
+        self.lock().requests.len()
+    }
+
+    /// Forget recorded requests and queued replies
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.requests.clear();
+        state.replies.clear();
+        state.files.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record a request and produce the next canned reply
+    fn record(
+        &self,
+        method: &str,
+        body: RequestBody,
+        timeout: Option<Duration>,
+    ) -> Result<RawResponse, Error> {
+        let params = match body {
+            RequestBody::Json(params) => params,
+            // Files are read like a real transport would, reporting their
+            // upload progress, and recorded by name and size
+            RequestBody::Multipart { mut params, files } => {
+                let mut body = RequestBody::multipart_reader(&params, files.clone())?;
+                std::io::copy(&mut body, &mut std::io::sink())
+                    .map_err(|e| Error::transport(format!("Failed to read upload: {}", e)))?;
+                for (name, file) in files {
+                    params[name] = serde_json::json!({
+                        "file_name": file.file_name,
+                        "size": file.size(),
+                    });
+                }
+                params
+            }
+        };
+        let mut state = self.lock();
+
+        let field = |name: &str| params.get(name).and_then(|v| v.as_str()).unwrap_or("");
+        let chat_id = params
+            .get("chat_id")
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| v.to_string())
+            })
+            .unwrap_or_default();
+        let parse_mode = Some(field("parse_mode"))
+            .filter(|mode| !mode.is_empty())
+            .map(str::to_string);
+
+        state.requests.push(RecordedRequest {
+            method: method.to_string(),
+            chat_id: chat_id.clone(),
+            text: field("text").to_string(),
+            parse_mode,
+            params: params.clone(),
+            timeout,
+        });
+
+        let reply = match state.replies.pop_front() {
+            Some(MockReply::Ok(result)) => serde_json::json!({ "ok": true, "result": result }),
+            Some(MockReply::ApiError(description)) => {
+                serde_json::json!({ "ok": false, "description": description })
+            }
+            Some(MockReply::Raw(response)) => return Ok(response),
+            None => {
+                state.next_message_id += 1;
+                serde_json::json!({
+                    "ok": true,
+                    "result": {
+                        "message_id": state.next_message_id,
+                        "chat": { "id": chat_id },
+                        "text": params.get("text").cloned().unwrap_or_default(),
+                    },
+                })
+            }
+        };
+
+        Ok(RawResponse {
+            status: 200,
+            body: reply.to_string(),
+        })
+    }
+}
+
+impl Transport for MockTransport {
+    fn execute(
+        &self,
+        _bot_token: &str,
+        method: &str,
+        body: RequestBody,
+    ) -> Result<RawResponse, Error> {
+        self.record(method, body, None)
+    }
+
+    fn execute_with_timeout(
+        &self,
+        _bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Duration,
+    ) -> Result<RawResponse, Error> {
+        self.record(method, body, Some(timeout))
+    }
+
+    fn is_local_server(&self) -> bool {
+        self.lock().local_server
+    }
+
+    fn download(
+        &self,
+        _bot_token: &str,
+        file_path: &str,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Error> {
+        let mut state = self.lock();
+        state.requests.push(RecordedRequest {
+            method: "download".to_string(),
+            chat_id: String::new(),
+            text: String::new(),
+            parse_mode: None,
+            params: serde_json::json!({ "file_path": file_path }),
+            timeout: None,
+        });
+
+        let contents = state
+            .files
+            .get(file_path)
+            .ok_or_else(|| transport::download_status_error(404))?;
+        writer
+            .write_all(contents)
+            .map_err(|e| Error::transport(format!("Failed to write file: {}", e)))?;
+        Ok(contents.len() as u64)
+    }
+}
@@ -0,0 +1,73 @@
+//! Process-wide flood throttle protecting chats from runaway loops.
+//!
+//! Independent of Telegram's own limits: with
+//! `ClientOptions::max_messages_per_minute` set, at most that many messages are
+//! sent per minute by the whole process. What happens to the messages over the
+//! limit is decided by the [`ThrottlePolicy`](crate::ThrottlePolicy); dropped
+//! messages are counted, and the next message that gets through is preceded by
+//! a summary such as "suppressed 37 messages".
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::configuration::ThrottlePolicy;
+use crate::error::Error;
+use crate::time::{self, Instant};
+
+/// Length of the sliding window the limit applies to
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Send times within the window and messages dropped since the last summary
+#[derive(Default)]
+struct State {
+    sent: VecDeque<Instant>,
+    suppressed: u64,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+/// Take a slot for one message, following `policy` when all `limit` slots are taken
+///
+/// Waits never go past `deadline`. Returns the number of messages dropped since
+/// the last message that got through, so the caller can report them.
+pub(crate) async fn acquire(
+    limit: u32,
+    policy: ThrottlePolicy,
+    deadline: Option<Instant>,
+) -> Result<u64, Error> {
+    loop {
+        let now = Instant::now();
+        let wait = {
+            let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+            let state = state.get_or_insert_with(State::default);
+
+            while state
+                .sent
+                .front()
+                .is_some_and(|&sent| now.duration_since(sent) >= WINDOW)
+            {
+                state.sent.pop_front();
+            }
+            if state.sent.len() < limit as usize {
+                state.sent.push_back(now);
+                return Ok(std::mem::take(&mut state.suppressed));
+            }
+
+            // The oldest send leaves the window first
+            let wait = state
+                .sent
+                .front()
+                .map_or(Duration::ZERO, |&sent| WINDOW - now.duration_since(sent));
+            let past_deadline = deadline.is_some_and(|deadline| now + wait > deadline);
+            if policy == ThrottlePolicy::Drop || past_deadline {
+                state.suppressed += 1;
+                return Err(Error::Throttled {
+                    retry_after: wait.as_secs() + u64::from(wait.subsec_nanos() > 0),
+                });
+            }
+            wait
+        };
+        time::sleep(wait).await;
+    }
+}
@@ -0,0 +1,97 @@
+//! Clock and timers of the send pipeline.
+//!
+//! The pipeline runs on native threads as well as in WASM hosts, where
+//! `std::time::Instant::now` and `SystemTime::now` panic and nothing can
+//! sleep: there, the time is read from JavaScript's `Date` and waits are
+//! `setTimeout` promises. With the reqwest backend, waits are tokio timers
+//! that never block the thread polling the send; other builds sleep.
+
+use std::time::{Duration, SystemTime};
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+pub(crate) use std::time::Instant;
+
+/// Point in time for measuring durations, read from `Date.now()`
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Instant(Duration);
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Instant(since_epoch())
+    }
+
+    pub(crate) fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    pub(crate) fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        Self::now().duration_since(*self)
+    }
+}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+impl std::ops::Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration)
+    }
+}
+
+/// Time elapsed since the Unix epoch according to the host
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+fn since_epoch() -> Duration {
+    Duration::from_secs_f64(js_sys::Date::now().max(0.0) / 1000.0)
+}
+
+/// The current wall-clock time
+pub(crate) fn system_now() -> SystemTime {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    return SystemTime::UNIX_EPOCH + since_epoch();
+
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+    SystemTime::now()
+}
+
+/// Wait for `duration` without blocking the executor
+///
+/// The timer belongs to the shared runtime of the reqwest backend, so it
+/// fires whichever executor polls it.
+#[cfg(feature = "reqwest")]
+pub(crate) async fn sleep(duration: Duration) {
+    let timer = {
+        let _runtime = crate::transport::RUNTIME.enter();
+        tokio::time::sleep(duration)
+    };
+    timer.await
+}
+
+/// Wait for `duration` on a `setTimeout` of the host
+#[cfg(all(not(feature = "reqwest"), feature = "wasm", target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    let timer = js_sys::Promise::new(&mut |resolve, _reject| {
+        let global = js_sys::global();
+        let set_timeout = js_sys::Reflect::get(&global, &js_sys::JsString::from("setTimeout"))
+            .map(js_sys::Function::from);
+        let millis = js_sys::Number::from(duration.as_millis() as f64);
+        // Hosts without timers don't wait at all
+        if set_timeout.map_or(true, |set_timeout| {
+            set_timeout.call2(&global, &resolve, &millis).is_err()
+        }) {
+            let _ = resolve.call0(&global);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(timer).await;
+}
+
+/// Wait for `duration` by sleeping the thread, for builds without an async runtime
+#[cfg(not(any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32"))))]
+pub(crate) async fn sleep(duration: Duration) {
+    std::thread::sleep(duration)
+}
@@ -0,0 +1,1232 @@
+#[cfg(feature = "reqwest")]
+use once_cell::sync::Lazy;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Cursor, Read, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+#[cfg(any(feature = "reqwest", feature = "ureq", feature = "wasm"))]
+use tracing::debug;
+use tracing::error;
+
+use crate::configuration::ClientOptions;
+use crate::error::Error;
+use crate::media::{FileReader, InputFile};
+
+/// Base URL of the public Telegram Bot API
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+/// Body of a Bot API request
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RequestBody {
+    /// JSON-encoded parameters
+    Json(serde_json::Value),
+    /// Parameters and uploaded files, sent as `multipart/form-data`
+    Multipart {
+        /// Parameters, one form field each
+        params: serde_json::Value,
+        /// Uploaded files keyed by field name
+        files: Vec<(String, InputFile)>,
+    },
+}
+
+impl RequestBody {
+    /// Encode a multipart body, returning its content type and bytes
+    ///
+    /// String parameters are sent as-is and other values JSON-encoded, as the
+    /// Bot API expects. Null parameters are left out. Files are read into
+    /// memory, reporting their [upload progress](InputFile::with_progress);
+    /// transports streaming the body use [`RequestBody::multipart_reader`].
+    pub fn encode_multipart(
+        params: &serde_json::Value,
+        files: &[(String, InputFile)],
+    ) -> Result<(String, Vec<u8>), Error> {
+        let mut reader = Self::multipart_reader(params, files.to_vec())?;
+        let mut body = Vec::with_capacity(reader.content_length() as usize);
+        reader
+            .read_to_end(&mut body)
+            .map_err(|e| Error::other(format!("Failed to read upload: {}", e)))?;
+
+        Ok((reader.content_type, body))
+    }
+
+    /// Stream a multipart body encoded like [`RequestBody::encode_multipart`]
+    ///
+    /// Files are opened right away and read into the body in chunks (see
+    /// [`InputFile::with_chunk_size`]), reporting their
+    /// [upload progress](InputFile::with_progress).
+    pub fn multipart_reader(
+        params: &serde_json::Value,
+        files: Vec<(String, InputFile)>,
+    ) -> Result<MultipartReader, Error> {
+        let fields: Vec<(&str, String)> = params
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    other => other.to_string(),
+                };
+                (name.as_str(), value)
+            })
+            .collect();
+
+        // Pick a boundary that doesn't occur in any part. Streamed files can't
+        // be searched, a random boundary is as good there.
+        let mut boundary = String::from("telegrama-boundary");
+        if files.iter().any(|(_, file)| file.contents().is_none()) {
+            boundary.push_str(&format!(
+                "-{:016x}",
+                RandomState::new().build_hasher().finish()
+            ));
+        }
+        let contains = |haystack: &[u8], needle: &str| {
+            haystack
+                .windows(needle.len())
+                .any(|window| window == needle.as_bytes())
+        };
+        while fields
+            .iter()
+            .any(|(_, value)| contains(value.as_bytes(), &boundary))
+            || files
+                .iter()
+                .filter_map(|(_, file)| file.contents())
+                .any(|contents| contains(contents, &boundary))
+        {
+            boundary.push('-');
+        }
+
+        let mut encoded_fields = Vec::new();
+        for (name, value) in &fields {
+            encoded_fields.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                    boundary, name, value
+                )
+                .as_bytes(),
+            );
+        }
+        let mut parts = VecDeque::new();
+        parts.push_back(Part::Bytes(Cursor::new(encoded_fields)));
+        for (name, file) in files {
+            let header = format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n",
+                boundary,
+                name,
+                // Quotes and line breaks would end the header
+                file.file_name.replace(['"', '\r', '\n'], "")
+            );
+            parts.push_back(Part::Bytes(Cursor::new(header.into_bytes())));
+            parts.push_back(Part::File {
+                reader: file.open()?,
+                file,
+                sent: 0,
+            });
+            parts.push_back(Part::Bytes(Cursor::new(b"\r\n".to_vec())));
+        }
+        parts.push_back(Part::Bytes(Cursor::new(
+            format!("--{}--\r\n", boundary).into_bytes(),
+        )));
+
+        let content_length = parts
+            .iter()
+            .map(|part| match part {
+                Part::Bytes(bytes) => bytes.get_ref().len() as u64,
+                Part::File { file, .. } => file.size(),
+            })
+            .sum();
+        Ok(MultipartReader {
+            content_type: format!("multipart/form-data; boundary={}", boundary),
+            content_length,
+            parts,
+        })
+    }
+}
+
+/// Default of [`InputFile::with_chunk_size`], the bytes of a file read at a time
+pub const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+enum Part {
+    Bytes(Cursor<Vec<u8>>),
+    File {
+        file: InputFile,
+        reader: FileReader,
+        sent: u64,
+    },
+}
+
+impl Part {
+    /// Most bytes of the part to read into a buffer of `buf_len` bytes
+    fn chunk_len(&self, buf_len: usize) -> usize {
+        match self {
+            Part::Bytes(bytes) => buf_len.min(bytes.get_ref().len() - bytes.position() as usize),
+            Part::File { file, sent, .. } => buf_len
+                .min(file.chunk_size())
+                .min(usize::try_from(file.size() - sent).unwrap_or(usize::MAX)),
+        }
+    }
+}
+
+/// Count `read` bytes of a file part as sent, reporting the upload progress
+///
+/// Reading nothing before the end is an error, the `Content-Length` promised the whole file.
+fn file_read(file: &InputFile, sent: &mut u64, read: usize) -> std::io::Result<usize> {
+    if read == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!(
+                "'{}' ended after {} of {} bytes",
+                file.file_name,
+                sent,
+                file.size()
+            ),
+        ));
+    }
+    *sent += read as u64;
+    file.report_progress(*sent);
+    Ok(read)
+}
+
+/// `multipart/form-data` body read in chunks, see [`RequestBody::multipart_reader`]
+///
+/// Transports send it with a `Content-Length`, so uploads are never buffered
+/// in memory as a whole.
+pub struct MultipartReader {
+    content_type: String,
+    content_length: u64,
+    parts: VecDeque<Part>,
+}
+
+impl MultipartReader {
+    /// `Content-Type` header of the body, including the boundary
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    /// Size of the whole body in bytes
+    pub fn content_length(&self) -> u64 {
+        self.content_length
+    }
+}
+
+impl Read for MultipartReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while let Some(part) = self.parts.front_mut() {
+            let chunk = part.chunk_len(buf.len());
+            let read = match part {
+                Part::Bytes(bytes) => bytes.read(buf)?,
+                Part::File { .. } if chunk == 0 => 0,
+                Part::File { file, reader, sent } => {
+                    let read = reader.read(&mut buf[..chunk])?;
+                    file_read(file, sent, read)?
+                }
+            };
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            self.parts.pop_front();
+        }
+        Ok(0)
+    }
+}
+
+impl std::fmt::Debug for MultipartReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultipartReader")
+            .field("content_type", &self.content_type)
+            .field("content_length", &self.content_length)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Raw HTTP response returned by a [`Transport`]
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response body as text
+    pub body: String,
+}
+
+impl RawResponse {
+    /// Whether the status code is in the 2xx range
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Pending Bot API request of a [`Transport`], see [`Transport::execute_async`]
+#[cfg(not(target_arch = "wasm32"))]
+pub type ResponseFuture<'a> = Pin<Box<dyn Future<Output = Result<RawResponse, Error>> + Send + 'a>>;
+
+/// Pending Bot API request of a [`Transport`], see [`Transport::execute_async`]
+///
+/// Requests are JavaScript promises on WASM targets, so the future isn't `Send`.
+#[cfg(target_arch = "wasm32")]
+pub type ResponseFuture<'a> = Pin<Box<dyn Future<Output = Result<RawResponse, Error>> + 'a>>;
+
+/// HTTP layer used by [`crate::Client`] to reach the Telegram Bot API
+///
+/// Implement this to route requests through hyper, ureq, a corporate HTTP
+/// gateway or a test double. Implementations only move bytes: status
+/// handling and JSON parsing happen in the client.
+pub trait Transport: Send + Sync {
+    /// Execute a Bot API `method` (e.g. `sendMessage`) on behalf of the bot owning `bot_token`
+    fn execute(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+    ) -> Result<RawResponse, Error>;
+
+    /// Execute a Bot API method with a total `timeout` replacing the transport's own
+    ///
+    /// The default implementation ignores the timeout and calls [`Transport::execute`].
+    fn execute_with_timeout(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Duration,
+    ) -> Result<RawResponse, Error> {
+        let _ = timeout;
+        self.execute(bot_token, method, body)
+    }
+
+    /// Execute a Bot API method without blocking, with a total `timeout` replacing the transport's own if given
+    ///
+    /// Clients send through this method, [`crate::Client`] by waiting for it and
+    /// [`crate::AsyncClient`] by awaiting it. The default implementation makes
+    /// the blocking call of [`Transport::execute_with_timeout`] or
+    /// [`Transport::execute`] when polled; transports with an async HTTP
+    /// stack override it.
+    fn execute_async<'a>(
+        &'a self,
+        bot_token: &'a str,
+        method: &'a str,
+        body: RequestBody,
+        timeout: Option<Duration>,
+    ) -> ResponseFuture<'a> {
+        Box::pin(async move {
+            match timeout {
+                Some(timeout) => self.execute_with_timeout(bot_token, method, body, timeout),
+                None => self.execute(bot_token, method, body),
+            }
+        })
+    }
+
+    /// Whether requests go to a local Bot API server sharing this machine's file system
+    ///
+    /// Such a server (started with `--local`) opens files passed as `file://`
+    /// URIs itself, so [local files](crate::InputFile::local) aren't uploaded.
+    /// The default implementation says no.
+    fn is_local_server(&self) -> bool {
+        false
+    }
+
+    /// Download `file_path` (as returned by `getFile`) into `writer`, returning the byte count
+    ///
+    /// Files are served from `/file/bot<token>/<file_path>`. The default
+    /// implementation reports that the transport can't download files.
+    fn download(
+        &self,
+        bot_token: &str,
+        file_path: &str,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Error> {
+        let _ = (bot_token, file_path, writer);
+        Err(Error::transport(
+            "File downloads are not supported by this transport",
+        ))
+    }
+}
+
+/// Error for a download answered with a non-2xx status
+pub(crate) fn download_status_error(status: u16) -> Error {
+    error!(status, "File download failed");
+    Error::api(format!("HTTP error (status {}) downloading file", status))
+}
+
+/// Create the transport of the preferred enabled backend (reqwest, then ureq)
+///
+/// Without an enabled backend the transport fails every request; such builds
+/// create clients with [`crate::Client::with_transport`], or send with
+/// [`crate::AsyncClient`] on WASM targets (`wasm` feature).
+pub fn default_transport(options: &ClientOptions) -> Result<Arc<dyn Transport>, Error> {
+    #[cfg(feature = "reqwest")]
+    return Ok(Arc::new(ReqwestTransport::from_options(options)?));
+
+    #[cfg(all(not(feature = "reqwest"), feature = "ureq"))]
+    return Ok(Arc::new(UreqTransport::from_options(options)?));
+
+    #[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+    {
+        let _ = options;
+        Ok(Arc::new(NoBackend))
+    }
+}
+
+/// Stand-in transport of builds without an HTTP backend
+#[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+#[derive(Debug)]
+struct NoBackend;
+
+#[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+impl Transport for NoBackend {
+    fn execute(
+        &self,
+        _bot_token: &str,
+        _method: &str,
+        _body: RequestBody,
+    ) -> Result<RawResponse, Error> {
+        Err(Error::configuration(
+            "No HTTP backend enabled: enable the `reqwest` or `ureq` feature, \
+             or create the client with Client::with_transport",
+        ))
+    }
+}
+
+/// Default transports shared by all clients, keyed by the options they were built for
+///
+/// Processes only ever use a handful of distinct options, so a list is enough.
+static SHARED_TRANSPORTS: Mutex<Vec<(ClientOptions, Arc<dyn Transport>)>> = Mutex::new(Vec::new());
+
+/// Get the shared default transport for the given options
+///
+/// The transport (and its connection pool) is reused across clients and sends
+/// with the same options, so repeated sends keep connections alive.
+pub fn shared_transport(options: &ClientOptions) -> Result<Arc<dyn Transport>, Error> {
+    let mut shared = SHARED_TRANSPORTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if let Some((_, transport)) = shared
+        .iter()
+        .find(|(shared_options, _)| shared_options == options)
+    {
+        return Ok(Arc::clone(transport));
+    }
+
+    let transport = default_transport(options)?;
+    shared.push((options.clone(), Arc::clone(&transport)));
+    Ok(transport)
+}
+
+/// Async reqwest transport, the core of [`ReqwestTransport`] and [`crate::AsyncClient`]
+///
+/// Async code can send through it directly instead of blocking a task on
+/// [`ReqwestTransport`]; both share the connection pool of the client they
+/// were built with. With the `wasm` feature it builds for
+/// `wasm32-unknown-unknown`, where reqwest sends requests with `fetch`.
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+#[derive(Debug, Clone)]
+pub struct AsyncReqwestTransport {
+    client: reqwest::Client,
+    base_url: String,
+    local_server: bool,
+}
+
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl Default for AsyncReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl AsyncReqwestTransport {
+    /// Create a transport with a 10 second connect and 30 second request timeout
+    ///
+    /// `fetch` has no timeouts, so WASM targets wait as long as the host allows.
+    pub fn new() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        #[cfg(target_arch = "wasm32")]
+        let client = reqwest::Client::new();
+
+        Self::with_client(client)
+    }
+
+    /// Create a transport honoring the timeout, pool and TLS settings of [`ClientOptions`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_options(options: &ClientOptions) -> Result<Self, Error> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(options.connect_timeout))
+            .timeout(Duration::from_secs(options.request_timeout))
+            .pool_idle_timeout(options.pool_idle_timeout.map(Duration::from_secs))
+            .tcp_keepalive(options.tcp_keepalive.map(Duration::from_secs))
+            .tls_built_in_root_certs(options.tls_built_in_root_certs)
+            .danger_accept_invalid_certs(options.danger_accept_invalid_certs);
+
+        if let Some(max_idle) = options.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+
+        for path in &options.root_certificates {
+            let pem = std::fs::read(path).map_err(|e| {
+                Error::configuration(format!(
+                    "Failed to read root certificate '{}': {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| {
+                Error::configuration(format!(
+                    "Invalid root certificate '{}': {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| Error::configuration(format!("Failed to build HTTP client: {}", e)))?;
+        Ok(Self::with_client(client))
+    }
+
+    /// Create a transport for [`ClientOptions`]
+    ///
+    /// `fetch` leaves timeouts, pooling and TLS to the host, so the options
+    /// are ignored on WASM targets.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_options(options: &ClientOptions) -> Result<Self, Error> {
+        let _ = options;
+        Ok(Self::new())
+    }
+
+    /// Create a transport around an existing reqwest client
+    pub fn with_client(client: reqwest::Client) -> Self {
+        AsyncReqwestTransport {
+            client,
+            base_url: DEFAULT_API_URL.to_string(),
+            local_server: false,
+        }
+    }
+
+    /// Send requests to another Bot API server, e.g. a gateway or a local one
+    ///
+    /// Files are still uploaded; call [`AsyncReqwestTransport::with_local_server`]
+    /// if the server shares this machine's file system.
+    pub fn with_base_url<S: AsRef<str>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Mark the server as a local Bot API server, see [`Transport::is_local_server`]
+    pub fn with_local_server(mut self, local_server: bool) -> Self {
+        self.local_server = local_server;
+        self
+    }
+
+    /// Whether requests go to a local Bot API server, see [`Transport::is_local_server`]
+    pub fn is_local_server(&self) -> bool {
+        self.local_server
+    }
+
+    /// Execute a Bot API `method`, with a total `timeout` replacing the client's own if given
+    ///
+    /// Multipart bodies are streamed from their [reader](RequestBody::multipart_reader)
+    /// by the `reqwest` backend and [encoded](RequestBody::encode_multipart)
+    /// up front on WASM targets, which also ignore the timeout.
+    pub async fn execute(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Option<Duration>,
+    ) -> Result<RawResponse, Error> {
+        let url = format!("{}/bot{}/{}", self.base_url, bot_token, method);
+
+        let request = match body {
+            RequestBody::Json(params) => self.client.post(&url).json(&params),
+            #[cfg(feature = "reqwest")]
+            RequestBody::Multipart { params, files } => {
+                let body = RequestBody::multipart_reader(&params, files)?;
+                let content_type = body.content_type().to_string();
+                let content_length = body.content_length();
+                self.client
+                    .post(&url)
+                    .header(reqwest::header::CONTENT_TYPE, content_type)
+                    .header(reqwest::header::CONTENT_LENGTH, content_length)
+                    .body(reqwest::Body::wrap_stream(MultipartStream::new(body)))
+            }
+            #[cfg(not(feature = "reqwest"))]
+            RequestBody::Multipart { params, files } => {
+                let (content_type, body) = RequestBody::encode_multipart(&params, &files)?;
+                self.client
+                    .post(&url)
+                    .header(reqwest::header::CONTENT_TYPE, content_type)
+                    .body(body)
+            }
+        };
+        let request = with_timeout(request, timeout);
+
+        // The URL contains the bot token, so it is stripped from errors
+        let response = request.send().await.map_err(|e| {
+            let e = e.without_url();
+            error!(error = %e, "HTTP request failed");
+            Error::Http(e)
+        })?;
+
+        let status = response.status().as_u16();
+        debug!(status, "Received HTTP response");
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::Http(e.without_url()))?;
+        Ok(RawResponse { status, body })
+    }
+
+    /// Download `file_path` (as returned by `getFile`) into `writer`, returning the byte count
+    ///
+    /// The file is written chunk by chunk as it arrives, or at once on WASM
+    /// targets, where `fetch` responses can't be read in chunks.
+    pub async fn download<W: Write + ?Sized>(
+        &self,
+        bot_token: &str,
+        file_path: &str,
+        writer: &mut W,
+    ) -> Result<u64, Error> {
+        let url = format!("{}/file/bot{}/{}", self.base_url, bot_token, file_path);
+
+        // The URL contains the bot token, so it is stripped from errors
+        #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+        let mut response = self.client.get(&url).send().await.map_err(|e| {
+            let e = e.without_url();
+            error!(error = %e, "File download failed");
+            Error::Http(e)
+        })?;
+
+        let status = response.status().as_u16();
+        debug!(status, "Received file response");
+        if !response.status().is_success() {
+            return Err(download_status_error(status));
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let contents = response
+                .bytes()
+                .await
+                .map_err(|e| Error::Http(e.without_url()))?;
+            writer
+                .write_all(&contents)
+                .map_err(|e| Error::transport(format!("Failed to write file: {}", e)))?;
+            Ok(contents.len() as u64)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut size = 0;
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| Error::Http(e.without_url()))?
+            {
+                writer
+                    .write_all(&chunk)
+                    .map_err(|e| Error::transport(format!("Failed to write file: {}", e)))?;
+                size += chunk.len() as u64;
+            }
+            Ok(size)
+        }
+    }
+}
+
+/// Apply a per-request `timeout`; `fetch` has none, so WASM targets ignore it
+#[cfg(all(
+    any(feature = "reqwest", feature = "wasm"),
+    not(target_arch = "wasm32")
+))]
+fn with_timeout(
+    request: reqwest::RequestBuilder,
+    timeout: Option<Duration>,
+) -> reqwest::RequestBuilder {
+    match timeout {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    }
+}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+fn with_timeout(
+    request: reqwest::RequestBuilder,
+    timeout: Option<Duration>,
+) -> reqwest::RequestBuilder {
+    let _ = timeout;
+    request
+}
+
+/// A blocking read of a [`MultipartStream`] body, handing the body back with the chunk read
+#[cfg(feature = "reqwest")]
+type BlockingRead = tokio::task::JoinHandle<(MultipartReader, std::io::Result<Vec<u8>>)>;
+
+/// [`MultipartReader`] read in chunks, the body of streamed uploads
+///
+/// Chunks are sized by the [chunk size](InputFile::with_chunk_size) of their
+/// file. Files on disk and blocking readers are read on tokio's blocking
+/// pool, async readers are polled, and contents in memory are copied directly.
+#[cfg(feature = "reqwest")]
+struct MultipartStream {
+    /// `None` while a blocking read of it is in flight
+    body: Option<MultipartReader>,
+    reading: Option<BlockingRead>,
+}
+
+#[cfg(feature = "reqwest")]
+impl MultipartStream {
+    fn new(body: MultipartReader) -> Self {
+        MultipartStream {
+            body: Some(body),
+            reading: None,
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl futures_core::Stream for MultipartStream {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::task::Poll;
+
+        let this = self.get_mut();
+        loop {
+            if let Some(reading) = &mut this.reading {
+                let Poll::Ready(done) = std::pin::Pin::new(reading).poll(cx) else {
+                    return Poll::Pending;
+                };
+                this.reading = None;
+                let (body, chunk) = done.map_err(std::io::Error::other)?;
+                this.body = Some(body);
+                return Poll::Ready(
+                    chunk
+                        .map(|chunk| (!chunk.is_empty()).then_some(chunk))
+                        .transpose(),
+                );
+            }
+
+            let Some(body) = &mut this.body else {
+                return Poll::Ready(None);
+            };
+            let Some(part) = body.parts.front_mut() else {
+                return Poll::Ready(None);
+            };
+            let len = part.chunk_len(usize::MAX);
+            if len == 0 {
+                body.parts.pop_front();
+                continue;
+            }
+            let mut chunk = vec![0; len];
+            match part {
+                Part::Bytes(_)
+                | Part::File {
+                    reader: FileReader::Memory(_),
+                    ..
+                } => {
+                    let read = body.read(&mut chunk)?;
+                    chunk.truncate(read);
+                    return Poll::Ready((read > 0).then_some(Ok(chunk)));
+                }
+                Part::File {
+                    reader: FileReader::Async(reader),
+                    file,
+                    sent,
+                } => {
+                    let mut buf = tokio::io::ReadBuf::new(&mut chunk);
+                    let Poll::Ready(polled) = reader.as_mut().poll_read(cx, &mut buf) else {
+                        return Poll::Pending;
+                    };
+                    polled?;
+                    let read = buf.filled().len();
+                    file_read(file, sent, read)?;
+                    chunk.truncate(read);
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+                Part::File {
+                    reader: FileReader::Blocking(_),
+                    ..
+                } => {
+                    let Some(mut body) = this.body.take() else {
+                        return Poll::Ready(None);
+                    };
+                    this.reading = Some(tokio::task::spawn_blocking(move || {
+                        let read = body.read(&mut chunk).map(|read| {
+                            chunk.truncate(read);
+                            chunk
+                        });
+                        (body, read)
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Blocking calls wait for the request on the calling thread
+///
+/// WASM targets can't block, so there they fail and only
+/// [`Transport::execute_async`] sends, as [`crate::AsyncClient`] does.
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl Transport for AsyncReqwestTransport {
+    fn execute(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+    ) -> Result<RawResponse, Error> {
+        self.execute_blocking(bot_token, method, body, None)
+    }
+
+    fn execute_with_timeout(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Duration,
+    ) -> Result<RawResponse, Error> {
+        self.execute_blocking(bot_token, method, body, Some(timeout))
+    }
+
+    fn execute_async<'a>(
+        &'a self,
+        bot_token: &'a str,
+        method: &'a str,
+        body: RequestBody,
+        timeout: Option<Duration>,
+    ) -> ResponseFuture<'a> {
+        Box::pin(AsyncReqwestTransport::execute(
+            self, bot_token, method, body, timeout,
+        ))
+    }
+
+    fn is_local_server(&self) -> bool {
+        self.local_server
+    }
+
+    #[cfg(feature = "reqwest")]
+    fn download(
+        &self,
+        bot_token: &str,
+        file_path: &str,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Error> {
+        block_on(AsyncReqwestTransport::download(
+            self, bot_token, file_path, writer,
+        ))
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "wasm"))]
+impl AsyncReqwestTransport {
+    /// Wait for a request on the calling thread, see [`block_on`]
+    #[cfg(feature = "reqwest")]
+    fn execute_blocking(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Option<Duration>,
+    ) -> Result<RawResponse, Error> {
+        block_on(AsyncReqwestTransport::execute(
+            self, bot_token, method, body, timeout,
+        ))
+    }
+
+    /// Blocking requests would need the runtime of the reqwest backend
+    #[cfg(not(feature = "reqwest"))]
+    fn execute_blocking(
+        &self,
+        _bot_token: &str,
+        _method: &str,
+        _body: RequestBody,
+        _timeout: Option<Duration>,
+    ) -> Result<RawResponse, Error> {
+        Err(Error::configuration(
+            "Blocking requests need the `reqwest` feature, send with AsyncClient instead",
+        ))
+    }
+}
+
+/// Default [`Transport`], blocking the caller on an [`AsyncReqwestTransport`]
+///
+/// Requests run on a small runtime shared by all reqwest transports while the
+/// calling thread waits for them. Inside a multi-threaded tokio runtime (e.g.
+/// in an axum handler) the waiting worker hands its other tasks over to the
+/// rest of the runtime first; a current-thread runtime pauses until the
+/// request is done.
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    inner: AsyncReqwestTransport,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    /// Create a transport with a 10 second connect and 30 second request timeout
+    pub fn new() -> Self {
+        Self::from(AsyncReqwestTransport::new())
+    }
+
+    /// Create a transport honoring the timeout, pool and TLS settings of [`ClientOptions`]
+    pub fn from_options(options: &ClientOptions) -> Result<Self, Error> {
+        AsyncReqwestTransport::from_options(options).map(Self::from)
+    }
+
+    /// Create a transport around an existing reqwest client
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self::from(AsyncReqwestTransport::with_client(client))
+    }
+
+    /// Send requests to another Bot API server, e.g. a gateway or a local one
+    ///
+    /// Files are still uploaded; call [`ReqwestTransport::with_local_server`]
+    /// if the server shares this machine's file system.
+    pub fn with_base_url<S: AsRef<str>>(self, base_url: S) -> Self {
+        Self::from(self.inner.with_base_url(base_url))
+    }
+
+    /// Mark the server as a local Bot API server, see [`Transport::is_local_server`]
+    pub fn with_local_server(self, local_server: bool) -> Self {
+        Self::from(self.inner.with_local_server(local_server))
+    }
+
+    /// The async transport requests are made with, for sending from async code
+    pub fn as_async(&self) -> &AsyncReqwestTransport {
+        &self.inner
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<AsyncReqwestTransport> for ReqwestTransport {
+    fn from(inner: AsyncReqwestTransport) -> Self {
+        ReqwestTransport { inner }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn execute(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+    ) -> Result<RawResponse, Error> {
+        Transport::execute(&self.inner, bot_token, method, body)
+    }
+
+    fn execute_with_timeout(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Duration,
+    ) -> Result<RawResponse, Error> {
+        self.inner
+            .execute_with_timeout(bot_token, method, body, timeout)
+    }
+
+    fn execute_async<'a>(
+        &'a self,
+        bot_token: &'a str,
+        method: &'a str,
+        body: RequestBody,
+        timeout: Option<Duration>,
+    ) -> ResponseFuture<'a> {
+        self.inner.execute_async(bot_token, method, body, timeout)
+    }
+
+    fn is_local_server(&self) -> bool {
+        self.inner.is_local_server()
+    }
+
+    fn download(
+        &self,
+        bot_token: &str,
+        file_path: &str,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Error> {
+        Transport::download(&self.inner, bot_token, file_path, writer)
+    }
+}
+
+/// Runtime driving the sockets and timers of blocking callers, shared by all reqwest transports
+///
+/// It is never shut down, so pooled connections stay usable for the whole process.
+#[cfg(feature = "reqwest")]
+pub(crate) static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("telegrama-http")
+        .enable_all()
+        .build()
+        .expect("Failed to start the HTTP runtime")
+});
+
+/// Wakes a thread parked in [`block_on`]
+#[cfg(not(target_arch = "wasm32"))]
+struct ThreadWaker(std::thread::Thread);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::task::Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll `future` to completion on the calling thread, parking it while the future waits
+#[cfg(not(target_arch = "wasm32"))]
+fn park_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = std::task::Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = std::task::Context::from_waker(&waker);
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+/// Poll `future` once; WASM hosts only make progress once control returns to them
+#[cfg(target_arch = "wasm32")]
+fn park_on<F: Future>(future: F) -> F::Output {
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    match std::pin::pin!(future).poll(&mut cx) {
+        std::task::Poll::Ready(output) => output,
+        std::task::Poll::Pending => {
+            panic!("Blocking calls can't wait on WASM targets, send with AsyncClient instead")
+        }
+    }
+}
+
+/// Run `future` to completion on the calling thread, from any thread
+///
+/// This is how blocking calls wait for the async send pipeline and requests.
+/// Sockets and timers are driven by [`RUNTIME`], so this works outside of
+/// tokio and inside either kind of runtime; on a multi-threaded runtime the
+/// worker hands its other tasks off while it waits.
+#[cfg(feature = "reqwest")]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let run = || {
+        let _runtime = RUNTIME.enter();
+        park_on(future)
+    };
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(run)
+        }
+        _ => run(),
+    }
+}
+
+/// Run `future` to completion on the calling thread
+///
+/// Without the reqwest backend, the futures of blocking calls only wait for
+/// blocking transports and [sleeps](crate::time), so nothing needs a runtime.
+#[cfg(not(feature = "reqwest"))]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    park_on(future)
+}
+
+/// Lightweight [`Transport`] backed by ureq, enabled with the `ureq` feature
+#[cfg(feature = "ureq")]
+#[derive(Debug, Clone)]
+pub struct UreqTransport {
+    agent: ureq::Agent,
+    base_url: String,
+    local_server: bool,
+}
+
+#[cfg(feature = "ureq")]
+impl Default for UreqTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "ureq")]
+impl UreqTransport {
+    /// Create a transport with a 10 second connect and 30 second request timeout
+    pub fn new() -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        Self::with_agent(agent)
+    }
+
+    /// Create a transport honoring the timeouts and pool size of [`ClientOptions`]
+    ///
+    /// Custom TLS settings are not supported by this backend; configure a
+    /// rustls client on an agent and use [`UreqTransport::with_agent`] instead.
+    /// `pool_idle_timeout` and `tcp_keepalive` are ignored.
+    pub fn from_options(options: &ClientOptions) -> Result<Self, Error> {
+        let defaults = ClientOptions::default();
+        if !options.root_certificates.is_empty()
+            || options.tls_built_in_root_certs != defaults.tls_built_in_root_certs
+            || options.danger_accept_invalid_certs
+        {
+            return Err(Error::configuration(
+                "Custom TLS options are not supported by the ureq backend",
+            ));
+        }
+
+        let mut builder = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(options.connect_timeout))
+            .timeout(Duration::from_secs(options.request_timeout));
+        if let Some(max_idle) = options.pool_max_idle_per_host {
+            builder = builder.max_idle_connections_per_host(max_idle);
+        }
+        Ok(Self::with_agent(builder.build()))
+    }
+
+    /// Create a transport around an existing ureq agent
+    pub fn with_agent(agent: ureq::Agent) -> Self {
+        UreqTransport {
+            agent,
+            base_url: DEFAULT_API_URL.to_string(),
+            local_server: false,
+        }
+    }
+
+    /// Send requests to another Bot API server, e.g. a gateway or a local one
+    ///
+    /// Files are still uploaded; call [`UreqTransport::with_local_server`]
+    /// if the server shares this machine's file system.
+    pub fn with_base_url<S: AsRef<str>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Mark the server as a local Bot API server, see [`Transport::is_local_server`]
+    pub fn with_local_server(mut self, local_server: bool) -> Self {
+        self.local_server = local_server;
+        self
+    }
+}
+
+#[cfg(feature = "ureq")]
+impl UreqTransport {
+    fn send(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Option<Duration>,
+    ) -> Result<RawResponse, Error> {
+        let url = format!("{}/bot{}/{}", self.base_url, bot_token, method);
+
+        let mut request = self.agent.post(&url);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+
+        let result = match body {
+            RequestBody::Json(params) => request
+                .set("Content-Type", "application/json")
+                .send_string(&params.to_string()),
+            RequestBody::Multipart { params, files } => {
+                let body = RequestBody::multipart_reader(&params, files)?;
+                request
+                    .set("Content-Type", body.content_type())
+                    .set("Content-Length", &body.content_length().to_string())
+                    .send(body)
+            }
+        };
+
+        // ureq reports 4xx/5xx as errors, but Telegram puts useful JSON in those bodies
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(e)) => {
+                // The URL contains the bot token, so it is masked in errors
+                let message = crate::secret::redact(&e.to_string(), bot_token);
+                error!(error = %message, "HTTP request failed");
+                return Err(Error::transport(message));
+            }
+        };
+
+        let status = response.status();
+        debug!(status, "Received HTTP response");
+
+        let body = response
+            .into_string()
+            .map_err(|e| Error::transport(format!("Failed to read response body: {}", e)))?;
+        Ok(RawResponse { status, body })
+    }
+}
+
+#[cfg(feature = "ureq")]
+impl Transport for UreqTransport {
+    fn execute(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+    ) -> Result<RawResponse, Error> {
+        self.send(bot_token, method, body, None)
+    }
+
+    fn execute_with_timeout(
+        &self,
+        bot_token: &str,
+        method: &str,
+        body: RequestBody,
+        timeout: Duration,
+    ) -> Result<RawResponse, Error> {
+        self.send(bot_token, method, body, Some(timeout))
+    }
+
+    fn is_local_server(&self) -> bool {
+        self.local_server
+    }
+
+    fn download(
+        &self,
+        bot_token: &str,
+        file_path: &str,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Error> {
+        let url = format!("{}/file/bot{}/{}", self.base_url, bot_token, file_path);
+
+        let response = match self.agent.get(&url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, _)) => return Err(download_status_error(status)),
+            Err(ureq::Error::Transport(e)) => {
+                // The URL contains the bot token, so it is masked in errors
+                let message = crate::secret::redact(&e.to_string(), bot_token);
+                error!(error = %message, "File download failed");
+                return Err(Error::transport(message));
+            }
+        };
+        debug!(status = response.status(), "Received file response");
+
+        std::io::copy(&mut response.into_reader(), writer)
+            .map_err(|e| Error::transport(format!("Failed to read file: {}", e)))
+    }
+}
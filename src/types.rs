@@ -0,0 +1,373 @@
+//! Typed models of the Bot API objects bots receive.
+//!
+//! Only the commonly used fields are typed. [`Update`] and [`Message`] keep
+//! everything else in their `extra` map, so no data is lost when Telegram adds
+//! fields or update types.
+
+use serde::{Deserialize, Serialize};
+
+use crate::payments::{PreCheckoutQuery, ShippingQuery};
+
+/// Untyped remaining fields of an object
+pub type Extra = serde_json::Map<String, serde_json::Value>;
+
+/// A Telegram user or bot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct User {
+    /// Unique identifier
+    pub id: i64,
+    /// Whether the user is a bot
+    #[serde(default)]
+    pub is_bot: bool,
+    /// First name
+    pub first_name: String,
+    /// Last name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    /// Username, without the `@`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// IETF language tag of the user's client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_code: Option<String>,
+}
+
+/// A private chat, group, supergroup or channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chat {
+    /// Unique identifier
+    pub id: i64,
+    /// `private`, `group`, `supergroup` or `channel`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Title of groups and channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Username of private chats, supergroups and channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// First name of the other party in a private chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    /// Last name of the other party in a private chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+}
+
+/// A special entity in a text (command, link, bold text, ...)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageEntity {
+    /// Entity type, e.g. `bot_command`, `url` or `bold`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Offset in UTF-16 code units
+    pub offset: usize,
+    /// Length in UTF-16 code units
+    pub length: usize,
+    /// URL opened by a `text_link`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Mentioned user of a `text_mention`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    /// Programming language of a `pre`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// A message received by the bot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    /// Identifier inside the chat
+    pub message_id: i64,
+    /// Thread of a forum topic the message belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_thread_id: Option<i64>,
+    /// Sender, empty for messages sent to channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<User>,
+    /// Sender chat, for messages sent on behalf of a chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_chat: Option<Chat>,
+    /// Unix time the message was sent
+    pub date: i64,
+    /// Chat the message belongs to
+    pub chat: Chat,
+    /// Message this one replies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to_message: Option<Box<Message>>,
+    /// Text of text messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Entities of the text
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<MessageEntity>,
+    /// Caption of media messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// Entities of the caption
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caption_entities: Vec<MessageEntity>,
+    /// Remaining fields (media, service message data, ...)
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// A press of an inline keyboard button
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallbackQuery {
+    /// Identifier to pass to [`Client::answer_callback_query`](crate::Client::answer_callback_query)
+    pub id: String,
+    /// User who pressed the button
+    pub from: User,
+    /// Message with the button, if sent by the bot and not too old
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<Message>,
+    /// Identifier of the inline message with the button
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline_message_id: Option<String>,
+    /// Identifier of the chat the message with the button was sent to
+    pub chat_instance: String,
+    /// `callback_data` of the button
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// Short name of the game to launch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_short_name: Option<String>,
+}
+
+/// Status of a chat member
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatMemberStatus {
+    /// Owner of the chat
+    Creator,
+    /// Administrator
+    Administrator,
+    /// Regular member
+    Member,
+    /// Member with restrictions
+    Restricted,
+    /// Not a member anymore
+    Left,
+    /// Banned
+    Kicked,
+}
+
+/// A member of a chat
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMember {
+    /// Membership status
+    pub status: ChatMemberStatus,
+    /// The member
+    pub user: User,
+    /// Status-specific fields (permissions, custom title, ban date, ...)
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+impl ChatMember {
+    /// Whether the member is the owner or an administrator of the chat
+    pub fn is_admin(&self) -> bool {
+        matches!(
+            self.status,
+            ChatMemberStatus::Creator | ChatMemberStatus::Administrator
+        )
+    }
+
+    /// Whether the member is currently in the chat, possibly with restrictions
+    pub fn is_member(&self) -> bool {
+        match self.status {
+            ChatMemberStatus::Creator
+            | ChatMemberStatus::Administrator
+            | ChatMemberStatus::Member => true,
+            // Restricted users may have left, Telegram tells with `is_member`
+            ChatMemberStatus::Restricted => self
+                .extra
+                .get("is_member")
+                .and_then(|is_member| is_member.as_bool())
+                .unwrap_or(true),
+            ChatMemberStatus::Left | ChatMemberStatus::Kicked => false,
+        }
+    }
+}
+
+/// A change of a chat member's status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMemberUpdated {
+    /// Chat the member belongs to
+    pub chat: Chat,
+    /// User who made the change
+    pub from: User,
+    /// Unix time of the change
+    pub date: i64,
+    /// Member before the change
+    pub old_chat_member: ChatMember,
+    /// Member after the change
+    pub new_chat_member: ChatMember,
+}
+
+/// A reaction to a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReactionType {
+    /// A standard emoji
+    Emoji {
+        /// The emoji
+        emoji: String,
+    },
+    /// A custom emoji
+    CustomEmoji {
+        /// Identifier of the custom emoji
+        custom_emoji_id: String,
+    },
+    /// A paid reaction
+    Paid,
+    /// A reaction type this library doesn't know yet
+    #[serde(other)]
+    Unknown,
+}
+
+/// A topic of a forum supergroup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForumTopic {
+    /// Identifier of the topic, the `message_thread_id` of its messages
+    pub message_thread_id: i64,
+    /// Name of the topic
+    pub name: String,
+    /// Color of the topic icon in RGB format
+    pub icon_color: u32,
+    /// Custom emoji shown as the topic icon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_custom_emoji_id: Option<String>,
+}
+
+/// Colors Telegram accepts for the icon of a new forum topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TopicIconColor {
+    /// `0x6FB9F0`
+    Blue,
+    /// `0xFFD67E`
+    Yellow,
+    /// `0xCB86DB`
+    Violet,
+    /// `0x8EEE98`
+    Green,
+    /// `0xFF93B2`
+    Rose,
+    /// `0xFB6F5F`
+    Red,
+}
+
+impl TopicIconColor {
+    /// Color in RGB format
+    pub fn rgb(self) -> u32 {
+        match self {
+            TopicIconColor::Blue => 0x6FB9F0,
+            TopicIconColor::Yellow => 0xFFD67E,
+            TopicIconColor::Violet => 0xCB86DB,
+            TopicIconColor::Green => 0x8EEE98,
+            TopicIconColor::Rose => 0xFF93B2,
+            TopicIconColor::Red => 0xFB6F5F,
+        }
+    }
+}
+
+/// A change of a user's reactions to a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageReactionUpdated {
+    /// Chat of the message
+    pub chat: Chat,
+    /// Identifier of the message
+    pub message_id: i64,
+    /// User who changed the reactions, unless anonymous
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    /// Chat on behalf of which an anonymous reaction was changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_chat: Option<Chat>,
+    /// Unix time of the change
+    pub date: i64,
+    /// Reactions before the change
+    #[serde(default)]
+    pub old_reaction: Vec<ReactionType>,
+    /// Reactions after the change
+    #[serde(default)]
+    pub new_reaction: Vec<ReactionType>,
+}
+
+/// An update sent by Telegram (new message, button press, ...)
+///
+/// At most one of the optional fields is set. Update types without a typed
+/// field end up in `extra`, keyed by their name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Update {
+    /// Sequential identifier of the update
+    pub update_id: i64,
+    /// New message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<Message>,
+    /// Edited message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_message: Option<Message>,
+    /// New channel post
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_post: Option<Message>,
+    /// Edited channel post
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_channel_post: Option<Message>,
+    /// Inline keyboard button press
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_query: Option<CallbackQuery>,
+    /// Change of the bot's own membership
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub my_chat_member: Option<ChatMemberUpdated>,
+    /// Change of another member's status (requires `allowed_updates`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_member: Option<ChatMemberUpdated>,
+    /// Change of reactions to a message (requires `allowed_updates`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_reaction: Option<MessageReactionUpdated>,
+    /// Request for shipping methods of a flexible invoice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shipping_query: Option<ShippingQuery>,
+    /// Confirmation request before a payment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_checkout_query: Option<PreCheckoutQuery>,
+    /// Other update types
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+impl Update {
+    /// Name of the update type, e.g. `"message"` or `"callback_query"`
+    pub fn kind(&self) -> Option<&str> {
+        let typed = [
+            ("message", self.message.is_some()),
+            ("edited_message", self.edited_message.is_some()),
+            ("channel_post", self.channel_post.is_some()),
+            ("edited_channel_post", self.edited_channel_post.is_some()),
+            ("callback_query", self.callback_query.is_some()),
+            ("my_chat_member", self.my_chat_member.is_some()),
+            ("chat_member", self.chat_member.is_some()),
+            ("message_reaction", self.message_reaction.is_some()),
+            ("shipping_query", self.shipping_query.is_some()),
+            ("pre_checkout_query", self.pre_checkout_query.is_some()),
+        ];
+
+        typed
+            .iter()
+            .find(|(_, present)| *present)
+            .map(|(kind, _)| *kind)
+            .or_else(|| self.extra.keys().next().map(String::as_str))
+    }
+
+    /// The new, edited or channel message carried by the update
+    pub fn any_message(&self) -> Option<&Message> {
+        self.message
+            .as_ref()
+            .or(self.edited_message.as_ref())
+            .or(self.channel_post.as_ref())
+            .or(self.edited_channel_post.as_ref())
+    }
+}
@@ -0,0 +1,20 @@
+//! Incoming updates and the handler trait shared by the ways of receiving them.
+
+pub use crate::types::Update;
+
+/// Receives updates, whichever way they reach the application
+///
+/// Implemented for closures taking an [`Update`].
+pub trait UpdateHandler: Send + Sync {
+    /// Handle one update
+    fn handle(&self, update: Update);
+}
+
+impl<F> UpdateHandler for F
+where
+    F: Fn(Update) + Send + Sync,
+{
+    fn handle(&self, update: Update) {
+        self(update)
+    }
+}
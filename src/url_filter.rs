@@ -0,0 +1,168 @@
+//! Filtering and rewriting of URLs in messages.
+//!
+//! Set a [`UrlFilter`] as `url_filter` of the
+//! [`FormattingOptions`](crate::FormattingOptions) to strip tracking parameters
+//! and keep links to unwanted domains out of chats:
+//!
+//! ```
+//! use telegrama_rs::url_filter::{DeniedUrlAction, UrlFilter};
+//!
+//! let filter = UrlFilter {
+//!     strip_tracking_params: true,
+//!     denied_domains: vec!["internal.example.com".to_string()],
+//!     denied_action: DeniedUrlAction::Unlink,
+//!     ..UrlFilter::default()
+//! };
+//! assert_eq!(
+//!     filter.apply("See https://shop.io/item?id=7&utm_source=alert and [admin](https://internal.example.com/x)"),
+//!     "See https://shop.io/item?id=7 and admin"
+//! );
+//! ```
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+/// Query parameters added by analytics and ad platforms
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gclsrc", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "_ga",
+    "_gl",
+];
+
+/// Text replacing bare URLs that are unlinked
+const UNLINKED: &str = "[link removed]";
+
+/// Markdown links, HTML anchors and bare URLs, matched in one pass
+static URL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"\[(?P<md_label>[^\]]+)\]\((?P<md_url>https?://[^)\s]+)\)",
+        r#"|(?is:<a\s+href=["'](?P<a_url>[^"']+)["'][^>]*>(?P<a_label>.*?)</a>)"#,
+        r#"|(?P<url>https?://[^\s<>()\[\]"']+)"#,
+    ))
+    .unwrap()
+});
+
+/// What happens to links to denied domains
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeniedUrlAction {
+    /// Remove the link together with its text
+    #[default]
+    Drop,
+    /// Keep the text of the link as plain text; bare URLs become `[link removed]`
+    Unlink,
+}
+
+/// Allow/deny lists and rewrites applied to every URL of a message
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrlFilter {
+    /// Remove `utm_*`, `fbclid`, `gclid` and similar tracking query parameters
+    pub strip_tracking_params: bool,
+    /// If not empty, only links to these domains (and their subdomains) are kept
+    pub allowed_domains: Vec<String>,
+    /// Links to these domains (and their subdomains) are removed
+    pub denied_domains: Vec<String>,
+    /// What happens to links that are not allowed
+    pub denied_action: DeniedUrlAction,
+}
+
+impl UrlFilter {
+    /// Filter and rewrite the Markdown links, HTML anchors and bare URLs of `text`
+    ///
+    /// Returns the text unchanged (borrowed) if no URL needs rewriting.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let filtered = URL_REGEX.replace_all(text, |caps: &Captures| {
+            // Punctuation ending a sentence isn't part of a bare URL
+            let (link, url, label, trailing) = if let Some(url) = caps.name("md_url") {
+                (&caps[0], url.as_str(), caps.name("md_label"), "")
+            } else if let Some(url) = caps.name("a_url") {
+                (&caps[0], url.as_str(), caps.name("a_label"), "")
+            } else {
+                let url = caps["url"].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+                (url, url, None, &caps["url"][url.len()..])
+            };
+
+            let filtered = if !self.is_allowed(url) {
+                match (self.denied_action, label) {
+                    (DeniedUrlAction::Drop, _) => String::new(),
+                    (DeniedUrlAction::Unlink, Some(label)) => label.as_str().to_string(),
+                    (DeniedUrlAction::Unlink, None) => UNLINKED.to_string(),
+                }
+            } else if self.strip_tracking_params {
+                match strip_tracking_params(url) {
+                    Cow::Borrowed(_) => link.to_string(),
+                    Cow::Owned(stripped) => link.replacen(url, &stripped, 1),
+                }
+            } else {
+                link.to_string()
+            };
+            filtered + trailing
+        });
+        match filtered {
+            Cow::Owned(filtered) if filtered == text => Cow::Borrowed(text),
+            filtered => filtered,
+        }
+    }
+
+    /// Whether links to `url` may stay in the message
+    pub fn is_allowed(&self, url: &str) -> bool {
+        let host = host(url);
+        let matches = |domains: &[String]| domains.iter().any(|domain| on_domain(&host, domain));
+        (self.allowed_domains.is_empty() || matches(&self.allowed_domains))
+            && !matches(&self.denied_domains)
+    }
+}
+
+/// Lowercase host of a URL, without user info and port
+fn host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    host.split(':').next().unwrap_or("").to_lowercase()
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn on_domain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.').to_lowercase();
+    host == domain
+        || host
+            .strip_suffix(&domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Remove tracking parameters from the query of `url`
+fn strip_tracking_params(url: &str) -> Cow<'_, str> {
+    let (without_fragment, fragment) = match url.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (url, None),
+    };
+    let Some((base, query)) = without_fragment.split_once('?') else {
+        return Cow::Borrowed(url);
+    };
+
+    let is_tracking = |param: &str| {
+        let name = param.split('=').next().unwrap_or("").to_lowercase();
+        name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+    };
+    if !query.split('&').any(is_tracking) {
+        return Cow::Borrowed(url);
+    }
+
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| !param.is_empty() && !is_tracking(param))
+        .collect();
+    let mut stripped = base.to_string();
+    if !kept.is_empty() {
+        stripped.push('?');
+        stripped.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        stripped.push('#');
+        stripped.push_str(fragment);
+    }
+    Cow::Owned(stripped)
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+
+use crate::configuration::Configuration;
+use crate::error::Error;
+
+/// Default interval between checks of the watched file
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Background watcher that reloads a configuration file when it changes
+///
+/// Every reload applies the file on top of the configuration the watcher
+/// started from (defaults, environment and programmatic settings) and swaps
+/// the result in as a single update, so sends always observe either the old
+/// or the new settings, and settings removed from the file go back to their
+/// base value. Changes made with `configure` while the watcher runs are
+/// replaced on the next reload. The watcher stops when dropped.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    /// Load the file into the global configuration and watch it for changes
+    pub fn watch<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::watch_with_interval(path, DEFAULT_POLL_INTERVAL)
+    }
+
+    /// Same as [`ConfigWatcher::watch`] with a custom polling interval
+    pub fn watch_with_interval<P: AsRef<Path>>(path: P, interval: Duration) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+
+        // Fail early if the initial file is unusable
+        let base = Configuration::get_instance().as_ref().clone();
+        Self::reload(&base, &path)?;
+        let mut last_modified = modified_time(&path);
+
+        let (stop, stop_rx) = mpsc::channel();
+        let watched_path = path.clone();
+        let handle = std::thread::Builder::new()
+            .name("telegrama-config-watcher".to_string())
+            .spawn(move || {
+                // Runs until a stop is requested or the watcher is dropped
+                while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                    let modified = modified_time(&watched_path);
+                    if modified.is_none() || modified == last_modified {
+                        continue;
+                    }
+                    last_modified = modified;
+
+                    match Self::reload(&base, &watched_path) {
+                        Ok(()) => info!(path = %watched_path.display(), "Reloaded configuration"),
+                        Err(e) => error!(error = %e, "Keeping previous configuration"),
+                    }
+                }
+            })
+            .map_err(|e| Error::other(format!("Failed to start configuration watcher: {}", e)))?;
+
+        Ok(ConfigWatcher {
+            path,
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// Path of the watched file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Apply the file to `base` and swap the result in as the global configuration
+    fn reload(base: &Configuration, path: &Path) -> Result<(), Error> {
+        // Parse outside of the global lock so a broken file never blocks sends
+        let mut candidate = base.clone();
+        candidate.apply_file(path)?;
+        candidate.validate()?;
+
+        Configuration::get_instance_mut(|config| *config = candidate);
+        Ok(())
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread up immediately
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Modification time of a file, if it can be read
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,182 @@
+//! Endpoint for updates pushed by Telegram to a webhook.
+//!
+//! [`Webhook`] checks the secret token Telegram sends in the
+//! `X-Telegram-Bot-Api-Secret-Token` header, parses the [`Update`] and passes
+//! it to an [`UpdateHandler`]. [`Webhook::receive`] works with any HTTP server;
+//! with the `axum` feature, [`Webhook::router`] builds a ready-made route.
+//!
+//! ```
+//! use telegrama_rs::update::Update;
+//! use telegrama_rs::webhook::Webhook;
+//!
+//! let webhook = Webhook::new(|update: Update| {
+//!     println!("update {} ({:?})", update.update_id, update.kind());
+//! })
+//! .with_secret_token("s3cr3t");
+//!
+//! let body = br#"{"update_id": 1, "message": {"message_id": 5, "date": 0, "chat": {"id": 7, "type": "private"}}}"#;
+//! let status = webhook.receive(Some("s3cr3t"), body);
+//! assert_eq!(status, 200);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::media::InputFile;
+use crate::secret::SecretString;
+use crate::update::{Update, UpdateHandler};
+
+/// Header carrying the secret token configured with `setWebhook`
+pub const SECRET_TOKEN_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+/// Settings of [`Client::set_webhook`](crate::Client::set_webhook)
+#[derive(Debug, Clone, Default)]
+pub struct WebhookOptions {
+    /// Public key certificate to upload when the endpoint uses a self-signed one
+    pub certificate: Option<InputFile>,
+    /// Fixed IP address to send updates to instead of resolving the URL
+    pub ip_address: Option<String>,
+    /// Maximum simultaneous connections for update delivery (1-100, default 40)
+    pub max_connections: Option<u32>,
+    /// Update types to receive (e.g. `message`, `callback_query`); all but a few if unset
+    pub allowed_updates: Option<Vec<String>>,
+    /// Drop updates that weren't delivered yet
+    pub drop_pending_updates: bool,
+    /// Secret token Telegram sends in the [`SECRET_TOKEN_HEADER`] header
+    pub secret_token: Option<SecretString>,
+}
+
+/// Current webhook status, returned by [`Client::get_webhook_info`](crate::Client::get_webhook_info)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookInfo {
+    /// Webhook URL, empty if no webhook is set
+    pub url: String,
+    /// Whether a custom certificate was uploaded
+    pub has_custom_certificate: bool,
+    /// Number of updates waiting for delivery
+    pub pending_update_count: u64,
+    /// IP address used to send updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<String>,
+    /// Unix time of the most recent delivery error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error_date: Option<i64>,
+    /// Description of the most recent delivery error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error_message: Option<String>,
+    /// Unix time of the most recent error syncing with Telegram's datacenters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_synchronization_error_date: Option<i64>,
+    /// Maximum simultaneous connections for update delivery
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    /// Update types the bot receives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_updates: Option<Vec<String>>,
+}
+
+/// Receives updates pushed by Telegram and hands them to a handler
+///
+/// Clones share the same handler.
+#[derive(Clone)]
+pub struct Webhook {
+    secret_token: Option<SecretString>,
+    handler: Arc<dyn UpdateHandler>,
+}
+
+impl Webhook {
+    /// Create a webhook passing every update to `handler`
+    pub fn new<H: UpdateHandler + 'static>(handler: H) -> Self {
+        Webhook {
+            secret_token: None,
+            handler: Arc::new(handler),
+        }
+    }
+
+    /// Only accept requests carrying this secret token
+    ///
+    /// Use the `secret_token` passed to `setWebhook`, so forged requests from
+    /// anyone who guessed the URL are rejected.
+    pub fn with_secret_token<S: Into<SecretString>>(mut self, secret_token: S) -> Self {
+        self.secret_token = Some(secret_token.into());
+        self
+    }
+
+    /// Whether the value of the secret token header is accepted
+    pub fn verify(&self, secret_token: Option<&str>) -> bool {
+        match (&self.secret_token, secret_token) {
+            (None, _) => true,
+            (Some(expected), Some(received)) => {
+                constant_time_eq(expected.expose_secret().as_bytes(), received.as_bytes())
+            }
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Process a webhook request and return the HTTP status to answer with
+    ///
+    /// `secret_token` is the value of the [`SECRET_TOKEN_HEADER`] header. Returns
+    /// 401 for a wrong secret token, 400 for a body that isn't an update and 200
+    /// once the handler has run. The handler runs on the calling thread, so async
+    /// servers should call this from a blocking task.
+    pub fn receive(&self, secret_token: Option<&str>, body: &[u8]) -> u16 {
+        if !self.verify(secret_token) {
+            warn!("Rejected webhook request with a wrong secret token");
+            return 401;
+        }
+
+        let update: Update = match serde_json::from_slice(body) {
+            Ok(update) => update,
+            Err(e) => {
+                warn!(error = %e, "Rejected webhook request without a valid update");
+                return 400;
+            }
+        };
+
+        debug!(update_id = update.update_id, "Received update");
+        self.handler.handle(update);
+        200
+    }
+
+    /// Build an axum router serving the webhook on `path` (e.g. `/telegram`)
+    ///
+    /// Handlers run on tokio's blocking thread pool, so they can use the
+    /// blocking [`Client`](crate::Client).
+    #[cfg(feature = "axum")]
+    pub fn router<S>(self, path: &str) -> axum::Router<S> {
+        axum::Router::new()
+            .route(path, axum::routing::post(receive_update))
+            .with_state(self)
+    }
+}
+
+/// axum handler forwarding a request to [`Webhook::receive`]
+#[cfg(feature = "axum")]
+async fn receive_update(
+    axum::extract::State(webhook): axum::extract::State<Webhook>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> axum::http::StatusCode {
+    use axum::http::StatusCode;
+
+    let secret_token = headers
+        .get(SECRET_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    match tokio::task::spawn_blocking(move || webhook.receive(secret_token.as_deref(), &body)).await
+    {
+        Ok(status) => StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => {
+            // Answering with an error would make Telegram redeliver the update forever
+            tracing::error!(error = %e, "Update handler panicked");
+            StatusCode::OK
+        }
+    }
+}
+
+/// Compare secrets without leaking the position of the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,193 @@
+//! A [`std::io::Write`] and [`std::fmt::Write`] sink sending to Telegram.
+//!
+//! [`TelegramWriter`] buffers what is written and sends it as a message on
+//! `flush()`, or as soon as the buffer reaches its size limit, so existing code
+//! using `writeln!` can target a chat directly:
+//!
+//! ```no_run
+//! use std::io::Write;
+//! use telegrama_rs::TelegramWriter;
+//!
+//! let mut out = TelegramWriter::new().to("ops");
+//! writeln!(out, "Backup finished")?;
+//! writeln!(out, "{} files, {} MB", 1204, 350)?;
+//! out.flush()?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fmt;
+use std::io;
+use tracing::warn;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::message::OutgoingMessage;
+
+/// Buffer size at which a message is sent without waiting for `flush()`
+///
+/// Below [`MAX_MESSAGE_LENGTH`](crate::formatter::MAX_MESSAGE_LENGTH), leaving
+/// room for the message prefix, suffix and escaping.
+pub const DEFAULT_LIMIT: usize = 3072;
+
+/// Buffered writer sending its contents as Telegram messages
+///
+/// A full buffer is split at the last line break before the limit where there
+/// is one. Whatever is still buffered is sent when the writer is dropped; call
+/// [`TelegramWriter::finish`] to handle errors of that last send.
+pub struct TelegramWriter {
+    client: Client,
+    /// Options of the sent messages, the text is ignored
+    message: OutgoingMessage,
+    limit: usize,
+    buffer: String,
+    /// Trailing bytes of an incomplete UTF-8 character
+    partial: Vec<u8>,
+}
+
+impl TelegramWriter {
+    /// Writer sending to the configured chat with a new [`Client`]
+    pub fn new() -> Self {
+        TelegramWriter {
+            client: Client::new(),
+            message: OutgoingMessage::new(""),
+            limit: DEFAULT_LIMIT,
+            buffer: String::new(),
+            partial: Vec::new(),
+        }
+    }
+
+    /// Send with `client`
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Send to `chat_id` (an ID, `@username` or alias) instead
+    pub fn to<S: Into<String>>(self, chat_id: S) -> Self {
+        self.with_option("chat_id", chat_id)
+    }
+
+    /// Set an option of [`Client::send_message`] for every message
+    pub fn with_option<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.message =
+            std::mem::replace(&mut self.message, OutgoingMessage::new("")).with_option(key, value);
+        self
+    }
+
+    /// Send once the buffer holds `limit` bytes (at least 1)
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    /// Text waiting for the next send
+    pub fn buffered(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Send the buffered text, if any
+    ///
+    /// Text that is only whitespace is discarded. On failure the text stays
+    /// buffered for the next attempt.
+    pub fn send(&mut self) -> Result<(), Error> {
+        if !self.partial.is_empty() {
+            let partial = std::mem::take(&mut self.partial);
+            self.buffer.push_str(&String::from_utf8_lossy(&partial));
+        }
+        if self.buffer.trim().is_empty() {
+            self.buffer.clear();
+            return Ok(());
+        }
+        self.deliver(self.buffer.len())
+    }
+
+    /// Send the buffered text and drop the writer
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.send()
+    }
+
+    /// Append `text`, sending full chunks
+    fn push(&mut self, text: &str) -> Result<(), Error> {
+        self.buffer.push_str(text);
+        while self.buffer.len() >= self.limit {
+            let mut end = self.limit;
+            while !self.buffer.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 {
+                // A single character longer than the limit
+                end = self.buffer.chars().next().map_or(0, char::len_utf8);
+            }
+            if let Some(line_end) = self.buffer[..end].rfind('\n') {
+                end = line_end + 1;
+            }
+            self.deliver(end)?;
+        }
+        Ok(())
+    }
+
+    /// Send the first `end` bytes of the buffer, removing them once sent
+    fn deliver(&mut self, end: usize) -> Result<(), Error> {
+        let text = self.buffer[..end].trim_end_matches('\n');
+        if !text.trim().is_empty() {
+            let mut message = self.message.clone();
+            message.text = text.to_string();
+            message.send_with(&self.client)?;
+        }
+        self.buffer.drain(..end);
+        Ok(())
+    }
+}
+
+impl Default for TelegramWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl io::Write for TelegramWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+        let complete = match std::str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            // A character split across writes, keep its start for the next one
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.partial.len(),
+        };
+        let bytes: Vec<u8> = self.partial.drain(..complete).collect();
+        self.push(&String::from_utf8_lossy(&bytes))
+            .map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send().map_err(io::Error::other)
+    }
+}
+
+impl fmt::Write for TelegramWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s).map_err(|e| {
+            warn!(error = %e, "Failed to send written text to Telegram");
+            fmt::Error
+        })
+    }
+}
+
+impl Drop for TelegramWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.send() {
+            warn!(error = %e, "Failed to send buffered text to Telegram");
+        }
+    }
+}
+
+impl fmt::Debug for TelegramWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TelegramWriter")
+            .field("message", &self.message)
+            .field("limit", &self.limit)
+            .field("buffered", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}